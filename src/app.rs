@@ -1,72 +1,447 @@
+use std::collections::{HashMap, VecDeque};
 use std::sync::{Arc, mpsc};
 use std::thread;
-use std::time::{Duration, Instant};
+use std::time::{Duration, Instant, SystemTime};
 use std::io::Stdout;
 
-use crossterm::event::{self, Event, KeyCode, KeyModifiers};
+use crossterm::event::{self, Event, KeyCode, KeyEvent, KeyModifiers};
 use ratatui::{Terminal, backend::CrosstermBackend};
 
-use crate::provider::{BoxedProvider, Package, SearchResult};
+use fex::journal::{JournalEntry, read_journal};
+use fex::manifest::ExportFormat;
+use fex::translate::Translations;
+use fex::util::{CancelToken, sort_by_relevance, with_batch_sink, with_cancel_token};
+use fex::query::Query;
+use fex::sort::SortMode;
+use fex::{BoxedProvider, Index, InstallPlan, Package, Provider, SearchError, SearchResult};
+pub use fex::journal::Operation;
+use crate::terminal::TerminalPane;
 use crate::ui;
 
 pub enum SearchState {
     Idle,
     Searching,
+    /// Some results have arrived; the search is still running.
+    Partial,
     Done,
+    Cancelled,
 }
 
-pub enum AppAction {
-    Quit,
-    Install,
+/// Progress of the offline package index.
+pub enum IndexState {
+    /// Nothing is being built; `App::index` holds the current index, if any.
+    Idle,
+    Refreshing,
+    Failed,
+}
+
+/// Message sent back from a search thread.
+enum SearchOutcome {
+    /// Packages parsed so far by a search that is still running.
+    Batch(Vec<Package>),
+    Finished(SearchResult),
+    /// Superseded or aborted before it completed; its child processes were killed.
+    Cancelled,
+}
+
+/// How many recent queries keep their results.
+const RESULT_CACHE_SIZE: usize = 32;
+
+/// Least-recently-used cache of query → results, so going back to an
+/// earlier query (e.g. by backspacing) doesn't search again.
+#[derive(Default)]
+struct ResultCache {
+    /// Most recently used last.
+    entries: VecDeque<(String, Vec<Package>)>,
+}
+
+impl ResultCache {
+    fn get(&mut self, query: &str) -> Option<Vec<Package>> {
+        let idx = self.entries.iter().position(|(q, _)| q == query)?;
+        let entry = self.entries.remove(idx)?;
+        let packages = entry.1.clone();
+        self.entries.push_back(entry);
+        Some(packages)
+    }
+
+    fn insert(&mut self, query: String, packages: Vec<Package>) {
+        self.remove(&query);
+        if self.entries.len() >= RESULT_CACHE_SIZE {
+            self.entries.pop_front();
+        }
+        self.entries.push_back((query, packages));
+    }
+
+    fn remove(&mut self, query: &str) {
+        self.entries.retain(|(q, _)| q != query);
+    }
+
+    fn clear(&mut self) {
+        self.entries.clear();
+    }
+}
+
+/// Dry run of an install shown in the confirmation dialog.
+pub enum PlanState {
+    Resolving,
+    Ready(InstallPlan),
+    Failed(SearchError),
+}
+
+/// An install or removal waiting for the user to confirm it.
+pub struct Confirmation {
+    pub op: Operation,
+    pub pkg: Package,
+    /// The command to run, possibly edited by the user.
+    pub command: String,
+    /// What the provider would run.
+    pub original: String,
+    /// Cursor position in `command`, in chars, while it is being edited.
+    pub editing: Option<usize>,
+    /// False if the provider installs in-process and `command` only
+    /// describes what it does.
+    pub editable: bool,
+    /// `None` for removals and providers without dry runs.
+    pub plan: Option<PlanState>,
+}
+
+/// An install or removal running in the embedded terminal.
+pub struct RunningOperation {
+    pub op: Operation,
+    pub pkg: Package,
+    pub pane: TerminalPane,
+    /// What is running, for the journal.
+    command: String,
+    started: SystemTime,
+}
+
+/// Past installs and removals from the journal, newest first.
+pub struct HistoryView {
+    pub entries: Vec<JournalEntry>,
+    pub selected: usize,
+    pub scroll_offset: usize,
+    /// Why the last undo couldn't be started.
+    pub message: Option<String>,
+}
+
+/// Which way a dependency tree goes from its root.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum TreeDirection {
+    /// What the package depends on.
+    Dependencies,
+    /// What depends on the package.
+    Dependents,
+}
+
+impl TreeDirection {
+    pub fn label(self) -> &'static str {
+        match self {
+            TreeDirection::Dependencies => "Depends on",
+            TreeDirection::Dependents => "Required by",
+        }
+    }
+
+    fn flip(self) -> TreeDirection {
+        match self {
+            TreeDirection::Dependencies => TreeDirection::Dependents,
+            TreeDirection::Dependents => TreeDirection::Dependencies,
+        }
+    }
+}
+
+/// A package in a dependency tree.
+pub struct TreeNode {
+    pub pkg: Package,
+    pub depth: usize,
+    /// Whether its children are shown, or being listed to be shown.
+    pub expanded: bool,
+}
+
+/// The dependencies or dependents of a package, listed a level at a time as
+/// nodes are expanded.
+pub struct DependencyTree {
+    pub direction: TreeDirection,
+    /// The shown nodes in display order, the root first and each expanded
+    /// node followed by its children one level deeper.
+    pub nodes: Vec<TreeNode>,
+    /// The children of every package listed so far, by name, or why listing
+    /// them failed.
+    pub children: HashMap<String, Result<Vec<Package>, String>>,
+    pub selected: usize,
+    pub scroll_offset: usize,
+    /// Why the last key did nothing.
+    pub message: Option<String>,
 }
 
+impl DependencyTree {
+    fn new(root: Package, direction: TreeDirection) -> DependencyTree {
+        DependencyTree {
+            direction,
+            nodes: vec![TreeNode { pkg: root, depth: 0, expanded: true }],
+            children: HashMap::new(),
+            selected: 0,
+            scroll_offset: 0,
+            message: None,
+        }
+    }
+
+    /// Expand the selected node. Returns its package if the children still
+    /// have to be listed.
+    fn expand(&mut self) -> Option<Package> {
+        let node = self.nodes.get_mut(self.selected)?;
+        node.expanded = true;
+        let name = node.pkg.name.clone();
+        if self.children.contains_key(&name) {
+            self.show_children(&name);
+            return None;
+        }
+        Some(self.nodes[self.selected].pkg.clone())
+    }
+
+    /// Collapse the selected node, or select its parent if it already is.
+    fn collapse(&mut self) {
+        let Some(node) = self.nodes.get_mut(self.selected) else { return };
+        let depth = node.depth;
+        if !node.expanded {
+            if let Some(parent) = self.nodes[..self.selected].iter().rposition(|n| n.depth < depth)
+            {
+                self.selected = parent;
+            }
+            return;
+        }
+        node.expanded = false;
+        let start = self.selected + 1;
+        let end = self.nodes[start..]
+            .iter()
+            .position(|n| n.depth <= depth)
+            .map_or(self.nodes.len(), |i| start + i);
+        self.nodes.drain(start..end);
+    }
+
+    /// Show the listed children of every expanded `name` node that doesn't
+    /// show them yet.
+    fn show_children(&mut self, name: &str) {
+        let Some(Ok(children)) = self.children.get(name) else { return };
+        let mut idx = 0;
+        while idx < self.nodes.len() {
+            let node = &self.nodes[idx];
+            let shown = self.nodes.get(idx + 1).is_some_and(|next| next.depth > node.depth);
+            if node.pkg.name == name && node.expanded && !shown {
+                let depth = node.depth + 1;
+                let nodes = children
+                    .iter()
+                    .map(|pkg| TreeNode { pkg: pkg.clone(), depth, expanded: false });
+                self.nodes.splice(idx + 1..idx + 1, nodes);
+            }
+            idx += 1;
+        }
+    }
+}
+
+/// The files of a package, narrowed down by a filter typed in place.
+pub struct FileList {
+    pub pkg: Package,
+    /// `None` while the files are being listed.
+    pub files: Option<Result<Vec<String>, String>>,
+    /// `files` in lowercase, made once when they arrive rather than on every
+    /// key press and frame.
+    lowercase: Vec<String>,
+    /// Only paths containing this, ignoring case, are shown.
+    pub filter: String,
+    pub selected: usize,
+    pub scroll_offset: usize,
+}
+
+impl FileList {
+    /// The files that pass the filter.
+    pub fn visible(&self) -> Vec<&str> {
+        let Some(Ok(files)) = &self.files else { return Vec::new() };
+        let filter = self.filter.to_lowercase();
+        files
+            .iter()
+            .zip(&self.lowercase)
+            .filter(|(_, lower)| lower.contains(&filter))
+            .map(|(file, _)| file.as_str())
+            .collect()
+    }
+}
+
+/// What `Provider::list_files` returned.
+type FileListing = Option<Result<Vec<String>, SearchError>>;
+
+/// What `Provider::dependencies` or `reverse_dependencies` returned.
+type DependencyListing = Option<Result<Vec<Package>, SearchError>>;
+
+/// What `Provider::list_installed` returned, for an export.
+type InstalledListing = Option<Result<Vec<Package>, SearchError>>;
+
 pub struct App {
     pub query: String,
+    /// `query` split into the text searched for and the filters applied to the results.
+    pub parsed: Query,
+    /// The results that pass the filters, in `sort_mode` order.
     pub packages: Vec<Package>,
+    /// Saved across sessions.
+    pub sort_mode: SortMode,
     pub selected: usize,
     pub scroll_offset: usize,
     pub provider: Arc<BoxedProvider>,
     pub search_state: SearchState,
     pub status_message: String,
+    /// Why the last search failed, shown with a hint in the status bar.
+    pub search_error: Option<SearchError>,
+    /// Searches running longer than this are killed; zero disables the limit.
+    pub search_timeout: Duration,
+    /// Offline index searched instead of the provider, when it has one.
+    pub index: Option<Index>,
+    pub index_state: IndexState,
+    /// Shown in place of the results while it runs and until dismissed.
+    pub operation: Option<RunningOperation>,
+    /// Shown as a dialog over the results until answered.
+    pub confirmation: Option<Confirmation>,
+    /// Shown in place of the results while open.
+    pub history: Option<HistoryView>,
+    /// Shown in place of the results while open.
+    pub tree: Option<DependencyTree>,
+    /// Shown in place of the results while open.
+    pub files: Option<FileList>,
+    /// Packages picked with Tab, for exporting.
+    pub marked: Vec<Package>,
+    /// The format selected in the export dialog, while it is open.
+    pub export: Option<usize>,
+    /// Whether to ask before installing or removing, see `fex::config::Config`.
+    pub confirm_operations: bool,
+    pub translations: Translations,
+    /// What the searched name is called here, if it is another package manager's name.
+    pub hint: Option<String>,
+    /// Whether the search text is a command to find the package of (Ctrl+F),
+    /// like a path in the query is (see `Query::file`).
+    pub file_mode: bool,
     last_input: Instant,
     query_changed: bool,
     generation: u64,
-    result_rx: mpsc::Receiver<(u64, SearchResult)>,
-    result_tx: mpsc::Sender<(u64, SearchResult)>,
+    active_search: Option<CancelToken>,
+    /// Whether the running search has delivered a batch yet.
+    streamed: bool,
+    /// The query the running (or last) search was started for.
+    search_query: String,
+    /// Whether that search was for a file, with `Provider::search_file`.
+    file_search: bool,
+    /// Everything the running (or last) search returned, before filtering.
+    results: Vec<Package>,
+    result_cache: ResultCache,
+    result_rx: mpsc::Receiver<(u64, SearchOutcome)>,
+    result_tx: mpsc::Sender<(u64, SearchOutcome)>,
+    index_rx: mpsc::Receiver<Option<Result<Index, SearchError>>>,
+    index_tx: mpsc::Sender<Option<Result<Index, SearchError>>>,
+    plan_rx: mpsc::Receiver<(Package, Option<Result<InstallPlan, SearchError>>)>,
+    plan_tx: mpsc::Sender<(Package, Option<Result<InstallPlan, SearchError>>)>,
+    export_rx: mpsc::Receiver<(ExportFormat, InstalledListing)>,
+    export_tx: mpsc::Sender<(ExportFormat, InstalledListing)>,
+    tree_rx: mpsc::Receiver<(TreeDirection, String, DependencyListing)>,
+    tree_tx: mpsc::Sender<(TreeDirection, String, DependencyListing)>,
+    files_rx: mpsc::Receiver<(String, FileListing)>,
+    files_tx: mpsc::Sender<(String, FileListing)>,
 }
 
 impl App {
     pub fn new(provider: BoxedProvider) -> Self {
         let (tx, rx) = mpsc::channel();
-        App {
+        let (index_tx, index_rx) = mpsc::channel();
+        let (plan_tx, plan_rx) = mpsc::channel();
+        let (export_tx, export_rx) = mpsc::channel();
+        let (tree_tx, tree_rx) = mpsc::channel();
+        let (files_tx, files_rx) = mpsc::channel();
+        let search_timeout = provider.search_timeout();
+        let index = Index::load(provider.name());
+        let refresh = index.as_ref().is_none_or(Index::is_stale);
+        let mut app = App {
             query: String::new(),
+            parsed: Query::default(),
             packages: Vec::new(),
+            sort_mode: SortMode::load(),
             selected: 0,
             scroll_offset: 0,
             provider: Arc::new(provider),
             search_state: SearchState::Idle,
             status_message: "Start typing to search.".to_string(),
+            search_error: None,
+            search_timeout,
+            index,
+            index_state: IndexState::Idle,
+            operation: None,
+            confirmation: None,
+            history: None,
+            tree: None,
+            files: None,
+            marked: Vec::new(),
+            export: None,
+            confirm_operations: true,
+            translations: Translations::default(),
+            hint: None,
+            file_mode: false,
             last_input: Instant::now(),
             query_changed: false,
             generation: 0,
+            active_search: None,
+            streamed: false,
+            search_query: String::new(),
+            file_search: false,
+            results: Vec::new(),
+            result_cache: ResultCache::default(),
             result_rx: rx,
             result_tx: tx,
+            index_rx,
+            index_tx,
+            plan_rx,
+            plan_tx,
+            export_rx,
+            export_tx,
+            tree_rx,
+            tree_tx,
+            files_rx,
+            files_tx,
+        };
+        if refresh {
+            app.refresh_index();
         }
+        app
     }
 
-    /// Main event loop. Returns when the user quits or selects a package to install.
+    /// Main event loop. Returns when the user quits.
     pub fn run(
         &mut self,
         terminal: &mut Terminal<CrosstermBackend<Stdout>>,
-    ) -> std::io::Result<AppAction> {
+    ) -> std::io::Result<()> {
         loop {
             terminal.draw(|f| ui::render(f, self))?;
 
-            if event::poll(Duration::from_millis(16))? {
-                if let Event::Key(key) = event::read()? {
-                    if let Some(action) = self.handle_key(key.code, key.modifiers) {
-                        return Ok(action);
-                    }
+            if event::poll(Duration::from_millis(16))?
+                && let Event::Key(key) = event::read()?
+            {
+                let quit = if self.operation.is_some() {
+                    self.handle_operation_key(key);
+                    false
+                } else if self.confirmation.is_some() {
+                    self.handle_confirmation_key(key.code);
+                    false
+                } else if self.export.is_some() {
+                    self.handle_export_key(key.code);
+                    false
+                } else if self.history.is_some() {
+                    self.handle_history_key(key.code);
+                    false
+                } else if self.tree.is_some() {
+                    self.handle_tree_key(key.code, key.modifiers);
+                    false
+                } else if self.files.is_some() {
+                    self.handle_files_key(key.code, key.modifiers);
+                    false
+                } else {
+                    self.handle_key(key.code, key.modifiers)
+                };
+                if quit {
+                    return Ok(());
                 }
             }
 
@@ -74,29 +449,425 @@ impl App {
         }
     }
 
-    fn handle_key(&mut self, code: KeyCode, mods: KeyModifiers) -> Option<AppAction> {
+    /// Keys go to the running command; once it has exited, Enter or Escape
+    /// returns to the results.
+    fn handle_operation_key(&mut self, key: KeyEvent) {
+        let page = terminal_pane_size().0 as isize;
+        let Some(running) = &mut self.operation else { return };
+        match key.code {
+            KeyCode::PageUp if key.modifiers.contains(KeyModifiers::SHIFT) => {
+                running.pane.scroll(page);
+            }
+            KeyCode::PageDown if key.modifiers.contains(KeyModifiers::SHIFT) => {
+                running.pane.scroll(-page);
+            }
+            KeyCode::Enter | KeyCode::Esc | KeyCode::Char('q') if running.pane.exit.is_some() => {
+                self.finish_operation();
+            }
+            _ if running.pane.exit.is_none() => running.pane.send_key(key),
+            _ => {}
+        }
+    }
+
+    /// Enter runs the command, Escape cancels and `e` edits it if it is
+    /// `editable`. While editing,
+    /// Enter runs the edited command and Escape stops editing.
+    fn handle_confirmation_key(&mut self, code: KeyCode) {
+        let Some(confirm) = &mut self.confirmation else { return };
+        let Some(cursor) = confirm.editing else {
+            match code {
+                KeyCode::Enter | KeyCode::Char('y') => {
+                    if let Some(confirm) = self.confirmation.take() {
+                        let edited = confirm.command != confirm.original;
+                        self.run_operation(confirm.op, confirm.pkg, confirm.command, edited);
+                    }
+                }
+                KeyCode::Esc | KeyCode::Char('n') | KeyCode::Char('q') => {
+                    self.confirmation = None;
+                    self.status_message = "Cancelled.".to_string();
+                }
+                KeyCode::Char('e') if confirm.editable => {
+                    confirm.editing = Some(confirm.command.chars().count());
+                }
+                _ => {}
+            }
+            return;
+        };
+
+        let len = confirm.command.chars().count();
+        let byte = |s: &str, idx: usize| s.char_indices().nth(idx).map_or(s.len(), |(i, _)| i);
+        match code {
+            KeyCode::Enter | KeyCode::Esc => {
+                confirm.editing = None;
+                if code == KeyCode::Enter {
+                    self.handle_confirmation_key(KeyCode::Enter);
+                }
+            }
+            KeyCode::Char(c) => {
+                confirm.command.insert(byte(&confirm.command, cursor), c);
+                confirm.editing = Some(cursor + 1);
+            }
+            KeyCode::Backspace if cursor > 0 => {
+                confirm.command.remove(byte(&confirm.command, cursor - 1));
+                confirm.editing = Some(cursor - 1);
+            }
+            KeyCode::Delete if cursor < len => {
+                confirm.command.remove(byte(&confirm.command, cursor));
+            }
+            KeyCode::Left => confirm.editing = Some(cursor.saturating_sub(1)),
+            KeyCode::Right => confirm.editing = Some((cursor + 1).min(len)),
+            KeyCode::Home => confirm.editing = Some(0),
+            KeyCode::End => confirm.editing = Some(len),
+            _ => {}
+        }
+    }
+
+    /// Arrows pick a format, Enter exports and Escape cancels.
+    fn handle_export_key(&mut self, code: KeyCode) {
+        let Some(selected) = &mut self.export else { return };
+        match code {
+            KeyCode::Up => *selected = selected.saturating_sub(1),
+            KeyCode::Down => *selected = (*selected + 1).min(ExportFormat::ALL.len() - 1),
+            KeyCode::Enter => {
+                let format = ExportFormat::ALL[*selected];
+                self.export = None;
+                self.start_export(format);
+            }
+            KeyCode::Esc | KeyCode::Char('q') => self.export = None,
+            _ => {}
+        }
+    }
+
+    /// Export the marked packages, or list the installed ones in the
+    /// background and export those.
+    fn start_export(&mut self, format: ExportFormat) {
+        if let Some(providers) = format.providers()
+            && !providers.contains(&self.provider.name())
+        {
+            self.status_message = format!(
+                "The {format} format only takes {} packages, export as fex instead.",
+                providers.join("/")
+            );
+            return;
+        }
+        if !self.marked.is_empty() {
+            let marked = std::mem::take(&mut self.marked);
+            self.write_export(format, &marked);
+            return;
+        }
+        self.status_message = "Listing installed packages...".to_string();
+        let tx = self.export_tx.clone();
+        let provider = Arc::clone(&self.provider);
+        thread::spawn(move || {
+            tx.send((format, provider.list_installed())).ok();
+        });
+    }
+
+    /// Write `packages` to the format's usual file name in the current
+    /// directory, never replacing an existing file.
+    fn write_export(&mut self, format: ExportFormat, packages: &[Package]) {
+        let path = std::path::Path::new(format.file_name());
+        let text = format.render(self.provider.name(), packages);
+        let written = std::fs::OpenOptions::new()
+            .write(true)
+            .create_new(true)
+            .open(path)
+            .and_then(|mut file| std::io::Write::write_all(&mut file, text.as_bytes()));
+        let provider = self.provider.name();
+        let exported = packages.iter().filter(|pkg| format.includes(provider, pkg)).count();
+        let noun = if exported == 1 { "package" } else { "packages" };
+        self.status_message = match written {
+            Ok(()) => format!(
+                "Exported {exported} {noun} to {}, install with: {}",
+                path.display(),
+                format.install_hint(format.file_name())
+            ),
+            Err(e) => format!("Error: could not write {}: {e}", path.display()),
+        };
+    }
+
+    /// Whether `pkg` is marked for export.
+    pub fn is_marked(&self, pkg: &Package) -> bool {
+        self.marked.iter().any(|m| m.name == pkg.name && m.source == pkg.source)
+    }
+
+    /// Arrows move through the entries, `u` undoes the selected one and
+    /// Escape or F2 closes the view.
+    fn handle_history_key(&mut self, code: KeyCode) {
+        let page = self.get_visible_count().max(1);
+        let Some(history) = &mut self.history else { return };
+        let last = history.entries.len().saturating_sub(1);
+        history.selected = match code {
+            KeyCode::Up => history.selected.saturating_sub(1),
+            KeyCode::Down => (history.selected + 1).min(last),
+            KeyCode::PageUp => history.selected.saturating_sub(page),
+            KeyCode::PageDown => (history.selected + page).min(last),
+            KeyCode::Home => 0,
+            KeyCode::End => last,
+            KeyCode::Esc | KeyCode::F(2) | KeyCode::Char('q') => {
+                self.history = None;
+                return;
+            }
+            KeyCode::Char('u') => {
+                self.undo_history_entry();
+                return;
+            }
+            _ => return,
+        };
+        // One line per entry, inside a bordered block
+        let visible = (page * 2).saturating_sub(2).max(1);
+        if history.selected < history.scroll_offset {
+            history.scroll_offset = history.selected;
+        } else if history.selected >= history.scroll_offset + visible {
+            history.scroll_offset = history.selected + 1 - visible;
+        }
+    }
+
+    /// Remove the package a history entry installed, or reinstall the one it removed.
+    fn undo_history_entry(&mut self) {
+        let Some(history) = &mut self.history else { return };
+        let Some(entry) = history.entries.get(history.selected) else { return };
+        if !entry.succeeded() {
+            history.message = Some("That operation failed; there is nothing to undo.".into());
+            return;
+        }
+        if entry.provider != self.provider.name() {
+            history.message = Some(format!("Run fex -p {} to undo that.", entry.provider));
+            return;
+        }
+        let (op, pkg) = (entry.operation.inverse(), entry.package());
+        self.history = None;
+        self.start_operation(op, pkg);
+    }
+
+    /// Show the dependency tree of `pkg`, listing its first level.
+    fn open_tree(&mut self, pkg: Package, direction: TreeDirection) {
+        let mut tree = DependencyTree::new(pkg, direction);
+        if let Some(pkg) = tree.expand() {
+            self.list_children(direction, pkg);
+        }
+        self.tree = Some(tree);
+    }
+
+    /// List the dependencies or dependents of `pkg` in the background.
+    fn list_children(&self, direction: TreeDirection, pkg: Package) {
+        let tx = self.tree_tx.clone();
+        let provider = Arc::clone(&self.provider);
+        thread::spawn(move || {
+            let listed = match direction {
+                TreeDirection::Dependencies => provider.dependencies(&pkg),
+                TreeDirection::Dependents => provider.reverse_dependencies(&pkg),
+            };
+            tx.send((direction, pkg.name, listed)).ok();
+        });
+    }
+
+    /// Arrows move through the tree and expand or collapse nodes, Enter
+    /// searches for the selected package, `i` installs it, Tab switches
+    /// between dependencies and dependents and Escape closes the view.
+    fn handle_tree_key(&mut self, code: KeyCode, mods: KeyModifiers) {
+        let page = self.get_visible_count().max(1);
+        let Some(tree) = &mut self.tree else { return };
+        tree.message = None;
+        let last = tree.nodes.len().saturating_sub(1);
+        match code {
+            KeyCode::Up => tree.selected = tree.selected.saturating_sub(1),
+            KeyCode::Down => tree.selected = (tree.selected + 1).min(last),
+            KeyCode::PageUp => tree.selected = tree.selected.saturating_sub(page),
+            KeyCode::PageDown => tree.selected = (tree.selected + page).min(last),
+            KeyCode::Home => tree.selected = 0,
+            KeyCode::End => tree.selected = last,
+            KeyCode::Right | KeyCode::Char(' ')
+                if tree.nodes.get(tree.selected).is_some_and(|n| !n.expanded) =>
+            {
+                if let Some(pkg) = tree.expand() {
+                    let direction = tree.direction;
+                    self.list_children(direction, pkg);
+                }
+            }
+            KeyCode::Left | KeyCode::Char(' ') => tree.collapse(),
+            KeyCode::Tab => {
+                let root = tree.nodes[0].pkg.clone();
+                let direction = tree.direction.flip();
+                self.open_tree(root, direction);
+            }
+            KeyCode::Enter => {
+                let Some(node) = tree.nodes.get(tree.selected) else { return };
+                self.query = node.pkg.name.clone();
+                self.tree = None;
+                self.query_changed = false;
+                self.start_search();
+            }
+            KeyCode::Char('i') => {
+                let Some(node) = tree.nodes.get(tree.selected) else { return };
+                if node.pkg.installed {
+                    tree.message = Some(format!("{} is already installed.", node.pkg.name));
+                } else {
+                    let pkg = node.pkg.clone();
+                    self.start_operation(Operation::Install, pkg);
+                }
+            }
+            KeyCode::Char('d') if mods.contains(KeyModifiers::CONTROL) => self.tree = None,
+            KeyCode::Esc | KeyCode::Char('q') => self.tree = None,
+            _ => {}
+        }
+        let Some(tree) = &mut self.tree else { return };
+        // One line per node, inside a bordered block
+        let visible = (page * 2).saturating_sub(2).max(1);
+        if tree.selected < tree.scroll_offset {
+            tree.scroll_offset = tree.selected;
+        } else if tree.selected >= tree.scroll_offset + visible {
+            tree.scroll_offset = tree.selected + 1 - visible;
+        }
+    }
+
+    /// Show the files of `pkg`, listing them in the background.
+    fn open_files(&mut self, pkg: Package) {
+        let tx = self.files_tx.clone();
+        let provider = Arc::clone(&self.provider);
+        let listed = pkg.clone();
+        thread::spawn(move || {
+            tx.send((listed.name.clone(), provider.list_files(&listed))).ok();
+        });
+        self.files = Some(FileList {
+            pkg,
+            files: None,
+            lowercase: Vec::new(),
+            filter: String::new(),
+            selected: 0,
+            scroll_offset: 0,
+        });
+    }
+
+    /// Typing filters the paths and arrows move through them. Escape clears
+    /// the filter, or closes the view if there is none.
+    fn handle_files_key(&mut self, code: KeyCode, mods: KeyModifiers) {
+        let page = self.get_visible_count().max(1);
+        let Some(list) = &mut self.files else { return };
+        let last = list.visible().len().saturating_sub(1);
+        match code {
+            KeyCode::Char('l') if mods.contains(KeyModifiers::CONTROL) => self.files = None,
+            KeyCode::Esc if list.filter.is_empty() => self.files = None,
+            KeyCode::Esc => {
+                list.filter.clear();
+                list.selected = 0;
+            }
+            KeyCode::Backspace => {
+                list.filter.pop();
+                list.selected = 0;
+            }
+            KeyCode::Char(c) if !mods.intersects(KeyModifiers::CONTROL | KeyModifiers::ALT) => {
+                list.filter.push(c);
+                list.selected = 0;
+            }
+            KeyCode::Up => list.selected = list.selected.saturating_sub(1),
+            KeyCode::Down => list.selected = (list.selected + 1).min(last),
+            KeyCode::PageUp => list.selected = list.selected.saturating_sub(page),
+            KeyCode::PageDown => list.selected = (list.selected + page).min(last),
+            KeyCode::Home => list.selected = 0,
+            KeyCode::End => list.selected = last,
+            _ => {}
+        }
+        let Some(list) = &mut self.files else { return };
+        // One line per path below the filter, inside a bordered block
+        let visible = (page * 2).saturating_sub(3).max(1);
+        if list.selected < list.scroll_offset {
+            list.scroll_offset = list.selected;
+        } else if list.selected >= list.scroll_offset + visible {
+            list.scroll_offset = list.selected + 1 - visible;
+        }
+    }
+
+    /// Returns true when the user asked to quit.
+    fn handle_key(&mut self, code: KeyCode, mods: KeyModifiers) -> bool {
         let ctrl = mods.contains(KeyModifiers::CONTROL);
         let no_meta = !mods.contains(KeyModifiers::CONTROL) && !mods.contains(KeyModifiers::ALT);
 
         match code {
             KeyCode::Char('c') | KeyCode::Char('q') | KeyCode::Char('x') if ctrl => {
-                return Some(AppAction::Quit);
+                return true;
+            }
+
+            KeyCode::Char('r') if ctrl && !self.query.is_empty() => {
+                self.query_changed = false;
+                self.cancel_search();
+                let parsed = Query::parse(&self.query);
+                let key = match self.file_target(&parsed) {
+                    Some(path) => cache_key(&path, true),
+                    None => parsed.text,
+                };
+                self.result_cache.remove(&key);
+                self.start_search();
+            }
+
+            KeyCode::Char('s') if ctrl => {
+                let results = &self.results;
+                self.sort_mode = self.sort_mode.next(|mode| mode.applies_to(results));
+                // Not worth interrupting the session over
+                self.sort_mode.save().ok();
+                let keep = self.selected_key();
+                self.apply_filters(keep);
+            }
+
+            KeyCode::F(5) => self.refresh_index(),
+
+            KeyCode::Char('f') if ctrl => {
+                self.file_mode = !self.file_mode;
+                if !self.query.is_empty() {
+                    self.query_changed = false;
+                    self.start_search();
+                }
+            }
+
+            KeyCode::Char('e') if ctrl => self.export = Some(0),
+
+            KeyCode::Char('l') if ctrl && self.selected < self.packages.len() => {
+                self.open_files(self.packages[self.selected].clone());
+            }
+
+            KeyCode::Char('d') if ctrl && self.selected < self.packages.len() => {
+                self.open_tree(self.packages[self.selected].clone(), TreeDirection::Dependencies);
+            }
+
+            KeyCode::Tab if self.selected < self.packages.len() => {
+                let pkg = &self.packages[self.selected];
+                if self.is_marked(pkg) {
+                    let (name, source) = (&pkg.name, &pkg.source);
+                    self.marked.retain(|m| m.name != *name || m.source != *source);
+                } else {
+                    self.marked.push(pkg.clone());
+                }
+                self.navigate(1);
+            }
+
+            KeyCode::F(2) => {
+                let mut entries = read_journal();
+                entries.reverse();
+                self.history =
+                    Some(HistoryView { entries, selected: 0, scroll_offset: 0, message: None });
             }
 
             KeyCode::Esc => {
                 self.query.clear();
-                self.packages.clear();
-                self.selected = 0;
-                self.scroll_offset = 0;
-                self.status_message = "Start typing to search.".to_string();
-                self.search_state = SearchState::Idle;
                 self.query_changed = false;
-                self.generation += 1; // invalidate any in-flight search
+                self.clear_results();
             }
 
-            KeyCode::Enter => {
-                if !self.packages.is_empty() && self.selected < self.packages.len() {
-                    return Some(AppAction::Install);
+            KeyCode::Enter
+                if !self.packages.is_empty() && self.selected < self.packages.len() =>
+            {
+                self.start_operation(Operation::Install, self.packages[self.selected].clone());
+            }
+
+            KeyCode::Delete if self.selected < self.packages.len() => {
+                let pkg = &self.packages[self.selected];
+                if !pkg.installed {
+                    self.status_message = format!("{} is not installed.", pkg.name);
+                } else if self.provider.remove_command(pkg).is_none() {
+                    self.status_message =
+                        format!("{} does not support removing packages.", self.provider.name());
+                } else {
+                    self.start_operation(Operation::Remove, pkg.clone());
                 }
             }
 
@@ -117,24 +888,21 @@ impl App {
                 self.scroll_offset = 0;
             }
 
-            KeyCode::End => {
-                if !self.packages.is_empty() {
-                    self.selected = self.packages.len() - 1;
-                    let visible = self.get_visible_count().max(1);
-                    self.scroll_offset = self.packages.len().saturating_sub(visible);
-                    self.adjust_scroll();
-                }
+            KeyCode::End if !self.packages.is_empty() => {
+                self.selected = self.packages.len() - 1;
+                let visible = self.get_visible_count().max(1);
+                self.scroll_offset = self.packages.len().saturating_sub(visible);
+                self.adjust_scroll();
             }
 
-            KeyCode::Backspace => {
+            KeyCode::Backspace if !self.query.is_empty() => {
+                self.query.pop();
+                self.query_changed = true;
+                self.last_input = Instant::now();
                 if !self.query.is_empty() {
-                    self.query.pop();
-                    self.query_changed = true;
-                    self.last_input = Instant::now();
-                    if !self.query.is_empty() {
-                        self.search_state = SearchState::Searching;
-                        self.status_message = "Searching...".to_string();
-                    }
+                    self.search_error = None;
+                    self.search_state = SearchState::Searching;
+                    self.status_message = "Searching...".to_string();
                 }
             }
 
@@ -142,6 +910,7 @@ impl App {
                 self.query.push(c);
                 self.query_changed = true;
                 self.last_input = Instant::now();
+                self.search_error = None;
                 self.search_state = SearchState::Searching;
                 self.status_message = "Searching...".to_string();
             }
@@ -149,7 +918,7 @@ impl App {
             _ => {}
         }
 
-        None
+        false
     }
 
     fn navigate(&mut self, delta: i32) {
@@ -179,55 +948,556 @@ impl App {
         results_height / 2 // 2 lines per package
     }
 
+    /// Kill the running search, if any. Returns whether one was running.
+    fn cancel_search(&mut self) -> bool {
+        match self.active_search.take() {
+            Some(token) => {
+                token.cancel();
+                true
+            }
+            None => false,
+        }
+    }
+
+    /// Back to the idle prompt, noting whether a running search was cut short.
+    fn reset_search_state(&mut self) {
+        self.search_error = None;
+        if self.cancel_search() {
+            self.status_message = "Search cancelled.".to_string();
+            self.search_state = SearchState::Cancelled;
+        } else {
+            self.status_message = "Start typing to search.".to_string();
+            self.search_state = SearchState::Idle;
+        }
+    }
+
     fn tick(&mut self) {
-        // Drain the results channel, accept only the latest generation
-        while let Ok((search_gen, result)) = self.result_rx.try_recv() {
-            if search_gen == self.generation {
-                self.packages = result.packages;
-                self.selected = 0;
-                self.scroll_offset = 0;
-                if let Some(err) = result.error {
-                    self.status_message = format!("Error: {err}");
-                } else if self.packages.is_empty() {
-                    self.status_message = "No results found.".to_string();
-                } else {
-                    let n = self.packages.len();
+        if let Some(running) = &mut self.operation {
+            let (rows, cols) = terminal_pane_size();
+            running.pane.resize(rows, cols);
+            let was_running = running.pane.exit.is_none();
+            if running.pane.poll() && was_running {
+                let exit_code = running.pane.exit_code;
+                if let Err(e) = self.journal_operation(exit_code) {
+                    self.status_message = format!("Error: could not write the journal: {e}");
+                }
+            }
+        }
+
+        while let Ok((format, listed)) = self.export_rx.try_recv() {
+            match listed {
+                Some(Ok(packages)) => self.write_export(format, &packages),
+                Some(Err(e)) => self.status_message = format!("Error: {e}"),
+                None => {
                     self.status_message = format!(
-                        "Found {n} result{}.",
-                        if n == 1 { "" } else { "s" }
+                        "Error: {} can't list installed packages; mark some with Tab",
+                        self.provider.name()
                     );
                 }
-                self.search_state = SearchState::Done;
+            }
+        }
+
+        while let Ok((name, listed)) = self.files_rx.try_recv() {
+            // Ignore listings for a view that has since been closed
+            let Some(list) = self.files.as_mut().filter(|list| list.pkg.name == name) else {
+                continue;
+            };
+            list.files = Some(match listed {
+                Some(Ok(files)) => {
+                    list.lowercase = files.iter().map(|f| f.to_lowercase()).collect();
+                    Ok(files)
+                }
+                Some(Err(e)) => Err(e.to_string()),
+                None => Err(format!("{} can't list package files", self.provider.name())),
+            });
+        }
+
+        while let Ok((direction, name, listed)) = self.tree_rx.try_recv() {
+            // Ignore listings for a tree that has since been closed or turned around
+            let Some(tree) = &mut self.tree else { continue };
+            if tree.direction != direction {
+                continue;
+            }
+            let children = match listed {
+                Some(Ok(children)) => Ok(children),
+                Some(Err(e)) => Err(e.to_string()),
+                None => Err(format!("{} can't list dependencies", self.provider.name())),
+            };
+            tree.children.insert(name.clone(), children);
+            tree.show_children(&name);
+        }
+
+        while let Ok(built) = self.index_rx.try_recv() {
+            self.index_state = IndexState::Idle;
+            match built {
+                Some(Ok(index)) => self.index = Some(index),
+                Some(Err(_)) => self.index_state = IndexState::Failed,
+                None => {}
+            }
+        }
+
+        while let Ok((pkg, plan)) = self.plan_rx.try_recv() {
+            // Ignore dry runs for a dialog that has since been closed
+            let Some(confirm) = &mut self.confirmation else { continue };
+            if confirm.pkg.name != pkg.name || confirm.pkg.source != pkg.source {
+                continue;
+            }
+            confirm.plan = match plan {
+                Some(Ok(plan)) => Some(PlanState::Ready(plan)),
+                Some(Err(e)) => Some(PlanState::Failed(e)),
+                None => None,
+            };
+        }
+
+        // Drain the results channel, accept only the latest generation
+        while let Ok((search_gen, outcome)) = self.result_rx.try_recv() {
+            if search_gen != self.generation {
+                continue;
+            }
+            if let SearchOutcome::Batch(batch) = outcome {
+                self.append_batch(batch);
+                continue;
+            }
+            self.active_search = None;
+            if let SearchOutcome::Finished(result) = outcome {
+                if result.error.is_none() {
+                    self.result_cache.insert(self.cache_key(), result.packages.clone());
+                }
+                self.show_result(result);
+            } else {
+                self.status_message = "Search cancelled.".to_string();
+                self.search_state = SearchState::Cancelled;
             }
         }
 
         // Fire a search after 400 ms debounce
         if self.query_changed && self.last_input.elapsed() >= Duration::from_millis(400) {
-            let query = self.query.clone();
-            if query.is_empty() {
-                self.packages.clear();
-                self.selected = 0;
-                self.scroll_offset = 0;
-                self.status_message = "Start typing to search.".to_string();
-                self.search_state = SearchState::Idle;
-                self.generation += 1;
+            if self.query.is_empty() {
+                self.clear_results();
             } else {
-                self.generation += 1;
-                self.search_state = SearchState::Searching;
-                self.status_message = "Searching...".to_string();
-                self.spawn_search(query);
+                self.start_search();
             }
             self.query_changed = false;
         }
     }
 
-    fn spawn_search(&self, query: String) {
+    /// Drop the results and go back to the idle prompt, invalidating any
+    /// in-flight search.
+    fn clear_results(&mut self) {
+        self.parsed = Query::default();
+        self.hint = None;
+        self.results.clear();
+        self.packages.clear();
+        self.selected = 0;
+        self.scroll_offset = 0;
+        self.generation += 1;
+        self.reset_search_state();
+    }
+
+    /// Display a completed search.
+    fn show_result(&mut self, result: SearchResult) {
+        let keep = self.streamed.then(|| self.selected_key()).flatten();
+        self.streamed = false;
+        self.results = result.packages;
+        self.apply_filters(keep);
+        if let Some(err) = result.error {
+            self.status_message = format!("Error: {err}");
+            self.search_error = Some(err);
+        } else if self.results.is_empty() {
+            self.status_message = "No results found.".to_string();
+        } else {
+            let n = self.packages.len();
+            self.status_message = format!(
+                "Found {n} result{}.",
+                if n == 1 { "" } else { "s" }
+            );
+        }
+        self.search_state = SearchState::Done;
+    }
+
+    /// Show the results that pass the current filters in the current order,
+    /// keeping the selection on `keep` if it is still shown.
+    fn apply_filters(&mut self, keep: Option<(String, String)>) {
+        self.packages = self.results.iter().filter(|p| self.parsed.matches(p)).cloned().collect();
+        // Results already come in relevance order
+        if self.sort_mode != SortMode::Relevance {
+            self.sort_mode.sort(&mut self.packages, &self.search_query);
+        }
+        self.reselect(keep);
+    }
+
+    /// Rebuild the offline index in the background. The current index, if
+    /// any, keeps answering searches until the new one is ready.
+    pub fn refresh_index(&mut self) {
+        if !self.provider.can_list_all() || matches!(self.index_state, IndexState::Refreshing) {
+            return;
+        }
+        self.index_state = IndexState::Refreshing;
+        let tx = self.index_tx.clone();
+        let provider = Arc::clone(&self.provider);
+        thread::spawn(move || {
+            tx.send(Index::build(provider.as_ref().as_ref())).ok();
+        });
+    }
+
+    /// Ask to confirm installing or removing `pkg`, or go ahead if
+    /// confirmation is turned off.
+    fn start_operation(&mut self, op: Operation, pkg: Package) {
+        let cmd = match op {
+            Operation::Install => Some(self.provider.install_command(&pkg)),
+            Operation::Remove => self.provider.remove_command(&pkg),
+        };
+        let Some(cmd) = cmd else {
+            self.status_message =
+                format!("{} does not support removing packages.", self.provider.name());
+            return;
+        };
+        if self.confirm_operations {
+            let plan = (op == Operation::Install).then(|| {
+                self.spawn_plan(pkg.clone());
+                PlanState::Resolving
+            });
+            self.confirmation = Some(Confirmation {
+                op,
+                pkg,
+                original: cmd.clone(),
+                command: cmd,
+                editing: None,
+                editable: !self.provider.installs_in_process(),
+                plan,
+            });
+        } else {
+            self.run_operation(op, pkg, cmd, false);
+        }
+    }
+
+    /// Dry-run installing `pkg` in the background, for the confirmation dialog.
+    fn spawn_plan(&self, pkg: Package) {
+        let tx = self.plan_tx.clone();
+        let provider = Arc::clone(&self.provider);
+        thread::spawn(move || {
+            let plan = provider.plan_install(std::slice::from_ref(&pkg));
+            tx.send((pkg, plan)).ok();
+        });
+    }
+
+    /// Run the install or removal in the embedded terminal. Unless the user
+    /// edited the command, it is run by a child `fex` so that providers which
+    /// install in-process get a terminal of their own too.
+    fn run_operation(&mut self, op: Operation, pkg: Package, cmd: String, edited: bool) {
+        let title = match op {
+            Operation::Install => format!("Installing {} from {}: {cmd}", pkg.name, pkg.source),
+            Operation::Remove => format!("Removing {}: {cmd}", pkg.name),
+        };
+        let (rows, cols) = terminal_pane_size();
+        let started = SystemTime::now();
+        let spawned = if edited {
+            let args = ["-c".to_string(), cmd.clone()];
+            TerminalPane::spawn(title, "sh".as_ref(), &args, rows, cols)
+        } else {
+            self.spawn_child_operation(op, &pkg, title, rows, cols)
+        };
+        match spawned {
+            Ok(pane) => {
+                self.operation =
+                    Some(RunningOperation { op, pkg, pane, command: cmd, started });
+            }
+            Err(e) => self.status_message = format!("Error: could not start `{cmd}`: {e}"),
+        }
+    }
+
+    fn spawn_child_operation(
+        &self,
+        op: Operation,
+        pkg: &Package,
+        title: String,
+        rows: u16,
+        cols: u16,
+    ) -> std::io::Result<TerminalPane> {
+        let verb = if op == Operation::Install { "install" } else { "remove" };
+        let args = [
+            "--provider".to_string(),
+            self.provider.name().to_string(),
+            "--run".to_string(),
+            verb.to_string(),
+            "--package".to_string(),
+            serde_json::to_string(pkg).unwrap_or_default(),
+        ];
+        let exe = std::env::current_exe()?;
+        TerminalPane::spawn(title, exe.as_os_str(), &args, rows, cols)
+    }
+
+    /// Journal the running operation with `exit_code`.
+    fn journal_operation(&self, exit_code: Option<i32>) -> std::io::Result<()> {
+        let Some(running) = &self.operation else { return Ok(()) };
+        let entry = JournalEntry::new(
+            running.op,
+            self.provider.name(),
+            &running.pkg,
+            &running.command,
+            running.started,
+            exit_code,
+        );
+        entry.record()
+    }
+
+    /// Journal an operation that is still running as fex exits, as killed,
+    /// since its command goes down with the terminal pane.
+    pub fn shutdown(&mut self) -> std::io::Result<()> {
+        let Some(running) = &mut self.operation else { return Ok(()) };
+        if running.pane.exit.is_some() {
+            // Journaled when it exited
+            return Ok(());
+        }
+        let exit_code = if running.pane.poll() { running.pane.exit_code } else { None };
+        self.journal_operation(exit_code)
+    }
+
+    /// Close the terminal of a finished operation and record its outcome.
+    fn finish_operation(&mut self) {
+        let Some(RunningOperation { op, pkg, pane, .. }) = self.operation.take() else { return };
+        let success = pane.exit == Some(true);
+
+        // Whatever happened, the cached installed sets and results may now be stale
+        fex::util::invalidate_installed();
+        self.clear_result_cache();
+
+        let name = &pkg.name;
+        self.status_message = match (op, success) {
+            (Operation::Install, true) => format!("Successfully installed {name}"),
+            (Operation::Install, false) => format!("Installation of {name} may have failed"),
+            (Operation::Remove, true) => format!("Successfully removed {name}"),
+            (Operation::Remove, false) => format!("Removal of {name} may have failed"),
+        };
+        if success && let Some(tree) = &mut self.tree {
+            for node in tree.nodes.iter_mut().filter(|n| n.pkg.name == pkg.name) {
+                node.pkg.installed = op == Operation::Install;
+            }
+        }
+        if success
+            && let Some(idx) =
+                self.packages.iter().position(|p| p.name == pkg.name && p.source == pkg.source)
+        {
+            self.set_installed(idx, op == Operation::Install);
+        }
+    }
+
+    /// Record that a package was installed or removed, in the results and the index.
+    fn set_installed(&mut self, idx: usize, installed: bool) {
+        let Some(pkg) = self.packages.get_mut(idx) else { return };
+        pkg.installed = installed;
+        for result in &mut self.results {
+            if result.name == pkg.name && result.source == pkg.source {
+                result.installed = installed;
+            }
+        }
+        if let Some(index) = &mut self.index {
+            index.set_installed(&pkg.name, &pkg.source, installed);
+            index.save(self.provider.name()).ok();
+        }
+    }
+
+    /// How many results the filters are hiding.
+    pub fn filtered_out(&self) -> usize {
+        self.results.len() - self.packages.len()
+    }
+
+    /// Forget cached search results, whose installed flags go stale after
+    /// installing or removing a package.
+    fn clear_result_cache(&mut self) {
+        self.result_cache.clear();
+    }
+
+    /// Name and source of the selected package, to find it again after re-ranking.
+    fn selected_key(&self) -> Option<(String, String)> {
+        self.packages.get(self.selected).map(|p| (p.name.clone(), p.source.clone()))
+    }
+
+    /// Select the package matching `key`, or the first one.
+    fn reselect(&mut self, key: Option<(String, String)>) {
+        self.selected = key
+            .and_then(|(name, source)| {
+                self.packages.iter().position(|p| p.name == name && p.source == source)
+            })
+            .unwrap_or(0);
+        if self.selected == 0 {
+            self.scroll_offset = 0;
+        }
+        self.adjust_scroll();
+    }
+
+    /// Show a batch of a search still in progress. The first batch replaces the
+    /// previous results; later ones are merged in and the list re-ranked.
+    fn append_batch(&mut self, batch: Vec<Package>) {
+        let keep = if self.streamed {
+            self.selected_key()
+        } else {
+            self.results.clear();
+            None
+        };
+        self.streamed = true;
+        self.results.extend(batch);
+        sort_by_relevance(&mut self.results, &self.search_query);
+        self.apply_filters(keep);
+        self.search_state = SearchState::Partial;
+    }
+
+    /// Search for the current query, superseding any search in flight.
+    /// An up-to-date offline index that finds something, or failing that the
+    /// result cache, answers instantly.
+    /// Only the free text is searched for; filters are applied to the results.
+    fn start_search(&mut self) {
+        self.parsed = Query::parse(&self.query);
+        self.hint = self.translations.hint(self.parsed.text.trim(), self.provider.name());
+        self.search_error = None;
+        let file = self.file_target(&self.parsed);
+        let query = file.clone().unwrap_or_else(|| self.parsed.text.clone());
+        if self.active_search.is_some()
+            && query == self.search_query
+            && file.is_some() == self.file_search
+        {
+            // Only the filters changed; the running search is still wanted
+            let keep = self.selected_key();
+            self.apply_filters(keep);
+            self.search_state =
+                if self.streamed { SearchState::Partial } else { SearchState::Searching };
+            self.status_message = "Searching...".to_string();
+            return;
+        }
+        self.generation += 1;
+        self.streamed = false;
+        self.search_query = query;
+        self.file_search = file.is_some();
+        if let Some(index) = self.index.as_ref().filter(|_| !self.file_search) {
+            // The index keeps the installed state of when it was built
+            let installed = self.provider.installed_names();
+            let current = |mut pkg: Package| {
+                if let Some(installed) = &installed {
+                    pkg.installed = installed.contains(&pkg.name);
+                }
+                pkg
+            };
+            let packages: Vec<Package> = if self.search_query.is_empty() {
+                // Filters alone narrow down the whole index, stale or not
+                index
+                    .packages
+                    .iter()
+                    .map(|p| current(p.clone()))
+                    .filter(|p| self.parsed.matches(p))
+                    .collect()
+            } else if index.is_stale() {
+                Vec::new()
+            } else {
+                index.search(&self.search_query).into_iter().map(current).collect()
+            };
+            // Otherwise the provider may know packages the index doesn't
+            if self.search_query.is_empty() || !packages.is_empty() {
+                self.cancel_search();
+                self.show_result(SearchResult { packages, error: None });
+                return;
+            }
+        }
+        if self.search_query.is_empty() {
+            self.cancel_search();
+            self.results.clear();
+            self.packages.clear();
+            self.reselect(None);
+            self.search_state = SearchState::Idle;
+            self.status_message = "Type something to search for besides the filters.".to_string();
+            return;
+        }
+        if let Some(packages) = self.result_cache.get(&self.cache_key()) {
+            self.cancel_search();
+            self.show_result(SearchResult { packages, error: None });
+            return;
+        }
+        self.search_state = SearchState::Searching;
+        self.status_message = "Searching...".to_string();
+        self.spawn_search(self.search_query.clone(), self.file_search);
+    }
+
+    /// Whether the query looks up the packages containing a file.
+    pub fn searching_files(&self) -> bool {
+        self.file_mode || self.parsed.file.is_some()
+    }
+
+    /// The path or command `parsed` looks up with `Provider::search_file`,
+    /// if it is a file search.
+    fn file_target(&self, parsed: &Query) -> Option<String> {
+        let text = parsed.text.trim();
+        parsed.file.clone().or_else(|| (self.file_mode && !text.is_empty()).then(|| text.into()))
+    }
+
+    /// Where the results of the running (or last) search are cached.
+    fn cache_key(&self) -> String {
+        cache_key(&self.search_query, self.file_search)
+    }
+
+    /// Start a search, killing the previous one so that at most one runs at a time.
+    fn spawn_search(&mut self, query: String, file: bool) {
+        self.cancel_search();
+        let token = CancelToken::new();
+        if !self.search_timeout.is_zero() {
+            token.set_timeout(self.search_timeout);
+        }
+        self.active_search = Some(token.clone());
+        let timeout = self.search_timeout;
+
         let search_gen = self.generation;
         let tx = self.result_tx.clone();
         let provider = Arc::clone(&self.provider);
         thread::spawn(move || {
-            let result = provider.search(&query);
-            tx.send((search_gen, result)).ok();
+            let batch_tx = tx.clone();
+            let result = with_cancel_token(&token, || {
+                with_batch_sink(
+                    move |batch: &[Package]| {
+                        batch_tx.send((search_gen, SearchOutcome::Batch(batch.to_vec()))).ok();
+                    },
+                    || {
+                        if file {
+                            search_file(provider.as_ref().as_ref(), &query)
+                        } else {
+                            provider.search(&query)
+                        }
+                    },
+                )
+            });
+            token.finish();
+            let outcome = if token.timed_out() {
+                SearchOutcome::Finished(SearchResult {
+                    packages: vec![],
+                    error: Some(SearchError::TimedOut(timeout)),
+                })
+            } else if token.is_cancelled() {
+                SearchOutcome::Cancelled
+            } else {
+                SearchOutcome::Finished(result)
+            };
+            tx.send((search_gen, outcome)).ok();
         });
     }
 }
+
+/// File searches and text searches are cached apart.
+fn cache_key(query: &str, file: bool) -> String {
+    if file { format!("file:{query}") } else { query.to_string() }
+}
+
+/// `Provider::search_file` as a search result.
+fn search_file(provider: &dyn Provider, path: &str) -> SearchResult {
+    match provider.search_file(path) {
+        Some(Ok(packages)) => SearchResult { packages, error: None },
+        Some(Err(e)) => SearchResult { packages: vec![], error: Some(e) },
+        None => {
+            let message = format!("{} can't search for files", provider.name());
+            SearchResult { packages: vec![], error: Some(SearchError::Other(message)) }
+        }
+    }
+}
+
+/// Rows and columns inside the terminal pane, which takes the place of the
+/// results below the search and status bars.
+fn terminal_pane_size() -> (u16, u16) {
+    let (width, height) = crossterm::terminal::size().unwrap_or((80, 24));
+    (height.saturating_sub(6), width.saturating_sub(2))
+}
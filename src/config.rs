@@ -0,0 +1,40 @@
+//! User settings from `$XDG_CONFIG_HOME/fex/config.toml`.
+//!
+//! ```toml
+//! # Run install and remove commands without asking first
+//! confirm = false
+//! ```
+
+use std::fs;
+
+use serde::Deserialize;
+
+use crate::util::config_dir;
+
+#[derive(Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct Config {
+    /// Show the command and ask before installing or removing a package.
+    pub confirm: bool,
+}
+
+impl Default for Config {
+    fn default() -> Self {
+        Config { confirm: true }
+    }
+}
+
+impl Config {
+    /// Load the settings. Problems are reported on stderr and the defaults
+    /// used, so a typo never prevents fex from starting.
+    pub fn load() -> Config {
+        let path = config_dir().join("config.toml");
+        let Ok(data) = fs::read_to_string(&path) else {
+            return Config::default();
+        };
+        toml::from_str(&data).unwrap_or_else(|e| {
+            eprintln!("Warning: ignoring {}: {e}", path.display());
+            Config::default()
+        })
+    }
+}
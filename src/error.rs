@@ -0,0 +1,170 @@
+use std::fmt;
+use std::time::Duration;
+
+/// Why a search produced no usable results.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum SearchError {
+    /// The provider's command is not installed or not on PATH.
+    ProviderMissing(String),
+    /// A command exited unsuccessfully for an unrecognised reason.
+    CommandFailed { command: String, code: i32, stderr: String },
+    /// The provider's output could not be understood.
+    ParseFailure(String),
+    /// Another process holds the package database lock.
+    LockedDatabase,
+    /// Repositories or remote indexes could not be reached.
+    NetworkUnavailable,
+    /// The command needs privileges the user doesn't have.
+    PermissionDenied,
+    /// The search was cancelled after running for too long.
+    TimedOut(Duration),
+    /// A provider-specific message, shown as-is.
+    Other(String),
+}
+
+const LOCK_PATTERNS: &[&str] = &[
+    "could not lock database",
+    "unable to lock database",
+    "could not get lock",
+    "unable to acquire the dpkg frontend lock",
+    "system management is locked",
+    "waiting for process with pid",
+    "database is locked",
+];
+
+const NETWORK_PATTERNS: &[&str] = &[
+    "could not resolve",
+    "couldn't resolve",
+    "temporary failure in name resolution",
+    "network is unreachable",
+    "no route to host",
+    "failed to download metadata",
+    "failed retrieving file",
+    "connection refused",
+    "connection timed out",
+    "failed to connect",
+    "unable to connect",
+    "unable to contact",
+    "dial tcp",
+    "curl error",
+];
+
+const PERMISSION_PATTERNS: &[&str] = &[
+    "permission denied",
+    "operation not permitted",
+    "must be root",
+    "must be run as root",
+    "you need to be root",
+    "root privileges are required",
+];
+
+impl SearchError {
+    /// Classify a failed command by its exit code and stderr.
+    pub fn from_command(command: &str, code: i32, stderr: &str) -> Self {
+        if code == 127 {
+            let program = command.split_whitespace().next().unwrap_or(command);
+            return SearchError::ProviderMissing(program.to_string());
+        }
+        let lower = stderr.to_lowercase();
+        let matches = |patterns: &[&str]| patterns.iter().any(|p| lower.contains(p));
+        if matches(LOCK_PATTERNS) {
+            SearchError::LockedDatabase
+        } else if matches(NETWORK_PATTERNS) {
+            SearchError::NetworkUnavailable
+        } else if matches(PERMISSION_PATTERNS) {
+            SearchError::PermissionDenied
+        } else {
+            SearchError::CommandFailed {
+                command: command.to_string(),
+                code,
+                stderr: stderr.trim().to_string(),
+            }
+        }
+    }
+
+    /// What the user can do about it, if anything.
+    pub fn hint(&self) -> Option<&'static str> {
+        match self {
+            SearchError::ProviderMissing(_) => {
+                Some("Install it, or pick another provider with -p (see fex -l).")
+            }
+            SearchError::CommandFailed { .. } => Some("Press Ctrl+R to retry."),
+            SearchError::ParseFailure(_) => {
+                Some("The package manager's output format may have changed; please report it.")
+            }
+            SearchError::LockedDatabase => {
+                Some("Wait for the other package manager to finish, then press Ctrl+R.")
+            }
+            SearchError::NetworkUnavailable => {
+                Some("Check your network connection, then press Ctrl+R.")
+            }
+            SearchError::PermissionDenied => {
+                Some("Check the permissions of the package database, or run with sudo.")
+            }
+            SearchError::TimedOut(_) => Some("Press Ctrl+R to retry, or raise the --timeout."),
+            SearchError::Other(_) => None,
+        }
+    }
+}
+
+impl fmt::Display for SearchError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            SearchError::ProviderMissing(program) => write!(f, "{program} is not installed."),
+            SearchError::CommandFailed { command, code, stderr } => {
+                let program = command.split_whitespace().next().unwrap_or(command);
+                match stderr.lines().find(|l| !l.trim().is_empty()) {
+                    Some(line) => write!(f, "{program} failed (exit {code}): {}", line.trim()),
+                    None => write!(f, "{program} failed (exit {code})."),
+                }
+            }
+            SearchError::ParseFailure(detail) => {
+                write!(f, "Could not parse provider output: {detail}")
+            }
+            SearchError::LockedDatabase => {
+                write!(f, "The package database is locked by another process.")
+            }
+            SearchError::NetworkUnavailable => write!(f, "Package repositories are unreachable."),
+            SearchError::PermissionDenied => write!(f, "Permission denied."),
+            SearchError::TimedOut(after) => {
+                write!(f, "Search timed out after {}s.", after.as_secs())
+            }
+            SearchError::Other(msg) => write!(f, "{msg}"),
+        }
+    }
+}
+
+impl std::error::Error for SearchError {}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn classifies_common_failures() {
+        assert_eq!(
+            SearchError::from_command("apt-cache search x", 100, "E: Could not get lock"),
+            SearchError::LockedDatabase
+        );
+        assert_eq!(
+            SearchError::from_command(
+                "dnf search x",
+                1,
+                "Error: Failed to download metadata for repo 'fedora'"
+            ),
+            SearchError::NetworkUnavailable
+        );
+        assert_eq!(
+            SearchError::from_command("pacman -Ss x", 1, "could not open: Permission denied"),
+            SearchError::PermissionDenied
+        );
+        assert_eq!(
+            SearchError::from_command("guix search x", 127, "sh: guix: not found"),
+            SearchError::ProviderMissing("guix".to_string())
+        );
+        assert!(matches!(
+            SearchError::from_command("foo", 2, "boom"),
+            SearchError::CommandFailed { code: 2, .. }
+        ));
+    }
+}
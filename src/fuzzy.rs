@@ -0,0 +1,236 @@
+//! Fuzzy matching of queries against package names and descriptions.
+//!
+//! A query word matches a name if its characters appear in order (scored by
+//! how tightly and where they match), or failing that if it is within a small
+//! edit distance, so typos like "fierfox" still find "firefox". Words found in
+//! the description count too, at a lower weight.
+
+use crate::provider::Package;
+
+const MATCH_SCORE: i32 = 16;
+const START_BONUS: i32 = 32;
+const BOUNDARY_BONUS: i32 = 24;
+const CONSECUTIVE_BONUS: i32 = 16;
+const GAP_PENALTY: i32 = 3;
+const LEADING_GAP_PENALTY: i32 = 1;
+const MAX_LEADING_GAP: usize = 10;
+const TYPO_PENALTY: i32 = 12;
+const DESCRIPTION_SCORE: i32 = 8;
+
+/// Words shorter than this must match a name tightly (see `FuzzyMatch::tight`)
+/// and without typos; looser matching would find them in almost any name.
+const MIN_TYPO_LEN: usize = 4;
+
+const NONE: i32 = i32::MIN / 2;
+
+pub struct FuzzyMatch {
+    pub score: i32,
+    /// Char indices of `text` that matched the pattern.
+    pub positions: Vec<usize>,
+    /// Whether every matched char after the first directly follows the
+    /// previous one or starts a word, as in "ff" for "fast-forward".
+    pub tight: bool,
+}
+
+fn position_bonus(text: &[char], j: usize) -> i32 {
+    if j == 0 {
+        return START_BONUS;
+    }
+    let (prev, cur) = (text[j - 1], text[j]);
+    if matches!(prev, ' ' | '-' | '_' | '.' | '/' | ':' | '@')
+        || (prev.is_lowercase() && cur.is_uppercase())
+    {
+        BOUNDARY_BONUS
+    } else {
+        0
+    }
+}
+
+fn lower(c: char) -> char {
+    c.to_lowercase().next().unwrap_or(c)
+}
+
+/// Best case-insensitive subsequence alignment of `pattern` in `text`.
+pub fn fuzzy_match(pattern: &str, text: &str) -> Option<FuzzyMatch> {
+    let p: Vec<char> = pattern.chars().map(lower).collect();
+    let t: Vec<char> = text.chars().collect();
+    let (m, n) = (p.len(), t.len());
+    if m == 0 || m > n {
+        return None;
+    }
+    // Most texts don't contain the pattern at all; skip the tables for those
+    let mut rest = t.iter().map(|&c| lower(c));
+    if !p.iter().all(|&c| rest.any(|tc| tc == c)) {
+        return None;
+    }
+
+    // score[i * n + j]: best score of p[..=i] with p[i] matched at t[j]
+    let mut score = vec![NONE; m * n];
+    let mut from = vec![0usize; m * n];
+    let at = |i: usize, j: usize| i * n + j;
+    for i in 0..m {
+        // Best predecessor at least one character back, minus its gap
+        let mut best_gap = NONE;
+        let mut best_gap_at = 0;
+        for j in i..n {
+            if i > 0 && j >= 2 {
+                best_gap -= GAP_PENALTY;
+                let candidate = score[at(i - 1, j - 2)] - GAP_PENALTY;
+                if candidate > best_gap {
+                    best_gap = candidate;
+                    best_gap_at = j - 2;
+                }
+            }
+            if lower(t[j]) != p[i] {
+                continue;
+            }
+            let here = MATCH_SCORE + position_bonus(&t, j);
+            if i == 0 {
+                score[j] = here - LEADING_GAP_PENALTY * j.min(MAX_LEADING_GAP) as i32;
+                continue;
+            }
+            let consecutive =
+                if j >= 1 { score[at(i - 1, j - 1)] + CONSECUTIVE_BONUS } else { NONE };
+            if consecutive > NONE / 2 && consecutive >= best_gap {
+                score[at(i, j)] = consecutive + here;
+                from[at(i, j)] = j - 1;
+            } else if best_gap > NONE / 2 {
+                score[at(i, j)] = best_gap + here;
+                from[at(i, j)] = best_gap_at;
+            }
+        }
+    }
+
+    let (end, &best) = score[at(m - 1, 0)..].iter().enumerate().max_by_key(|&(_, s)| *s)?;
+    if best <= NONE / 2 {
+        return None;
+    }
+    let mut positions = vec![0; m];
+    let mut j = end;
+    for i in (0..m).rev() {
+        positions[i] = j;
+        j = from[at(i, j)];
+    }
+    let tight = positions.windows(2).all(|w| w[1] == w[0] + 1 || position_bonus(&t, w[1]) > 0);
+    Some(FuzzyMatch { score: best, positions, tight })
+}
+
+/// Optimal string alignment distance (Levenshtein plus adjacent transpositions).
+fn edit_distance(a: &[char], b: &[char]) -> usize {
+    let mut d = vec![vec![0; b.len() + 1]; a.len() + 1];
+    for (i, row) in d.iter_mut().enumerate() {
+        row[0] = i;
+    }
+    for (j, cell) in d[0].iter_mut().enumerate() {
+        *cell = j;
+    }
+    for i in 1..=a.len() {
+        for j in 1..=b.len() {
+            let cost = usize::from(a[i - 1] != b[j - 1]);
+            d[i][j] = (d[i - 1][j] + 1).min(d[i][j - 1] + 1).min(d[i - 1][j - 1] + cost);
+            if i > 1 && j > 1 && a[i - 1] == b[j - 2] && a[i - 2] == b[j - 1] {
+                d[i][j] = d[i][j].min(d[i - 2][j - 2] + 1);
+            }
+        }
+    }
+    d[a.len()][b.len()]
+}
+
+/// Score for a word that is a near miss of `text` or of its start.
+pub fn typo_score(pattern: &str, text: &str) -> Option<i32> {
+    let p: Vec<char> = pattern.chars().map(lower).collect();
+    if p.len() < MIN_TYPO_LEN {
+        return None;
+    }
+    let t: Vec<char> = text.chars().map(lower).collect();
+    let max_distance = (p.len() / 4).max(1);
+    let whole = edit_distance(&p, &t);
+    let prefix = edit_distance(&p, &t[..p.len().min(t.len())]);
+    let distance = whole.min(prefix);
+    (distance <= max_distance)
+        .then(|| MATCH_SCORE * p.len() as i32 / 2 - TYPO_PENALTY * distance as i32)
+}
+
+/// How well `pkg` matches every word of `query`, or `None` if some word
+/// matches neither its name nor its description.
+pub fn score_package(pkg: &Package, query: &str) -> Option<i32> {
+    let description = pkg.description.to_lowercase();
+    let mut total = 0;
+    for word in query.split_whitespace() {
+        let short = word.chars().count() < MIN_TYPO_LEN;
+        let name = fuzzy_match(word, &pkg.name)
+            .filter(|m| m.tight || !short)
+            .map(|m| m.score)
+            .or_else(|| typo_score(word, &pkg.name));
+        let in_description = description.contains(&word.to_lowercase());
+        if name.is_none() && !in_description {
+            return None;
+        }
+        total += name.unwrap_or(0) + if in_description { DESCRIPTION_SCORE } else { 0 };
+    }
+    Some(total)
+}
+
+/// Char indices of `text` to highlight for `query`.
+pub fn highlight_positions(query: &str, text: &str) -> Vec<usize> {
+    let mut positions: Vec<usize> = query
+        .split_whitespace()
+        .filter_map(|word| fuzzy_match(word, text))
+        .flat_map(|m| m.positions)
+        .collect();
+    positions.sort_unstable();
+    positions.dedup();
+    positions
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn pkg(name: &str, description: &str) -> Package {
+        Package {
+            name: name.to_string(),
+            description: description.to_string(),
+            ..Default::default()
+        }
+    }
+
+    #[test]
+    fn prefers_tight_and_boundary_matches() {
+        let tight = fuzzy_match("ff", "ff-tool").unwrap();
+        let boundary = fuzzy_match("ff", "fast-forward").unwrap();
+        let scattered = fuzzy_match("ff", "fluff").unwrap();
+        assert!(tight.score > boundary.score);
+        assert!(boundary.score > scattered.score);
+        assert_eq!(boundary.positions, [0, 5]);
+        assert!(fuzzy_match("xyz", "firefox").is_none());
+    }
+
+    #[test]
+    fn tolerates_typos() {
+        assert_eq!(fuzzy_match("firefx", "firefox").unwrap().positions, [0, 1, 2, 3, 4, 6]);
+        assert!(fuzzy_match("fierfox", "firefox").is_none());
+        assert!(typo_score("fierfox", "firefox").is_some());
+        assert!(typo_score("fierf", "firefox").is_some());
+        assert!(typo_score("vmi", "vim").is_none());
+        assert!(typo_score("chromium", "firefox").is_none());
+    }
+
+    #[test]
+    fn description_matches_weigh_less_than_names() {
+        let by_name = pkg("ripgrep", "Line-oriented search tool");
+        let by_description = pkg("ag", "Code search tool like grep");
+        let name_score = score_package(&by_name, "grep").unwrap();
+        let description_score = score_package(&by_description, "grep").unwrap();
+        assert!(name_score > description_score);
+        assert!(score_package(&by_name, "grep editor").is_none());
+    }
+
+    #[test]
+    fn short_words_only_match_names_tightly() {
+        assert!(score_package(&pkg("gnome-text-editor", ""), "gt").is_some());
+        assert!(score_package(&pkg("libgit2", ""), "gt").is_none());
+        assert!(score_package(&pkg("gettext", ""), "gtx").is_none());
+        assert!(score_package(&pkg("gettext", ""), "gtxt").is_some());
+    }
+}
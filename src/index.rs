@@ -0,0 +1,137 @@
+//! Offline package index.
+//!
+//! Providers that implement `Provider::list_all` can have their full listing
+//! saved under `$XDG_CACHE_HOME/fex/<provider>/index.json` and searched
+//! in-process, so results appear instantly and without network access.
+
+use std::fs;
+use std::io;
+use std::path::PathBuf;
+use std::time::{Duration, SystemTime, UNIX_EPOCH};
+
+use serde::{Deserialize, Serialize};
+
+use crate::error::SearchError;
+use crate::fuzzy::score_package;
+use crate::provider::{Package, Provider};
+use crate::util::{cache_dir, relevance_key};
+
+/// Indexes older than this should be rebuilt.
+pub const INDEX_MAX_AGE: Duration = Duration::from_secs(24 * 60 * 60);
+
+#[derive(Serialize, Deserialize)]
+pub struct Index {
+    /// When the listing was taken, in seconds since the Unix epoch.
+    pub built: u64,
+    pub packages: Vec<Package>,
+}
+
+fn index_path(provider: &str) -> PathBuf {
+    cache_dir().join(provider).join("index.json")
+}
+
+impl Index {
+    /// The saved index for `provider`, if there is a readable one.
+    pub fn load(provider: &str) -> Option<Index> {
+        let data = fs::read_to_string(index_path(provider)).ok()?;
+        serde_json::from_str(&data).ok()
+    }
+
+    /// List every package of `provider` and save the result. Returns `None`
+    /// if the provider can't be indexed.
+    pub fn build(provider: &dyn Provider) -> Option<Result<Index, SearchError>> {
+        let packages = match provider.list_all()? {
+            Ok(packages) => packages,
+            Err(e) => return Some(Err(e)),
+        };
+        let index = Index { built: unix_now(), packages };
+        // An unwritable cache only costs the next startup a rebuild
+        index.save(provider.name()).ok();
+        Some(Ok(index))
+    }
+
+    /// Write the index to disk, replacing the previous one atomically.
+    pub fn save(&self, provider: &str) -> io::Result<()> {
+        let path = index_path(provider);
+        if let Some(dir) = path.parent() {
+            fs::create_dir_all(dir)?;
+        }
+        let tmp = path.with_extension("json.tmp");
+        fs::write(&tmp, serde_json::to_vec(self)?)?;
+        fs::rename(&tmp, &path)
+    }
+
+    pub fn age(&self) -> Duration {
+        Duration::from_secs(unix_now().saturating_sub(self.built))
+    }
+
+    pub fn is_stale(&self) -> bool {
+        self.age() > INDEX_MAX_AGE
+    }
+
+    /// Packages matching every word of the query, fuzzily by name or
+    /// literally in the description.
+    pub fn search(&self, query: &str) -> Vec<Package> {
+        if query.trim().is_empty() {
+            return Vec::new();
+        }
+        // Scored once, for both the filtering and the sorting
+        let query_lower = query.to_lowercase();
+        let mut scored: Vec<(&Package, i32)> = self
+            .packages
+            .iter()
+            .filter_map(|p| Some((p, score_package(p, query)?)))
+            .collect();
+        scored.sort_by_cached_key(|&(pkg, score)| relevance_key(pkg, &query_lower, score));
+        scored.into_iter().map(|(pkg, _)| pkg.clone()).collect()
+    }
+
+    /// Update the installed flag of a package, e.g. after installing it.
+    pub fn set_installed(&mut self, name: &str, source: &str, installed: bool) {
+        for pkg in &mut self.packages {
+            if pkg.name == name && pkg.source == source {
+                pkg.installed = installed;
+            }
+        }
+    }
+}
+
+fn unix_now() -> u64 {
+    SystemTime::now().duration_since(UNIX_EPOCH).map(|d| d.as_secs()).unwrap_or(0)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn pkg(name: &str, description: &str) -> Package {
+        Package {
+            name: name.to_string(),
+            version: "1.0".to_string(),
+            description: description.to_string(),
+            source: "extra".to_string(),
+            ..Default::default()
+        }
+    }
+
+    #[test]
+    fn search_matches_all_words_in_name_or_description() {
+        let index = Index {
+            built: unix_now(),
+            packages: vec![
+                pkg("ripgrep", "Fast line-oriented search tool"),
+                pkg("grep", "GNU pattern search"),
+                pkg("vim", "Vi Improved text editor"),
+            ],
+        };
+        let names = |q: &str| index.search(q).into_iter().map(|p| p.name).collect::<Vec<_>>();
+        assert_eq!(names("grep"), ["grep", "ripgrep"]);
+        assert_eq!(names("search fast"), ["ripgrep"]);
+        assert_eq!(names("EDITOR"), ["vim"]);
+        assert_eq!(names("rigrep"), ["ripgrep"]);
+        // Scattered across "ripgrep", so too loose for a short word
+        assert!(names("rp").is_empty());
+        assert!(names("  ").is_empty());
+        assert!(!index.is_stale());
+    }
+}
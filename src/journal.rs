@@ -0,0 +1,149 @@
+//! Record of installs and removals, one JSON object per line in
+//! `$XDG_STATE_HOME/fex/journal.jsonl`.
+
+use std::fs::{self, OpenOptions};
+use std::io::{self, Write};
+use std::path::PathBuf;
+use std::time::{SystemTime, UNIX_EPOCH};
+
+use serde::{Deserialize, Serialize};
+
+use crate::provider::Package;
+use crate::util::state_dir;
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum Operation {
+    Install,
+    Remove,
+}
+
+impl Operation {
+    /// The operation that reverts this one.
+    pub fn inverse(self) -> Operation {
+        match self {
+            Operation::Install => Operation::Remove,
+            Operation::Remove => Operation::Install,
+        }
+    }
+}
+
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct JournalEntry {
+    /// When the operation started, in seconds since the Unix epoch.
+    pub timestamp: u64,
+    pub user: String,
+    pub operation: Operation,
+    pub provider: String,
+    pub package: String,
+    #[serde(default)]
+    pub version: String,
+    pub source: String,
+    pub command: String,
+    /// `None` if the command was killed before it could exit.
+    pub exit_code: Option<i32>,
+    pub duration_ms: u64,
+}
+
+fn journal_path() -> PathBuf {
+    state_dir().join("journal.jsonl")
+}
+
+impl JournalEntry {
+    /// An entry for `operation` on `pkg` that started at `started`.
+    pub fn new(
+        operation: Operation,
+        provider: &str,
+        pkg: &Package,
+        command: &str,
+        started: SystemTime,
+        exit_code: Option<i32>,
+    ) -> JournalEntry {
+        let timestamp = started.duration_since(UNIX_EPOCH).map(|d| d.as_secs()).unwrap_or(0);
+        let duration = started.elapsed().unwrap_or_default();
+        JournalEntry {
+            timestamp,
+            user: std::env::var("USER").unwrap_or_default(),
+            operation,
+            provider: provider.to_string(),
+            package: pkg.name.clone(),
+            version: pkg.version.clone(),
+            source: pkg.source.clone(),
+            command: command.to_string(),
+            exit_code,
+            duration_ms: duration.as_millis() as u64,
+        }
+    }
+
+    pub fn succeeded(&self) -> bool {
+        self.exit_code == Some(0)
+    }
+
+    /// The package as it was before an install or after a removal.
+    pub fn package(&self) -> Package {
+        Package {
+            name: self.package.clone(),
+            version: self.version.clone(),
+            source: self.source.clone(),
+            installed: self.operation == Operation::Install && self.succeeded(),
+            ..Default::default()
+        }
+    }
+
+    /// Append the entry to the journal.
+    pub fn record(&self) -> io::Result<()> {
+        let path = journal_path();
+        if let Some(dir) = path.parent() {
+            fs::create_dir_all(dir)?;
+        }
+        let mut line = serde_json::to_string(self)?;
+        line.push('\n');
+        OpenOptions::new().create(true).append(true).open(path)?.write_all(line.as_bytes())
+    }
+}
+
+/// Every journal entry, oldest first. Lines that can't be parsed are skipped.
+pub fn read_journal() -> Vec<JournalEntry> {
+    let Ok(data) = fs::read_to_string(journal_path()) else {
+        return Vec::new();
+    };
+    data.lines().filter_map(|line| serde_json::from_str(line).ok()).collect()
+}
+
+/// "YYYY-MM-DD HH:MM" in UTC.
+pub fn format_timestamp(secs: u64) -> String {
+    let days = (secs / 86400) as i64;
+    let minutes = secs % 86400 / 60;
+    // Civil date from days since 1970-01-01 (Howard Hinnant's algorithm)
+    let z = days + 719468;
+    let era = z.div_euclid(146097);
+    let doe = z.rem_euclid(146097);
+    let yoe = (doe - doe / 1460 + doe / 36524 - doe / 146096) / 365;
+    let doy = doe - (365 * yoe + yoe / 4 - yoe / 100);
+    let mp = (5 * doy + 2) / 153;
+    let day = doy - (153 * mp + 2) / 5 + 1;
+    let month = if mp < 10 { mp + 3 } else { mp - 9 };
+    let year = yoe + era * 400 + i64::from(month <= 2);
+    format!("{year:04}-{month:02}-{day:02} {:02}:{:02}", minutes / 60, minutes % 60)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn formats_timestamps_and_round_trips_entries() {
+        assert_eq!(format_timestamp(0), "1970-01-01 00:00");
+        assert_eq!(format_timestamp(951_782_400 + 3_661), "2000-02-29 01:01");
+        assert_eq!(format_timestamp(1_792_360_800), "2026-10-18 22:00");
+
+        let line = r#"{"timestamp":1,"user":"me","operation":"install","provider":"apt",
+            "package":"vim","source":"apt","command":"sudo apt install vim",
+            "exit_code":0,"duration_ms":1200}"#;
+        let entry: JournalEntry = serde_json::from_str(line).unwrap();
+        assert_eq!(entry.operation, Operation::Install);
+        assert!(entry.succeeded());
+        assert!(entry.package().installed);
+        assert_eq!(entry.operation.inverse(), Operation::Remove);
+    }
+}
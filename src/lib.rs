@@ -0,0 +1,36 @@
+//! Package search across system package managers.
+//!
+//! The `fex` binary is a TUI on top of this library. Other tools can use the
+//! provider registry and parsers directly; build with `default-features = false`
+//! to leave out the TUI dependencies.
+
+pub mod error;
+pub mod provider;
+pub mod providers;
+pub mod query;
+
+// Used by the binary but not part of the supported API.
+#[doc(hidden)]
+pub mod config;
+#[doc(hidden)]
+pub mod fuzzy;
+#[doc(hidden)]
+pub mod journal;
+#[doc(hidden)]
+pub mod manifest;
+#[doc(hidden)]
+pub mod sort;
+#[doc(hidden)]
+pub mod translate;
+#[doc(hidden)]
+pub mod util;
+
+mod index;
+
+pub use error::SearchError;
+pub use index::Index;
+pub use provider::{
+    BoxedProvider, Color, InstallPlan, Package, PlanAction, PlanEntry, Provider, SearchResult,
+};
+pub use providers::{auto_detect_provider, create_provider, get_available_providers};
+pub use util::sort_by_relevance;
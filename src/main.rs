@@ -1,12 +1,15 @@
 mod app;
-mod provider;
-mod providers;
+mod terminal;
 mod ui;
-mod util;
 
-use std::io::{self, Write as _};
+use std::collections::{HashMap, HashSet};
+use std::io::{self, BufRead, Write};
+use std::path::{Path, PathBuf};
+use std::sync::Arc;
+use std::time::SystemTime;
+use std::time::Duration;
 
-use clap::Parser;
+use clap::{Parser, Subcommand};
 use crossterm::{
     cursor::{Hide, Show},
     execute,
@@ -14,7 +17,11 @@ use crossterm::{
 };
 use ratatui::{Terminal, backend::CrosstermBackend};
 
-use app::{App, AppAction};
+use app::App;
+use fex::journal::{JournalEntry, Operation, format_timestamp, read_journal};
+use fex::manifest::{ExportFormat, Manifest};
+use fex::translate::Translations;
+use fex::providers;
 
 #[derive(Parser)]
 #[command(name = "fex", about = "A TUI package search tool", version)]
@@ -23,9 +30,70 @@ struct Cli {
     #[arg(short = 'p', long = "provider", value_name = "PROVIDER")]
     provider: Option<String>,
 
+    /// Cancel searches that take longer than this many seconds (0 disables)
+    #[arg(short = 't', long = "timeout", value_name = "SECONDS")]
+    timeout: Option<u64>,
+
     /// List available providers and exit
     #[arg(short = 'l', long = "list")]
     list: bool,
+
+    /// Install or remove `--package` and exit; how the TUI runs operations
+    /// in its terminal pane
+    #[arg(long = "run", value_name = "OPERATION", hide = true, requires = "package")]
+    run: Option<String>,
+
+    /// The package for `--run`, as JSON
+    #[arg(long = "package", value_name = "JSON", hide = true)]
+    package: Option<String>,
+
+    #[command(subcommand)]
+    command: Option<Command>,
+}
+
+#[derive(Subcommand)]
+enum Command {
+    /// Show past installs and removals, newest last
+    History {
+        /// Show only the last N entries
+        #[arg(short = 'n', long = "limit", value_name = "N")]
+        limit: Option<usize>,
+    },
+    /// Export the installed packages as a manifest
+    Export {
+        /// fex, brewfile, pacman, apt, flatpak or nix
+        #[arg(short = 'f', long = "format", default_value = "fex")]
+        format: ExportFormat,
+        /// Write to this file instead of stdout
+        #[arg(short = 'o', long = "output", value_name = "FILE")]
+        output: Option<PathBuf>,
+    },
+    /// Install the packages of a fex manifest that are missing
+    ///
+    /// Versions in the manifest are not pinned: missing packages are installed at the
+    /// version the provider offers now, and installed ones at another version are only
+    /// reported.
+    Apply {
+        /// The manifest, as written by `fex export`
+        manifest: PathBuf,
+        /// Only show what is missing
+        #[arg(long = "dry-run")]
+        dry_run: bool,
+        /// Install without asking first
+        #[arg(short = 'y', long = "yes")]
+        yes: bool,
+    },
+    /// Look up what a package is called in other package managers
+    Translate {
+        /// Package name, e.g. libssl-dev
+        name: String,
+        /// Print only its name for this provider
+        #[arg(long = "to", value_name = "PROVIDER")]
+        to: Option<String>,
+        /// Only match the name as this provider calls it
+        #[arg(long = "from", value_name = "PROVIDER")]
+        from: Option<String>,
+    },
 }
 
 fn main() {
@@ -44,6 +112,19 @@ fn main() {
         return;
     }
 
+    if let Some(Command::History { limit }) = cli.command {
+        print_history(limit);
+        return;
+    }
+
+    if let Some(Command::Translate { name, to, from }) = &cli.command {
+        std::process::exit(translate(name, to.as_deref(), from.as_deref()));
+    }
+
+    if let Some(Command::Apply { manifest, dry_run, yes }) = &cli.command {
+        std::process::exit(apply(manifest, *dry_run, *yes));
+    }
+
     let provider = if let Some(name) = cli.provider {
         match providers::create_provider(&name) {
             Some(p) if p.is_available() => p,
@@ -66,13 +147,29 @@ fn main() {
         }
     };
 
-    if let Err(e) = run(provider) {
+    if let Some(Command::Export { format, output }) = cli.command {
+        export(provider.as_ref(), format, output);
+        return;
+    }
+
+    if let (Some(op), Some(package)) = (cli.run, cli.package) {
+        std::process::exit(run_operation(provider.as_ref(), &op, &package));
+    }
+
+    if let Err(e) = run(provider, cli.timeout) {
         eprintln!("Error: {e}");
         std::process::exit(1);
     }
 }
 
-fn run(provider: crate::provider::BoxedProvider) -> Result<(), Box<dyn std::error::Error>> {
+fn run(
+    provider: fex::BoxedProvider,
+    timeout: Option<u64>,
+) -> Result<(), Box<dyn std::error::Error>> {
+    // Before the alternate screen, so that warnings stay visible
+    let config = fex::config::Config::load();
+    let translations = Translations::load();
+
     enable_raw_mode()?;
     let mut stdout = io::stdout();
     execute!(stdout, EnterAlternateScreen, Hide)?;
@@ -81,76 +178,313 @@ fn run(provider: crate::provider::BoxedProvider) -> Result<(), Box<dyn std::erro
     terminal.clear()?;
 
     let mut app = App::new(provider);
+    app.confirm_operations = config.confirm;
+    app.translations = translations;
+    if let Some(secs) = timeout {
+        app.search_timeout = Duration::from_secs(secs);
+    }
 
-    let result = run_loop(&mut app, &mut terminal);
+    let result = app.run(&mut terminal);
+    let journaled = app.shutdown();
 
     // Always restore terminal
     disable_raw_mode()?;
     execute!(terminal.backend_mut(), LeaveAlternateScreen, Show)?;
     terminal.show_cursor()?;
+    if let Err(e) = journaled {
+        eprintln!("Warning: could not write the journal: {e}");
+    }
 
     result.map_err(Into::into)
 }
 
-fn run_loop(
-    app: &mut App,
-    terminal: &mut Terminal<CrosstermBackend<io::Stdout>>,
-) -> io::Result<()> {
-    loop {
-        match app.run(terminal)? {
-            AppAction::Quit => break,
-            AppAction::Install => {
-                install_package(app, terminal)?;
+/// Print the journal as a table.
+fn print_history(limit: Option<usize>) {
+    let entries = read_journal();
+    if entries.is_empty() {
+        println!("No installs or removals recorded yet.");
+        return;
+    }
+    let skip = limit.map_or(0, |n| entries.len().saturating_sub(n));
+    for entry in &entries[skip..] {
+        let op = match entry.operation {
+            Operation::Install => "install",
+            Operation::Remove => "remove",
+        };
+        let outcome = match entry.exit_code {
+            Some(0) => "ok".to_string(),
+            Some(code) => format!("exit {code}"),
+            None => "killed".to_string(),
+        };
+        println!(
+            "{}  {op:<7}  {outcome:<7}  {}{}{} via {}, {:.1}s{}",
+            format_timestamp(entry.timestamp),
+            entry.package,
+            if entry.version.is_empty() { String::new() } else { format!(" {}", entry.version) },
+            if entry.source.is_empty() { String::new() } else { format!(" [{}]", entry.source) },
+            entry.provider,
+            entry.duration_ms as f64 / 1000.0,
+            if entry.user.is_empty() { String::new() } else { format!(" by {}", entry.user) },
+        );
+        println!("    {}", entry.command);
+    }
+}
+
+/// Write the provider's installed packages in `format` to `output` or stdout.
+fn export(provider: &dyn fex::Provider, format: ExportFormat, output: Option<PathBuf>) {
+    if let Some(providers) = format.providers()
+        && !providers.contains(&provider.name())
+    {
+        eprintln!(
+            "The {format} format only takes {} packages; use --format fex for {}.",
+            providers.join("/"),
+            provider.name()
+        );
+        std::process::exit(1);
+    }
+    let packages = match provider.list_installed() {
+        Some(Ok(packages)) => packages,
+        Some(Err(e)) => {
+            eprintln!("Error: {e}");
+            std::process::exit(1);
+        }
+        None => {
+            eprintln!("{} can't list installed packages.", provider.name());
+            std::process::exit(1);
+        }
+    };
+    let text = format.render(provider.name(), &packages);
+    let exported = packages.iter().filter(|pkg| format.includes(provider.name(), pkg)).count();
+    let left_out = packages.len() - exported;
+    match output {
+        Some(path) => {
+            if let Err(e) = std::fs::write(&path, text) {
+                eprintln!("Error: could not write {}: {e}", path.display());
+                std::process::exit(1);
             }
+            eprintln!("Exported {} to {}", count(exported), path.display());
+            eprintln!("Install with: {}", format.install_hint(&path.display().to_string()));
         }
+        None => print!("{text}"),
+    }
+    if left_out > 0 {
+        eprintln!("Left out {} that the {format} format can't install.", count(left_out));
     }
-    Ok(())
 }
 
-fn install_package(
-    app: &mut App,
-    terminal: &mut Terminal<CrosstermBackend<io::Stdout>>,
-) -> io::Result<()> {
-    if app.packages.is_empty() || app.selected >= app.packages.len() {
-        return Ok(());
+/// Print the names `name` has elsewhere, or only for provider `to`.
+fn translate(name: &str, to: Option<&str>, from: Option<&str>) -> i32 {
+    let translations = Translations::load();
+    let names: Vec<String> = match to {
+        Some(to) => translations.translate(name, from, to).into_iter().map(String::from).collect(),
+        None => translations
+            .equivalents(name, from)
+            .into_iter()
+            .map(|(eco, other)| format!("{eco:<8}{other}"))
+            .collect(),
+    };
+    if names.is_empty() {
+        let target = to.map(|to| format!(" on {to}")).unwrap_or_default();
+        eprintln!("No known name for {name}{target}.");
+        return 1;
     }
+    for line in names {
+        println!("{line}");
+    }
+    0
+}
 
-    let pkg = &app.packages[app.selected];
-    let cmd = app.provider.install_command(pkg);
-    let pkg_name = pkg.name.clone();
-    let pkg_source = pkg.source.clone();
-    let pkg_idx = app.selected;
+/// Names of the provider's installed packages, falling back to the ones
+/// the user installed when it can't list dependencies.
+fn installed_names(provider: &dyn fex::Provider) -> Option<Arc<HashSet<String>>> {
+    provider.installed_names().or_else(|| match provider.list_installed()? {
+        Ok(packages) => Some(Arc::new(packages.into_iter().map(|p| p.name).collect())),
+        Err(e) => {
+            println!("{}: {e}", provider.name());
+            None
+        }
+    })
+}
 
-    // Leave TUI
-    disable_raw_mode()?;
-    execute!(terminal.backend_mut(), LeaveAlternateScreen, Show)?;
+/// The versions of the packages the user installed, where the provider can
+/// list them; empty otherwise.
+fn installed_versions(provider: &dyn fex::Provider) -> HashMap<String, String> {
+    match provider.list_installed() {
+        Some(Ok(packages)) => packages.into_iter().map(|p| (p.name, p.version)).collect(),
+        _ => HashMap::new(),
+    }
+}
 
-    // Run the install command
-    println!("\nInstalling {pkg_name} from {pkg_source}...\n");
-    let status = std::process::Command::new("sh")
-        .args(["-c", &cmd])
-        .status()
-        .ok();
+/// Install the packages of a manifest that are missing, provider by provider.
+/// Returns the exit code: non-zero if anything could not be installed.
+fn apply(path: &Path, dry_run: bool, yes: bool) -> i32 {
+    let manifest = match Manifest::load(path) {
+        Ok(manifest) => manifest,
+        Err(e) => {
+            eprintln!("Error: {e}");
+            return 1;
+        }
+    };
 
-    // Wait for user acknowledgement
-    println!("\nPress Enter to return...");
-    io::stdout().flush()?;
-    let mut line = String::new();
-    io::stdin().read_line(&mut line)?;
+    let mut failed = false;
+    let mut missing = Vec::new();
+    for (name, entries) in manifest.by_provider() {
+        let skip = |reason: &str| println!("{name}: {reason}, skipping {}", count(entries.len()));
+        let provider = match providers::create_provider(name) {
+            Some(p) if p.is_available() => p,
+            Some(_) => {
+                skip("not available on this system");
+                failed = true;
+                continue;
+            }
+            None => {
+                skip("unknown provider");
+                failed = true;
+                continue;
+            }
+        };
+        let Some(installed) = installed_names(provider.as_ref()) else {
+            skip("can't tell which packages are installed");
+            failed = true;
+            continue;
+        };
+        let (present, absent): (Vec<_>, Vec<_>) =
+            entries.into_iter().partition(|e| installed.contains(&e.name));
+        let versions = if present.iter().any(|e| e.version.is_some()) {
+            installed_versions(provider.as_ref())
+        } else {
+            HashMap::new()
+        };
+        // (name, version in the manifest, installed version)
+        let changed: Vec<(&str, &str, &str)> = present
+            .iter()
+            .filter_map(|e| {
+                let wanted = e.version.as_deref()?;
+                let current = versions.get(&e.name).filter(|v| !v.is_empty())?;
+                (wanted != current).then_some((e.name.as_str(), wanted, current.as_str()))
+            })
+            .collect();
+        let other = if changed.is_empty() {
+            String::new()
+        } else {
+            format!(" ({} at another version)", changed.len())
+        };
+        println!("{name}: {} installed{other}, {} missing", present.len(), absent.len());
+        for (pkg, wanted, current) in &changed {
+            println!("  ~ {pkg} {wanted}, installed {current}");
+        }
+        for entry in &absent {
+            let version = entry.version.as_deref().map(|v| format!(" {v}")).unwrap_or_default();
+            println!("  + {}{version}", entry.name);
+        }
+        if !absent.is_empty() {
+            missing.push((provider, absent));
+        }
+    }
 
-    // Re-enter TUI
-    enable_raw_mode()?;
-    execute!(terminal.backend_mut(), EnterAlternateScreen, Hide)?;
-    terminal.clear()?;
+    let total: usize = missing.iter().map(|(_, entries)| entries.len()).sum();
+    if total == 0 {
+        println!("Nothing to install.");
+        return i32::from(failed);
+    }
+    if dry_run {
+        return i32::from(failed);
+    }
+    if !yes && !ask(&format!("Install {}?", count(total))) {
+        println!("Cancelled.");
+        return 1;
+    }
 
-    // Update installed status
-    let success = status.map(|s| s.success()).unwrap_or(false);
-    if success {
-        app.packages[pkg_idx].installed = true;
-        app.status_message = format!("Successfully installed {pkg_name}");
-    } else {
-        app.status_message = format!("Installation of {pkg_name} may have failed");
+    for (provider, entries) in missing {
+        for entry in entries {
+            let pkg = entry.package();
+            let cmd = provider.install_command(&pkg);
+            let started = SystemTime::now();
+            let code = perform(provider.as_ref(), Operation::Install, &pkg);
+            let journal = JournalEntry::new(
+                Operation::Install,
+                provider.name(),
+                &pkg,
+                &cmd,
+                started,
+                Some(code),
+            );
+            if let Err(e) = journal.record() {
+                eprintln!("Warning: could not write the journal: {e}");
+            }
+            failed |= code != 0;
+        }
+    }
+    i32::from(failed)
+}
+
+/// "1 package", "2 packages".
+fn count(n: usize) -> String {
+    if n == 1 { "1 package".to_string() } else { format!("{n} packages") }
+}
+
+/// Ask a yes/no question on the terminal; anything but yes is no.
+fn ask(question: &str) -> bool {
+    print!("{question} [y/N] ");
+    io::stdout().flush().ok();
+    let mut answer = String::new();
+    io::stdin().lock().read_line(&mut answer).ok();
+    matches!(answer.trim(), "y" | "Y" | "yes")
+}
+
+/// Run `--run OPERATION --package JSON` for the TUI's terminal pane.
+fn run_operation(provider: &dyn fex::Provider, op: &str, package: &str) -> i32 {
+    let pkg: fex::Package = match serde_json::from_str(package) {
+        Ok(pkg) => pkg,
+        Err(e) => {
+            eprintln!("Invalid package: {e}");
+            return 1;
+        }
+    };
+    match op {
+        "install" => perform(provider, Operation::Install, &pkg),
+        "remove" => perform(provider, Operation::Remove, &pkg),
+        _ => {
+            eprintln!("Unknown operation '{op}'.");
+            1
+        }
     }
+}
 
-    Ok(())
+/// Install or remove a package in the foreground, printing progress.
+/// Returns the exit code of the command that ran.
+fn perform(provider: &dyn fex::Provider, op: Operation, pkg: &fex::Package) -> i32 {
+    let (cmd, managed) = match op {
+        Operation::Install => {
+            match pkg.source.as_str() {
+                "" => println!("Installing {}...\n", pkg.name),
+                source => println!("Installing {} from {source}...\n", pkg.name),
+            }
+            let managed = provider.install(pkg, &mut |message| println!("{message}"));
+            (Some(provider.install_command(pkg)), managed)
+        }
+        Operation::Remove => {
+            println!("Removing {}...\n", pkg.name);
+            (provider.remove_command(pkg), provider.remove(pkg))
+        }
+    };
+    let code = match (managed, cmd) {
+        (Some(Ok(())), _) => 0,
+        (Some(Err(e)), _) => {
+            println!("Error: {e}");
+            1
+        }
+        // Killed by a signal counts as a failure
+        (None, Some(cmd)) => std::process::Command::new("sh")
+            .args(["-c", &cmd])
+            .status()
+            .map(|s| s.code().unwrap_or(1))
+            .unwrap_or(1),
+        (None, None) => {
+            println!("{} does not support removing packages.", provider.name());
+            1
+        }
+    };
+    println!("\n{}", if code == 0 { "Done." } else { "Failed." });
+    code
 }
@@ -0,0 +1,319 @@
+//! Package lists that recreate a setup on another machine: fex's own TOML
+//! manifest, which records the provider of every package, and the native
+//! list formats of the package managers.
+//!
+//! ```toml
+//! [[package]]
+//! provider = "pacman"
+//! name = "firefox"
+//! version = "128.0-1"
+//! ```
+
+use std::fmt;
+use std::fs;
+use std::path::Path;
+use std::str::FromStr;
+
+use serde::{Deserialize, Serialize};
+
+use crate::provider::Package;
+
+#[derive(Clone, Debug, Default, PartialEq, Serialize, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct Manifest {
+    #[serde(rename = "package", default)]
+    pub packages: Vec<ManifestEntry>,
+}
+
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct ManifestEntry {
+    pub provider: String,
+    pub name: String,
+    /// The version that was installed when the manifest was exported.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub version: Option<String>,
+    /// Repository, remote or kind of package, where the provider needs it
+    /// (brew casks, flatpak remotes).
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub source: Option<String>,
+}
+
+impl Manifest {
+    pub fn from_packages(provider: &str, packages: &[Package]) -> Manifest {
+        let non_empty = |s: &str| (!s.is_empty()).then(|| s.to_string());
+        let packages = packages
+            .iter()
+            .map(|pkg| ManifestEntry {
+                provider: provider.to_string(),
+                name: pkg.name.clone(),
+                version: non_empty(&pkg.version),
+                source: non_empty(&pkg.source),
+            })
+            .collect();
+        Manifest { packages }
+    }
+
+    /// Read a manifest, with the file name in error messages.
+    pub fn load(path: &Path) -> Result<Manifest, String> {
+        let data = fs::read_to_string(path).map_err(|e| format!("{}: {e}", path.display()))?;
+        toml::from_str(&data).map_err(|e| format!("{}: {e}", path.display()))
+    }
+
+    /// The entries grouped by provider, in the order providers first appear.
+    pub fn by_provider(&self) -> Vec<(&str, Vec<&ManifestEntry>)> {
+        let mut groups: Vec<(&str, Vec<&ManifestEntry>)> = Vec::new();
+        for entry in &self.packages {
+            match groups.iter_mut().find(|(provider, _)| *provider == entry.provider) {
+                Some((_, entries)) => entries.push(entry),
+                None => groups.push((&entry.provider, vec![entry])),
+            }
+        }
+        groups
+    }
+}
+
+impl ManifestEntry {
+    /// The package to hand to the provider for installing. The version is
+    /// only informational; the provider installs its current one.
+    pub fn package(&self) -> Package {
+        Package {
+            name: self.name.clone(),
+            source: self.source.clone().unwrap_or_default(),
+            ..Default::default()
+        }
+    }
+}
+
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum ExportFormat {
+    /// fex's own TOML manifest, see `Manifest`.
+    #[default]
+    Fex,
+    /// `brew bundle` input.
+    Brewfile,
+    /// Names for `pacman -S --needed - < pacman.txt`, without AUR packages.
+    Pacman,
+    /// Names for `xargs sudo apt-get install -y < apt.txt`.
+    Apt,
+    /// `.flatpakref` URLs for `flatpak install`, for apps from Flathub.
+    Flatpak,
+    /// A NixOS module adding the packages to `environment.systemPackages`.
+    Nix,
+}
+
+impl ExportFormat {
+    pub const ALL: [ExportFormat; 6] = [
+        ExportFormat::Fex,
+        ExportFormat::Brewfile,
+        ExportFormat::Pacman,
+        ExportFormat::Apt,
+        ExportFormat::Flatpak,
+        ExportFormat::Nix,
+    ];
+
+    pub fn label(self) -> &'static str {
+        match self {
+            ExportFormat::Fex => "fex",
+            ExportFormat::Brewfile => "brewfile",
+            ExportFormat::Pacman => "pacman",
+            ExportFormat::Apt => "apt",
+            ExportFormat::Flatpak => "flatpak",
+            ExportFormat::Nix => "nix",
+        }
+    }
+
+    /// The conventional file name for the format.
+    pub fn file_name(self) -> &'static str {
+        match self {
+            ExportFormat::Fex => "fex.toml",
+            ExportFormat::Brewfile => "Brewfile",
+            ExportFormat::Pacman => "pacman.txt",
+            ExportFormat::Apt => "apt.txt",
+            ExportFormat::Flatpak => "flatpak.txt",
+            ExportFormat::Nix => "packages.nix",
+        }
+    }
+
+    /// How to install the packages of the exported `file`, for telling the
+    /// user. Not part of the file, so that it can be fed to the command as is.
+    pub fn install_hint(self, file: &str) -> String {
+        match self {
+            ExportFormat::Fex => format!("fex apply {file}"),
+            ExportFormat::Brewfile => format!("brew bundle --file {file}"),
+            ExportFormat::Pacman => format!("sudo pacman -S --needed - < {file}"),
+            ExportFormat::Apt => format!("xargs sudo apt-get install -y < {file}"),
+            ExportFormat::Flatpak => format!("xargs -n1 flatpak install -y < {file}"),
+            ExportFormat::Nix => format!("import {file} from configuration.nix"),
+        }
+    }
+
+    /// The providers whose packages the format can install, or `None` for
+    /// fex's own manifest, which takes any.
+    pub fn providers(self) -> Option<&'static [&'static str]> {
+        match self {
+            ExportFormat::Fex => None,
+            ExportFormat::Brewfile => Some(&["brew", "zerobrew"]),
+            ExportFormat::Pacman => Some(&["pacman", "paru", "yay"]),
+            ExportFormat::Apt => Some(&["apt"]),
+            ExportFormat::Flatpak => Some(&["flatpak"]),
+            ExportFormat::Nix => Some(&["nix"]),
+        }
+    }
+
+    /// Whether `pkg` of `provider` can be written in this format. Native
+    /// formats only take their own package manager's names, AUR packages
+    /// can't be installed by pacman, and `.flatpakref` URLs are only known
+    /// for Flathub.
+    pub fn includes(self, provider: &str, pkg: &Package) -> bool {
+        if self.providers().is_some_and(|providers| !providers.contains(&provider)) {
+            return false;
+        }
+        match self {
+            ExportFormat::Pacman => pkg.source != "aur",
+            ExportFormat::Flatpak => flatpakref_url(pkg).is_some(),
+            _ => true,
+        }
+    }
+
+    /// The packages of `provider` in this format, leaving out those it
+    /// doesn't `include`.
+    pub fn render(self, provider: &str, packages: &[Package]) -> String {
+        let mut out = String::new();
+        let mut line = |text: String| {
+            out.push_str(&text);
+            out.push('\n');
+        };
+        let included = packages.iter().filter(|pkg| self.includes(provider, pkg));
+        match self {
+            ExportFormat::Fex => {
+                let manifest = Manifest::from_packages(provider, packages);
+                return toml::to_string(&manifest).unwrap_or_default();
+            }
+            ExportFormat::Brewfile => {
+                for pkg in included {
+                    let kind = if pkg.source == "cask" { "cask" } else { "brew" };
+                    line(format!("{kind} \"{}\"", pkg.name));
+                }
+            }
+            ExportFormat::Pacman | ExportFormat::Apt => {
+                included.for_each(|pkg| line(pkg.name.clone()));
+            }
+            ExportFormat::Flatpak => included.filter_map(flatpakref_url).for_each(line),
+            ExportFormat::Nix => {
+                line("# Import from configuration.nix, or use the list in home.packages".into());
+                line("{ pkgs, ... }:".to_string());
+                line("{".to_string());
+                line("  environment.systemPackages = with pkgs; [".to_string());
+                included.for_each(|pkg| line(format!("    {}", pkg.name)));
+                line("  ];".to_string());
+                line("}".to_string());
+            }
+        }
+        out
+    }
+}
+
+/// Where Flathub publishes the `.flatpakref` of an app from the "flathub" or
+/// "flathub-beta" remote; apps without a remote are taken to be from Flathub.
+fn flatpakref_url(pkg: &Package) -> Option<String> {
+    let repo = match pkg.source.as_str() {
+        "" | "flathub" => "repo",
+        "flathub-beta" => "beta-repo",
+        _ => return None,
+    };
+    Some(format!("https://dl.flathub.org/{repo}/appstream/{}.flatpakref", pkg.name))
+}
+
+impl fmt::Display for ExportFormat {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(self.label())
+    }
+}
+
+impl FromStr for ExportFormat {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let s = s.trim().to_lowercase();
+        ExportFormat::ALL.into_iter().find(|f| f.label() == s).ok_or_else(|| {
+            let labels: Vec<&str> = ExportFormat::ALL.iter().map(|f| f.label()).collect();
+            format!("unknown format '{s}', expected one of: {}", labels.join(", "))
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn pkg(name: &str, version: &str, source: &str) -> Package {
+        Package {
+            name: name.to_string(),
+            version: version.to_string(),
+            source: source.to_string(),
+            installed: true,
+            ..Default::default()
+        }
+    }
+
+    #[test]
+    fn renders_native_formats() {
+        let packages = [pkg("git", "2.45", "formula"), pkg("firefox", "", "cask")];
+        assert_eq!(
+            ExportFormat::Brewfile.render("brew", &packages),
+            "brew \"git\"\ncask \"firefox\"\n"
+        );
+        let pacman = [pkg("git", "2.45", "local"), pkg("yay", "12.4", "aur")];
+        assert_eq!(ExportFormat::Pacman.render("pacman", &pacman), "git\n");
+        let flatpak = [pkg("org.gimp.GIMP", "2.10", "flathub"), pkg("org.foo.Bar", "", "fedora")];
+        assert_eq!(
+            ExportFormat::Flatpak.render("flatpak", &flatpak),
+            "https://dl.flathub.org/repo/appstream/org.gimp.GIMP.flatpakref\n"
+        );
+        assert!(!ExportFormat::Flatpak.includes("flatpak", &flatpak[1]));
+        assert_eq!(ExportFormat::Brewfile.render("apt", &[pkg("libssl-dev", "3.0", "")]), "");
+        assert!(!ExportFormat::Nix.includes("pacman", &packages[0]));
+        assert!(ExportFormat::Fex.includes("apt", &packages[0]));
+        let nix = ExportFormat::Nix.render("nix", &packages[..1]);
+        assert!(nix.contains("with pkgs; [\n    git\n  ];"));
+        assert_eq!("Brewfile".parse(), Ok(ExportFormat::Brewfile));
+    }
+
+    #[test]
+    fn round_trips_fex_manifest() {
+        let packages = [pkg("org.gimp.GIMP", "2.10", "flathub"), pkg("ripgrep", "", "")];
+        let text = ExportFormat::Fex.render("flatpak", &packages);
+        let manifest: Manifest = toml::from_str(&text).unwrap();
+        assert_eq!(manifest, Manifest::from_packages("flatpak", &packages));
+        assert_eq!(manifest.packages[0].source.as_deref(), Some("flathub"));
+        assert_eq!(manifest.packages[1].version, None);
+    }
+
+    #[test]
+    fn groups_entries_by_provider() {
+        let text = r#"
+            [[package]]
+            provider = "apt"
+            name = "git"
+            [[package]]
+            provider = "flatpak"
+            name = "org.gimp.GIMP"
+            source = "flathub"
+            [[package]]
+            provider = "apt"
+            name = "curl"
+            version = "8.5"
+        "#;
+        let manifest: Manifest = toml::from_str(text).unwrap();
+        let groups = manifest.by_provider();
+        let names: Vec<(&str, Vec<&str>)> = groups
+            .iter()
+            .map(|(provider, entries)| {
+                (*provider, entries.iter().map(|e| e.name.as_str()).collect())
+            })
+            .collect();
+        assert_eq!(names, [("apt", vec!["git", "curl"]), ("flatpak", vec!["org.gimp.GIMP"])]);
+        assert_eq!(groups[1].1[0].package().source, "flathub");
+    }
+}
@@ -1,16 +1,122 @@
-use ratatui::style::Color;
+use std::collections::HashSet;
+use std::str::FromStr;
+use std::sync::Arc;
+use std::time::Duration;
 
+use serde::{Deserialize, Serialize};
+
+use crate::error::SearchError;
+
+/// Terminal color for source badges. Kept independent of any UI library so
+/// headless users of the crate don't need one.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Color {
+    Black,
+    Red,
+    Green,
+    Yellow,
+    Blue,
+    Magenta,
+    Cyan,
+    Gray,
+    DarkGray,
+    LightRed,
+    LightGreen,
+    LightYellow,
+    LightBlue,
+    LightMagenta,
+    LightCyan,
+    White,
+    Rgb(u8, u8, u8),
+}
+
+impl FromStr for Color {
+    type Err = ();
+
+    /// Parses color names ("blue", "light-blue", "lightblue") and "#rrggbb".
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let name = s.trim().to_lowercase().replace(['-', '_', ' '], "");
+        let color = match name.as_str() {
+            "black" => Color::Black,
+            "red" => Color::Red,
+            "green" => Color::Green,
+            "yellow" => Color::Yellow,
+            "blue" => Color::Blue,
+            "magenta" => Color::Magenta,
+            "cyan" => Color::Cyan,
+            "gray" | "grey" => Color::Gray,
+            "darkgray" | "darkgrey" => Color::DarkGray,
+            "lightred" => Color::LightRed,
+            "lightgreen" => Color::LightGreen,
+            "lightyellow" => Color::LightYellow,
+            "lightblue" => Color::LightBlue,
+            "lightmagenta" => Color::LightMagenta,
+            "lightcyan" => Color::LightCyan,
+            "white" => Color::White,
+            hex if hex.len() == 7 && hex.starts_with('#') => {
+                let channel = |i: usize| u8::from_str_radix(&hex[i..i + 2], 16).map_err(|_| ());
+                Color::Rgb(channel(1)?, channel(3)?, channel(5)?)
+            }
+            _ => return Err(()),
+        };
+        Ok(color)
+    }
+}
+
+#[derive(Clone, Debug, Default, Serialize, Deserialize)]
+#[non_exhaustive]
 pub struct Package {
     pub name: String,
     pub version: String,
     pub description: String,
     pub source: String,
     pub installed: bool,
+    /// How popular the package is, where the provider reports it (e.g. AUR
+    /// popularity). Only comparable between packages of one provider.
+    #[serde(default)]
+    pub popularity: Option<f64>,
+    /// Paths that matched a file search (see `Provider::search_file`).
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub files: Vec<String>,
+}
+
+impl Package {
+    /// A package with just a name and source; the other fields can be set
+    /// afterwards.
+    pub fn new(name: impl Into<String>, source: impl Into<String>) -> Package {
+        Package { name: name.into(), source: source.into(), ..Default::default() }
+    }
 }
 
 pub struct SearchResult {
     pub packages: Vec<Package>,
-    pub error: Option<String>,
+    pub error: Option<SearchError>,
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum PlanAction {
+    Install,
+    Upgrade,
+    Remove,
+}
+
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct PlanEntry {
+    pub action: PlanAction,
+    pub name: String,
+    /// Version after the transaction, or the removed one; may be empty.
+    pub version: String,
+}
+
+/// What an install would change, from a dry run of the package manager.
+#[derive(Clone, Debug, Default)]
+pub struct InstallPlan {
+    /// The requested packages and everything pulled in or removed with them.
+    pub entries: Vec<PlanEntry>,
+    /// Bytes to download, if reported.
+    pub download_size: Option<u64>,
+    /// Change in disk usage in bytes, negative if space is freed, if reported.
+    pub install_size: Option<i64>,
 }
 
 pub trait Provider: Send + Sync {
@@ -19,6 +125,33 @@ pub trait Provider: Send + Sync {
     fn search(&self, query: &str) -> SearchResult;
     fn install_command(&self, pkg: &Package) -> String;
 
+    /// Command that removes an installed package, or `None` if unsupported.
+    fn remove_command(&self, _pkg: &Package) -> Option<String> {
+        None
+    }
+
+    /// Install in-process instead of running `install_command` in a shell,
+    /// passing progress messages to `progress` for the caller to show.
+    /// Providers that return `Some` use `install_command` as a description only.
+    fn install(
+        &self,
+        _pkg: &Package,
+        _progress: &mut dyn FnMut(&str),
+    ) -> Option<Result<(), String>> {
+        None
+    }
+
+    /// Remove in-process instead of running `remove_command` in a shell.
+    fn remove(&self, _pkg: &Package) -> Option<Result<(), String>> {
+        None
+    }
+
+    /// Whether `install` and `remove` work in-process, so that the commands
+    /// only describe them and can't be edited and run in a shell instead.
+    fn installs_in_process(&self) -> bool {
+        false
+    }
+
     fn source_color(&self, source: &str) -> Color {
         match source {
             "core" => Color::Cyan,
@@ -29,6 +162,73 @@ pub trait Provider: Send + Sync {
             _ => Color::White,
         }
     }
+
+    /// How long a search may run before it is cancelled as timed out.
+    fn search_timeout(&self) -> Duration {
+        Duration::from_secs(30)
+    }
+
+    /// Every package the provider offers, for the offline index (see
+    /// `crate::index`). `None` means the provider is always searched live.
+    fn list_all(&self) -> Option<Result<Vec<Package>, SearchError>> {
+        None
+    }
+
+    /// Whether `list_all` is implemented, so that no index is built for
+    /// providers without one.
+    fn can_list_all(&self) -> bool {
+        false
+    }
+
+    /// The packages the user installed, leaving out those pulled in as
+    /// dependencies where the provider can tell, for exporting manifests
+    /// (see `crate::manifest`). `None` means the provider can't list them.
+    fn list_installed(&self) -> Option<Result<Vec<Package>, SearchError>> {
+        None
+    }
+
+    /// Names of every installed package, dependencies included, to tell
+    /// which packages of a manifest are missing. `None` if unknown.
+    fn installed_names(&self) -> Option<Arc<HashSet<String>>> {
+        None
+    }
+
+    /// The packages `pkg` directly depends on. Where the provider only
+    /// reports names, the other fields are left empty. `None` means the
+    /// provider can't tell.
+    fn dependencies(&self, _pkg: &Package) -> Option<Result<Vec<Package>, SearchError>> {
+        None
+    }
+
+    /// The packages that directly depend on `pkg`, like `dependencies`.
+    fn reverse_dependencies(&self, _pkg: &Package) -> Option<Result<Vec<Package>, SearchError>> {
+        None
+    }
+
+    /// The files `pkg` installs, as absolute paths. Installed packages are
+    /// listed from the local database, others from the repositories where
+    /// the provider can. `None` means the provider can't list files.
+    fn list_files(&self, _pkg: &Package) -> Option<Result<Vec<String>, SearchError>> {
+        None
+    }
+
+    /// Dry run of installing `pkgs`: what would be installed, upgraded or
+    /// removed along with them. `None` means the provider can't tell.
+    fn plan_install(&self, _pkgs: &[Package]) -> Option<Result<InstallPlan, SearchError>> {
+        None
+    }
+
+    /// Packages containing `path`, an absolute path or a bare command name,
+    /// with the paths that matched in `Package::files`. `None` means the
+    /// provider can't search by file.
+    fn search_file(&self, _path: &str) -> Option<Result<Vec<Package>, SearchError>> {
+        None
+    }
+
+    /// Extra context for the status bar, e.g. the active environment.
+    fn status_context(&self) -> Option<String> {
+        None
+    }
 }
 
 pub type BoxedProvider = Box<dyn Provider>;
@@ -1,15 +1,20 @@
 use std::collections::HashSet;
+use std::sync::Arc;
 
-use ratatui::style::Color;
-
-use crate::provider::{Package, Provider, SearchResult};
-use crate::util::{command_exists, escape_query, exec_command, sort_by_relevance};
+use crate::error::SearchError;
+use crate::provider::{Color, Package, Provider, SearchResult};
+use crate::util::{
+    cached_installed, command_exists, escape_query, exec_command, exec_search, exec_search_lines,
+    group_file_matches, parse_file_list, shell_quote, sort_by_relevance,
+};
 
 pub struct ApkProvider;
 
-fn get_installed() -> HashSet<String> {
-    let output = exec_command("apk info 2>/dev/null");
-    output.lines().filter(|l| !l.is_empty()).map(|l| l.to_string()).collect()
+fn get_installed() -> Arc<HashSet<String>> {
+    cached_installed("apk", || {
+        let output = exec_command("apk info 2>/dev/null");
+        output.lines().filter(|l| !l.is_empty()).map(|l| l.to_string()).collect()
+    })
 }
 
 /// Split "name-version" by the last hyphen followed by a digit.
@@ -25,6 +30,20 @@ fn split_name_version(s: &str) -> (&str, &str) {
     (s, "")
 }
 
+/// Parse a line of `apk search -v` output.
+fn parse_line(line: &str) -> Option<Package> {
+    // Format: package-name-version - description
+    let (name_version, description) = line.split_once(" - ")?;
+    let (name, version) = split_name_version(name_version);
+    Some(Package {
+        name: name.to_string(),
+        version: version.to_string(),
+        description: description.to_string(),
+        source: "alpine".to_string(),
+        ..Default::default()
+    })
+}
+
 impl Provider for ApkProvider {
     fn name(&self) -> &str {
         "apk"
@@ -40,42 +59,112 @@ impl Provider for ApkProvider {
         }
 
         let escaped = escape_query(query);
-        let output = exec_command(&format!("apk search -v '{escaped}' 2>/dev/null"));
-        if output.is_empty() {
-            return SearchResult { packages: vec![], error: None };
-        }
+        let installed = get_installed();
+        let mut packages = match exec_search_lines(
+            &format!("apk search -v '{escaped}'"),
+            &[],
+            |line| {
+                let mut pkg = parse_line(line)?;
+                pkg.installed = installed.contains(&pkg.name);
+                Some(pkg)
+            },
+        ) {
+            Ok(packages) => packages,
+            Err(e) => return SearchResult { packages: vec![], error: Some(e) },
+        };
 
+        sort_by_relevance(&mut packages, query);
+        SearchResult { packages, error: None }
+    }
+
+    fn list_all(&self) -> Option<Result<Vec<Package>, SearchError>> {
         let installed = get_installed();
-        let mut packages = Vec::new();
-
-        for line in output.lines() {
-            if line.is_empty() {
-                continue;
-            }
-            // Format: package-name-version - description
-            let Some(sep) = line.find(" - ") else { continue };
-            let name_version = &line[..sep];
-            let description = line[sep + 3..].to_string();
-
-            let (name, version) = split_name_version(name_version);
-            let is_installed = installed.contains(name);
-            packages.push(Package {
-                name: name.to_string(),
-                version: version.to_string(),
-                description,
-                source: "alpine".to_string(),
-                installed: is_installed,
+        // Without a pattern, apk lists every available package
+        Some(exec_search_lines("apk search -v", &[], |line| {
+            let mut pkg = parse_line(line)?;
+            pkg.installed = installed.contains(&pkg.name);
+            Some(pkg)
+        }))
+    }
+
+    fn can_list_all(&self) -> bool {
+        true
+    }
+
+    /// The world file lists what was added explicitly, possibly with
+    /// constraints such as "foo>=1.2" or "foo@testing".
+    fn list_installed(&self) -> Option<Result<Vec<Package>, SearchError>> {
+        let result = exec_search("cat /etc/apk/world", &[]).map(|output| {
+            output
+                .split_whitespace()
+                .filter_map(|entry| {
+                    let name = entry.split(['<', '>', '=', '~', '@']).next()?;
+                    Some(Package {
+                        name: name.to_string(),
+                        source: "alpine".to_string(),
+                        installed: true,
+                        ..Default::default()
+                    })
+                })
+                .collect()
+        });
+        Some(result)
+    }
+
+    fn installed_names(&self) -> Option<Arc<HashSet<String>>> {
+        Some(get_installed())
+    }
+
+    /// Commands are looked up through the repositories' "cmd:" provides;
+    /// paths only among installed packages, which is all apk knows about.
+    fn search_file(&self, path: &str) -> Option<Result<Vec<Package>, SearchError>> {
+        if path.contains('/') {
+            let cmd = format!("apk info --who-owns {}", shell_quote(path));
+            let result = exec_search(&cmd, &["is not owned"]).map(|output| {
+                let owners = output.lines().filter_map(|line| line.split_once(" is owned by "));
+                let matches = owners.map(|(file, owner)| {
+                    let (name, version) = split_name_version(owner.trim());
+                    let pkg = Package {
+                        name: name.to_string(),
+                        version: version.to_string(),
+                        source: "alpine".to_string(),
+                        installed: true,
+                        ..Default::default()
+                    };
+                    (pkg, file.to_string())
+                });
+                group_file_matches(matches)
             });
+            return Some(result);
         }
+        let provide = format!("cmd:{path}");
+        let installed = get_installed();
+        Some(exec_search_lines(&format!("apk search -v {}", shell_quote(&provide)), &[], |line| {
+            let mut pkg = parse_line(line)?;
+            pkg.installed = installed.contains(&pkg.name);
+            pkg.files = vec![provide.clone()];
+            Some(pkg)
+        }))
+    }
 
-        sort_by_relevance(&mut packages, query);
-        SearchResult { packages, error: None }
+    /// apk only knows the files of installed packages.
+    fn list_files(&self, pkg: &Package) -> Option<Result<Vec<String>, SearchError>> {
+        let cmd = format!("apk info --contents {}", shell_quote(&pkg.name));
+        // The list follows a "name-version contains:" line
+        let result = exec_search(&cmd, &[]).map(|output| {
+            parse_file_list(output.lines().filter(|line| !line.ends_with(" contains:")))
+        });
+        Some(result)
     }
 
     fn install_command(&self, pkg: &Package) -> String {
         format!("sudo apk add {}", pkg.name)
     }
 
+    fn remove_command(&self, pkg: &Package) -> Option<String> {
+        Some(format!("sudo apk del {}", pkg.name))
+    }
+
     fn source_color(&self, source: &str) -> Color {
         match source {
             "community" => Color::Yellow,
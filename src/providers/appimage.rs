@@ -0,0 +1,417 @@
+use std::collections::BTreeMap;
+use std::fs;
+use std::os::unix::fs::PermissionsExt;
+use std::path::{Path, PathBuf};
+use std::time::{Duration, SystemTime};
+
+use serde::{Deserialize, Serialize};
+
+use crate::error::SearchError;
+use crate::provider::{Color, Package, Provider, SearchResult};
+use crate::util::{
+    applications_dir, cache_dir, command_exists, data_dir, escape_query, exec_command,
+    exec_command_full, home_dir, sort_by_relevance,
+};
+
+pub struct AppImageProvider;
+
+const FEED_URL: &str = "https://appimage.github.io/feed.json";
+const FEED_MAX_AGE: Duration = Duration::from_secs(24 * 60 * 60);
+
+#[derive(Deserialize)]
+struct Feed {
+    items: Vec<FeedItem>,
+}
+
+#[derive(Deserialize)]
+struct FeedItem {
+    name: String,
+    #[serde(default)]
+    description: Option<String>,
+    #[serde(default)]
+    categories: Option<Vec<String>>,
+    #[serde(default)]
+    links: Option<Vec<FeedLink>>,
+}
+
+#[derive(Deserialize)]
+struct FeedLink {
+    #[serde(rename = "type")]
+    kind: String,
+    url: String,
+}
+
+#[derive(Deserialize)]
+struct Release {
+    tag_name: String,
+    assets: Vec<ReleaseAsset>,
+}
+
+#[derive(Deserialize)]
+struct ReleaseAsset {
+    name: String,
+    browser_download_url: String,
+}
+
+/// fex-owned record of AppImages it has installed.
+#[derive(Default, Serialize, Deserialize)]
+struct State {
+    #[serde(default)]
+    installed: BTreeMap<String, InstalledApp>,
+}
+
+#[derive(Serialize, Deserialize)]
+struct InstalledApp {
+    version: String,
+    path: PathBuf,
+    desktop_file: PathBuf,
+}
+
+fn feed_path() -> PathBuf {
+    cache_dir().join("appimage").join("feed.json")
+}
+
+fn state_path() -> PathBuf {
+    data_dir().join("appimages.json")
+}
+
+fn install_dir() -> PathBuf {
+    home_dir().join("Applications")
+}
+
+/// File-system friendly name, e.g. "Visual Studio Code" → "Visual-Studio-Code".
+fn slug(name: &str) -> String {
+    let slug: String = name
+        .chars()
+        .map(|c| if c.is_ascii_alphanumeric() || c == '.' || c == '_' { c } else { '-' })
+        .collect();
+    slug.trim_matches('-').to_string()
+}
+
+fn is_stale(path: &Path) -> bool {
+    fs::metadata(path)
+        .and_then(|m| m.modified())
+        .ok()
+        .and_then(|modified| SystemTime::now().duration_since(modified).ok())
+        .is_none_or(|age| age > FEED_MAX_AGE)
+}
+
+/// Download the catalog feed if it is missing or older than a day.
+/// A stale copy is still used when the refresh fails (e.g. offline).
+fn refresh_feed() -> Result<(), SearchError> {
+    let path = feed_path();
+    if !is_stale(&path) {
+        return Ok(());
+    }
+    if let Some(dir) = path.parent() {
+        fs::create_dir_all(dir).map_err(|e| {
+            SearchError::Other(format!("Cannot create {}: {e}", dir.display()))
+        })?;
+    }
+    let tmp = path.with_extension("json.tmp");
+    let escaped = escape_query(&tmp.to_string_lossy());
+    let cmd = format!("curl -fsSL -o '{escaped}' '{FEED_URL}'");
+    let (_, stderr, code) = exec_command_full(&cmd);
+    if code == 0 && fs::rename(&tmp, &path).is_ok() {
+        return Ok(());
+    }
+    fs::remove_file(&tmp).ok();
+    if path.exists() {
+        Ok(())
+    } else {
+        Err(SearchError::from_command(&cmd, code, &stderr))
+    }
+}
+
+fn load_feed() -> Result<Vec<FeedItem>, SearchError> {
+    refresh_feed()?;
+    let data = fs::read_to_string(feed_path()).map_err(|e| {
+        SearchError::Other(format!("Could not read the AppImage catalog: {e}"))
+    })?;
+    serde_json::from_str::<Feed>(&data)
+        .map(|feed| feed.items)
+        .map_err(|e| SearchError::ParseFailure(format!("AppImage catalog: {e}")))
+}
+
+fn load_state() -> State {
+    fs::read_to_string(state_path())
+        .ok()
+        .and_then(|data| serde_json::from_str(&data).ok())
+        .unwrap_or_default()
+}
+
+fn save_state(state: &State) -> Result<(), String> {
+    let path = state_path();
+    if let Some(dir) = path.parent() {
+        fs::create_dir_all(dir).map_err(|e| format!("Cannot create {}: {e}", dir.display()))?;
+    }
+    let data = serde_json::to_string_pretty(state).map_err(|e| e.to_string())?;
+    fs::write(&path, data).map_err(|e| format!("Cannot write {}: {e}", path.display()))
+}
+
+/// Crude tag stripper for the HTML snippets used in catalog descriptions.
+fn strip_html(s: &str) -> String {
+    let mut out = String::with_capacity(s.len());
+    let mut in_tag = false;
+    for c in s.chars() {
+        match c {
+            '<' => in_tag = true,
+            '>' => in_tag = false,
+            _ if !in_tag => out.push(if c.is_whitespace() { ' ' } else { c }),
+            _ => {}
+        }
+    }
+    out.split_whitespace().collect::<Vec<_>>().join(" ")
+}
+
+/// "owner/repo" of the GitHub project publishing the AppImage, if any.
+fn github_repo(item: &FeedItem) -> Option<String> {
+    item.links.as_ref()?.iter().find(|l| l.kind == "GitHub").map(|l| {
+        l.url
+            .trim_start_matches("https://github.com/")
+            .trim_end_matches('/')
+            .to_string()
+    })
+}
+
+/// Pick the AppImage asset for `arch`, as in `std::env::consts::ARCH`.
+fn pick_asset<'a>(assets: &'a [ReleaseAsset], arch: &str) -> Option<&'a ReleaseAsset> {
+    let arch_names: &[&str] = match arch {
+        "x86_64" => &["x86_64", "amd64", "x64"],
+        "aarch64" => &["aarch64", "arm64"],
+        "x86" => &["i386", "i686"],
+        _ => &[],
+    };
+    let all_arches = ["x86_64", "amd64", "x64", "aarch64", "arm64", "armhf", "i386", "i686"];
+
+    let appimages: Vec<&ReleaseAsset> = assets
+        .iter()
+        .filter(|a| a.name.to_lowercase().ends_with(".appimage"))
+        .collect();
+
+    appimages
+        .iter()
+        .find(|a| arch_names.iter().any(|arch| a.name.contains(arch)))
+        .or_else(|| {
+            // Unmarked assets are conventionally x86_64 builds
+            appimages
+                .iter()
+                .filter(|_| arch == "x86_64")
+                .find(|a| !all_arches.iter().any(|arch| a.name.contains(arch)))
+        })
+        .copied()
+}
+
+fn desktop_entry(item: &FeedItem, path: &Path) -> String {
+    let comment = item.description.as_deref().map(strip_html).unwrap_or_default();
+    let categories = item
+        .categories
+        .as_ref()
+        .map(|c| c.iter().map(|c| format!("{c};")).collect::<String>())
+        .unwrap_or_default();
+    format!(
+        "[Desktop Entry]\nType=Application\nName={}\nComment={comment}\nExec=\"{}\" %U\n\
+         Terminal=false\nCategories={categories}\nX-Fex-AppImage=true\n",
+        item.name,
+        path.display()
+    )
+}
+
+fn install_appimage(name: &str, progress: &mut dyn FnMut(&str)) -> Result<(), String> {
+    let feed = load_feed().map_err(|e| e.to_string())?;
+    let item = feed
+        .iter()
+        .find(|i| i.name == name)
+        .ok_or_else(|| format!("{name} is not in the AppImage catalog"))?;
+    let repo = github_repo(item)
+        .ok_or_else(|| format!("{name} has no GitHub releases to download from"))?;
+
+    let release_json =
+        exec_command(&format!("curl -fsSL 'https://api.github.com/repos/{repo}/releases/latest'"));
+    let release: Release = serde_json::from_str(&release_json)
+        .map_err(|_| format!("Could not fetch the latest release of {repo}"))?;
+    let asset = pick_asset(&release.assets, std::env::consts::ARCH)
+        .ok_or_else(|| format!("The latest release of {repo} has no AppImage for this machine"))?;
+
+    let dir = install_dir();
+    fs::create_dir_all(&dir).map_err(|e| format!("Cannot create {}: {e}", dir.display()))?;
+    let path = dir.join(format!("{}.AppImage", slug(name)));
+
+    progress(&format!("Downloading {} ({})...", asset.name, release.tag_name));
+    let output = std::process::Command::new("curl")
+        .args(["-fsSL", "-o"])
+        .arg(&path)
+        .arg(&asset.browser_download_url)
+        .output()
+        .map_err(|e| format!("Could not run curl: {e}"))?;
+    if !output.status.success() {
+        fs::remove_file(&path).ok();
+        let stderr = String::from_utf8_lossy(&output.stderr);
+        return Err(format!("Download of {} failed: {}", asset.name, stderr.trim()));
+    }
+    fs::set_permissions(&path, fs::Permissions::from_mode(0o755))
+        .map_err(|e| format!("Cannot make {} executable: {e}", path.display()))?;
+
+    let apps = applications_dir();
+    fs::create_dir_all(&apps).map_err(|e| format!("Cannot create {}: {e}", apps.display()))?;
+    let desktop_file = apps.join(format!("fex-appimage-{}.desktop", slug(name)));
+    fs::write(&desktop_file, desktop_entry(item, &path))
+        .map_err(|e| format!("Cannot write {}: {e}", desktop_file.display()))?;
+
+    let mut state = load_state();
+    state.installed.insert(
+        name.to_string(),
+        InstalledApp { version: release.tag_name.clone(), path, desktop_file },
+    );
+    save_state(&state)
+}
+
+fn remove_appimage(name: &str) -> Result<(), String> {
+    let mut state = load_state();
+    let app = state
+        .installed
+        .remove(name)
+        .ok_or_else(|| format!("{name} was not installed by fex"))?;
+    for file in [&app.path, &app.desktop_file] {
+        if let Err(e) = fs::remove_file(file)
+            && e.kind() != std::io::ErrorKind::NotFound
+        {
+            return Err(format!("Cannot remove {}: {e}", file.display()));
+        }
+    }
+    save_state(&state)
+}
+
+impl Provider for AppImageProvider {
+    fn name(&self) -> &str {
+        "appimage"
+    }
+
+    fn is_available(&self) -> bool {
+        cfg!(target_os = "linux") && command_exists("curl")
+    }
+
+    fn search(&self, query: &str) -> SearchResult {
+        if query.is_empty() {
+            return SearchResult { packages: vec![], error: None };
+        }
+
+        let feed = match load_feed() {
+            Ok(feed) => feed,
+            Err(e) => return SearchResult { packages: vec![], error: Some(e) },
+        };
+
+        let state = load_state();
+        let query_lower = query.to_lowercase();
+        let mut packages = Vec::new();
+
+        for item in feed {
+            let description = item.description.as_deref().map(strip_html).unwrap_or_default();
+            if !item.name.to_lowercase().contains(&query_lower)
+                && !description.to_lowercase().contains(&query_lower)
+            {
+                continue;
+            }
+            let installed = state.installed.get(&item.name);
+            packages.push(Package {
+                version: installed.map(|app| app.version.clone()).unwrap_or_default(),
+                installed: installed.is_some(),
+                name: item.name,
+                description,
+                source: "appimagehub".to_string(),
+                ..Default::default()
+            });
+        }
+
+        sort_by_relevance(&mut packages, query);
+        SearchResult { packages, error: None }
+    }
+
+    /// The AppImages fex installed.
+    fn list_installed(&self) -> Option<Result<Vec<Package>, SearchError>> {
+        let packages = load_state()
+            .installed
+            .into_iter()
+            .map(|(name, app)| Package {
+                name,
+                version: app.version,
+                source: "appimagehub".to_string(),
+                installed: true,
+                ..Default::default()
+            })
+            .collect();
+        Some(Ok(packages))
+    }
+
+    fn install_command(&self, pkg: &Package) -> String {
+        format!(
+            "download {} AppImage to {}",
+            pkg.name,
+            install_dir().join(format!("{}.AppImage", slug(&pkg.name))).display()
+        )
+    }
+
+    fn remove_command(&self, pkg: &Package) -> Option<String> {
+        let state = load_state();
+        let app = state.installed.get(&pkg.name)?;
+        Some(format!("rm {} {}", app.path.display(), app.desktop_file.display()))
+    }
+
+    fn install(
+        &self,
+        pkg: &Package,
+        progress: &mut dyn FnMut(&str),
+    ) -> Option<Result<(), String>> {
+        Some(install_appimage(&pkg.name, progress))
+    }
+
+    fn remove(&self, pkg: &Package) -> Option<Result<(), String>> {
+        Some(remove_appimage(&pkg.name))
+    }
+
+    fn installs_in_process(&self) -> bool {
+        true
+    }
+
+    fn source_color(&self, _source: &str) -> Color {
+        Color::LightCyan
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn asset(name: &str) -> ReleaseAsset {
+        ReleaseAsset { name: name.to_string(), browser_download_url: String::new() }
+    }
+
+    #[test]
+    fn picks_appimage_for_the_arch() {
+        let assets = vec![
+            asset("Tool-1.0.tar.gz"),
+            asset("Tool-1.0-arm64.AppImage"),
+            asset("Tool-1.0-x86_64.AppImage"),
+        ];
+        let picked = |arch| pick_asset(&assets, arch).map(|a| a.name.as_str());
+        assert_eq!(picked("x86_64"), Some("Tool-1.0-x86_64.AppImage"));
+        assert_eq!(picked("aarch64"), Some("Tool-1.0-arm64.AppImage"));
+        assert_eq!(picked("riscv64"), None);
+    }
+
+    #[test]
+    fn only_falls_back_to_unmarked_appimage_on_x86_64() {
+        let assets = vec![asset("Tool-armhf.AppImage"), asset("Tool.AppImage")];
+        let picked = |arch| pick_asset(&assets, arch).map(|a| a.name.as_str());
+        assert_eq!(picked("x86_64"), Some("Tool.AppImage"));
+        assert_eq!(picked("aarch64"), None);
+        assert_eq!(picked("x86"), None);
+        assert_eq!(picked("riscv64"), None);
+    }
+
+    #[test]
+    fn strips_html_descriptions() {
+        assert_eq!(strip_html("<p>A  fast\n<b>editor</b></p>"), "A fast editor");
+        assert_eq!(slug("Visual Studio Code"), "Visual-Studio-Code");
+    }
+}
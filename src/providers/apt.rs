@@ -1,15 +1,175 @@
-use std::collections::HashSet;
+use std::collections::{HashMap, HashSet};
+use std::sync::Arc;
 
-use ratatui::style::Color;
-
-use crate::provider::{Package, Provider, SearchResult};
-use crate::util::{command_exists, escape_query, exec_command, sort_by_relevance};
+use crate::error::SearchError;
+use crate::provider::{Color, InstallPlan, Package, PlanAction, PlanEntry, Provider, SearchResult};
+use crate::util::{
+    cached_installed, command_exists, escape_query, exec_command, exec_search, exec_search_lines,
+    group_file_matches, parse_file_list, parse_size, shell_quote, sort_by_relevance,
+};
 
 pub struct AptProvider;
 
-fn get_installed() -> HashSet<String> {
-    let output = exec_command("dpkg-query -W -f='${Package}\\n' 2>/dev/null");
-    output.lines().filter(|l| !l.is_empty()).map(|l| l.to_string()).collect()
+fn get_installed() -> Arc<HashSet<String>> {
+    cached_installed("apt", || {
+        let output = exec_command("dpkg-query -W -f='${Package}\\n' 2>/dev/null");
+        output.lines().filter(|l| !l.is_empty()).map(|l| l.to_string()).collect()
+    })
+}
+
+/// Parse a line of `apt-cache search` output.
+fn parse_line(line: &str) -> Option<Package> {
+    // Format: package-name - description
+    let (name, description) = line.split_once(" - ")?;
+    Some(Package {
+        name: name.to_string(),
+        description: description.to_string(),
+        source: "apt".to_string(),
+        ..Default::default()
+    })
+}
+
+/// Parse the `Package:` stanzas of `apt-cache dumpavail`.
+fn parse_dumpavail(output: &str) -> Vec<Package> {
+    output
+        .split("\n\n")
+        .filter_map(|stanza| {
+            let field = |key: &str| {
+                stanza
+                    .lines()
+                    .find_map(|l| l.strip_prefix(key))
+                    .map(|v| v.trim().to_string())
+                    .unwrap_or_default()
+            };
+            let name = field("Package:");
+            if name.is_empty() {
+                return None;
+            }
+            Some(Package {
+                name,
+                version: field("Version:"),
+                description: field("Description:"),
+                source: "apt".to_string(),
+                ..Default::default()
+            })
+        })
+        .collect()
+}
+
+/// Parse `apt-get -s install`: `Inst`/`Remv` lines and the size summary.
+fn parse_simulation(output: &str) -> InstallPlan {
+    let mut plan = InstallPlan::default();
+    for line in output.lines() {
+        if let Some(rest) = line.strip_prefix("Inst ") {
+            // "Inst name [old] (new repo [arch])"; no "[old]" when newly installed
+            let name = rest.split_whitespace().next().unwrap_or("");
+            let after = rest[name.len()..].trim_start();
+            let action =
+                if after.starts_with('[') { PlanAction::Upgrade } else { PlanAction::Install };
+            let version = after.split('(').nth(1).and_then(|v| v.split_whitespace().next());
+            plan.entries.push(PlanEntry {
+                action,
+                name: name.to_string(),
+                version: version.unwrap_or("").to_string(),
+            });
+        } else if let Some(rest) = line.strip_prefix("Remv ") {
+            let mut fields = rest.split_whitespace();
+            let name = fields.next().unwrap_or("").to_string();
+            let version = fields.next().unwrap_or("").trim_matches(['[', ']']).to_string();
+            plan.entries.push(PlanEntry { action: PlanAction::Remove, name, version });
+        } else if let Some(rest) = line.strip_prefix("Need to get ") {
+            // "Need to get 1,234 kB/5,678 kB of archives." when some are cached
+            let size = rest.split(" of archives").next().unwrap_or(rest);
+            plan.download_size = parse_size(size.split('/').next().unwrap_or(size));
+        } else if let Some(rest) = line.strip_prefix("After this operation, ") {
+            let size = parse_size(rest.split(" of ").next().unwrap_or("")).map(|s| s as i64);
+            plan.install_size = if rest.contains("freed") { size.map(|s| -s) } else { size };
+        }
+    }
+    plan
+}
+
+/// The packages named in `apt-mark showmanual`, with versions from
+/// `dpkg-query -W` ("name version" per line).
+fn parse_manual(manual: &str, versions: &str) -> Vec<Package> {
+    let versions: HashMap<&str, &str> =
+        versions.lines().filter_map(|line| line.split_once(' ')).collect();
+    manual
+        .lines()
+        .filter(|name| !name.is_empty())
+        .map(|name| Package {
+            name: name.to_string(),
+            version: versions.get(name).unwrap_or(&"").to_string(),
+            source: "apt".to_string(),
+            installed: true,
+            ..Default::default()
+        })
+        .collect()
+}
+
+/// Parse `apt-file search`, "package: /path" per line.
+fn parse_file_matches(output: &str, installed: &HashSet<String>) -> Vec<Package> {
+    let matches = output.lines().filter_map(|line| {
+        let (name, path) = line.split_once(": ")?;
+        let pkg = Package {
+            name: name.to_string(),
+            source: "apt".to_string(),
+            installed: installed.contains(name),
+            ..Default::default()
+        };
+        Some((pkg, path.trim().to_string()))
+    });
+    group_file_matches(matches)
+}
+
+/// Relations left out of dependency trees; only hard dependencies are kept.
+const SOFT_RELATIONS: &str =
+    "--no-recommends --no-suggests --no-conflicts --no-breaks --no-replaces --no-enhances";
+
+/// Parse `apt-cache depends`: "Depends: name" lines under the package, with
+/// a leading "|" for alternatives and virtual packages in angle brackets.
+fn parse_depends(output: &str) -> Vec<String> {
+    let mut names: Vec<String> = Vec::new();
+    for line in output.lines() {
+        let line = line.trim_start().trim_start_matches('|');
+        let Some((_, name)) =
+            line.split_once("PreDepends: ").or_else(|| line.split_once("Depends: "))
+        else {
+            continue;
+        };
+        let name = name.trim().trim_start_matches('<').trim_end_matches('>');
+        if !names.iter().any(|n| n == name) {
+            names.push(name.to_string());
+        }
+    }
+    names
+}
+
+/// Parse `apt-cache rdepends`: the indented names after "Reverse Depends:".
+fn parse_rdepends(output: &str) -> Vec<String> {
+    let mut names: Vec<String> = Vec::new();
+    let lines = output.lines().skip_while(|line| !line.starts_with("Reverse Depends:"));
+    for line in lines.skip(1) {
+        let name = line.trim().trim_start_matches('|');
+        if !name.is_empty() && !names.iter().any(|n| n == name) {
+            names.push(name.to_string());
+        }
+    }
+    names
+}
+
+/// Named packages as dependency tree nodes.
+fn named_packages(names: Vec<String>) -> Vec<Package> {
+    let installed = get_installed();
+    names
+        .into_iter()
+        .map(|name| Package {
+            installed: installed.contains(&name),
+            name,
+            source: "apt".to_string(),
+            ..Default::default()
+        })
+        .collect()
 }
 
 impl Provider for AptProvider {
@@ -27,41 +187,176 @@ impl Provider for AptProvider {
         }
 
         let escaped = escape_query(query);
-        let output = exec_command(&format!("apt-cache search '{escaped}' 2>/dev/null"));
-        if output.is_empty() {
-            return SearchResult { packages: vec![], error: None };
-        }
-
         let installed = get_installed();
-        let mut packages = Vec::new();
-
-        for line in output.lines() {
-            if line.is_empty() {
-                continue;
-            }
-            // Format: package-name - description
-            let Some(sep) = line.find(" - ") else { continue };
-            let name = line[..sep].to_string();
-            let description = line[sep + 3..].to_string();
-            let is_installed = installed.contains(&name);
-            packages.push(Package {
-                name,
-                version: String::new(),
-                description,
-                source: "apt".to_string(),
-                installed: is_installed,
-            });
-        }
+        let mut packages = match exec_search_lines(
+            &format!("apt-cache search '{escaped}'"),
+            &[],
+            |line| {
+                let mut pkg = parse_line(line)?;
+                pkg.installed = installed.contains(&pkg.name);
+                Some(pkg)
+            },
+        ) {
+            Ok(packages) => packages,
+            Err(e) => return SearchResult { packages: vec![], error: Some(e) },
+        };
 
         sort_by_relevance(&mut packages, query);
         SearchResult { packages, error: None }
     }
 
+    fn list_all(&self) -> Option<Result<Vec<Package>, SearchError>> {
+        let result = exec_search("apt-cache dumpavail", &[]).map(|output| {
+            let installed = get_installed();
+            let mut packages = parse_dumpavail(&output);
+            for pkg in &mut packages {
+                pkg.installed = installed.contains(&pkg.name);
+            }
+            packages
+        });
+        Some(result)
+    }
+
+    fn can_list_all(&self) -> bool {
+        true
+    }
+
+    fn list_installed(&self) -> Option<Result<Vec<Package>, SearchError>> {
+        let versions = exec_command("dpkg-query -W -f='${Package} ${Version}\\n' 2>/dev/null");
+        let result = exec_search("apt-mark showmanual", &[])
+            .map(|manual| parse_manual(&manual, &versions));
+        Some(result)
+    }
+
+    fn plan_install(&self, pkgs: &[Package]) -> Option<Result<InstallPlan, SearchError>> {
+        let names: Vec<String> = pkgs.iter().map(|p| shell_quote(&p.name)).collect();
+        let cmd = format!("LANG=C apt-get -s install {}", names.join(" "));
+        Some(exec_search(&cmd, &[]).map(|output| parse_simulation(&output)))
+    }
+
+    fn installed_names(&self) -> Option<Arc<HashSet<String>>> {
+        Some(get_installed())
+    }
+
+    fn search_file(&self, path: &str) -> Option<Result<Vec<Package>, SearchError>> {
+        let cmd = if path.contains('/') {
+            format!("apt-file search -F {}", shell_quote(path))
+        } else {
+            let pattern = format!("/s?bin/{}$", regex::escape(path));
+            format!("apt-file search -x {}", shell_quote(&pattern))
+        };
+        let result = exec_search(&cmd, &[])
+            .map(|output| parse_file_matches(&output, &get_installed()));
+        Some(result)
+    }
+
+    fn dependencies(&self, pkg: &Package) -> Option<Result<Vec<Package>, SearchError>> {
+        let cmd = format!("apt-cache depends {SOFT_RELATIONS} {}", shell_quote(&pkg.name));
+        Some(exec_search(&cmd, &[]).map(|output| named_packages(parse_depends(&output))))
+    }
+
+    fn reverse_dependencies(&self, pkg: &Package) -> Option<Result<Vec<Package>, SearchError>> {
+        let cmd = format!("apt-cache rdepends {SOFT_RELATIONS} {}", shell_quote(&pkg.name));
+        Some(exec_search(&cmd, &[]).map(|output| named_packages(parse_rdepends(&output))))
+    }
+
+    /// Packages that aren't installed are listed with `apt-file`.
+    fn list_files(&self, pkg: &Package) -> Option<Result<Vec<String>, SearchError>> {
+        let name = shell_quote(&pkg.name);
+        let result = if pkg.installed {
+            let cmd = format!("dpkg -L {name}");
+            exec_search(&cmd, &[]).map(|output| parse_file_list(output.lines()))
+        } else {
+            // "package: /path" per line
+            exec_search(&format!("apt-file list {name}"), &[]).map(|output| {
+                parse_file_list(output.lines().filter_map(|line| Some(line.split_once(": ")?.1)))
+            })
+        };
+        Some(result)
+    }
+
     fn install_command(&self, pkg: &Package) -> String {
         format!("sudo apt install {}", pkg.name)
     }
 
+    fn remove_command(&self, pkg: &Package) -> Option<String> {
+        Some(format!("sudo apt remove {}", pkg.name))
+    }
+
     fn source_color(&self, _source: &str) -> Color {
         Color::Yellow
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn parses_manual_packages_with_versions() {
+        let packages = parse_manual("vim\ncurl\n", "curl 8.5.0-2\nlibc6 2.39-0\nvim 2:9.1-1\n");
+        assert_eq!(packages.len(), 2);
+        assert_eq!(packages[0].version, "2:9.1-1");
+        assert_eq!(packages[1].name, "curl");
+    }
+
+    #[test]
+    fn parses_dumpavail_stanzas() {
+        let output = "Package: ripgrep\nVersion: 14.1.0-1\nSection: utils\n\
+Description: Recursively searches directories for a regex pattern\n \
+ripgrep is a line-oriented search tool\n\n\
+Package: vim\nVersion: 2:9.1.0016-1\nDescription: Vi IMproved\n\n";
+        let packages = parse_dumpavail(output);
+        assert_eq!(packages.len(), 2);
+        assert_eq!(packages[0].name, "ripgrep");
+        assert_eq!(packages[0].version, "14.1.0-1");
+        assert_eq!(
+            packages[0].description,
+            "Recursively searches directories for a regex pattern"
+        );
+        assert_eq!(packages[1].version, "2:9.1.0016-1");
+    }
+
+    #[test]
+    fn parses_simulated_install() {
+        let output = "Reading package lists...\n\
+Need to get 2,048 kB of archives.\n\
+After this operation, 6,144 kB of additional disk space will be used.\n\
+Remv vim-tiny [2:9.1.0016-1]\n\
+Inst vim-common [2:9.0.1378-2] (2:9.1.0016-1 Debian:13/testing [all])\n\
+Inst vim (2:9.1.0016-1 Debian:13/testing [amd64])\n\
+Conf vim (2:9.1.0016-1 Debian:13/testing [amd64])\n";
+        let plan = parse_simulation(output);
+        let actions: Vec<_> = plan.entries.iter().map(|e| (e.action, e.name.as_str())).collect();
+        assert_eq!(
+            actions,
+            [
+                (PlanAction::Remove, "vim-tiny"),
+                (PlanAction::Upgrade, "vim-common"),
+                (PlanAction::Install, "vim")
+            ]
+        );
+        assert_eq!(plan.entries[0].version, "2:9.1.0016-1");
+        assert_eq!(plan.entries[2].version, "2:9.1.0016-1");
+        assert_eq!(plan.download_size, Some(2048 * 1000));
+        assert_eq!(plan.install_size, Some(6144 * 1000));
+    }
+
+    #[test]
+    fn parses_apt_file_matches() {
+        let output = "coreutils: /bin/ls\ncoreutils: /usr/bin/ls\nbusybox: /bin/ls\n";
+        let packages = parse_file_matches(output, &HashSet::from(["busybox".to_string()]));
+        assert_eq!(packages.len(), 2);
+        assert_eq!(packages[0].files, ["/bin/ls", "/usr/bin/ls"]);
+        assert!(packages[1].installed);
+    }
+
+    #[test]
+    fn parses_depends_and_rdepends() {
+        let depends = "vim\n  Depends: vim-common\n |Depends: libgpm2\n  Depends: <libpython3.12>\n\
+                       PreDepends: dpkg\n";
+        assert_eq!(parse_depends(depends), ["vim-common", "libgpm2", "libpython3.12", "dpkg"]);
+        let rdepends = "vim\nReverse Depends:\n  vim-gtk3\n |vim-addon-manager\n  vim-gtk3\n";
+        assert_eq!(parse_rdepends(rdepends), ["vim-gtk3", "vim-addon-manager"]);
+    }
+}
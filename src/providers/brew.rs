@@ -1,25 +1,75 @@
-use std::collections::HashSet;
+use std::collections::{HashMap, HashSet};
+use std::sync::Arc;
 
-use ratatui::style::Color;
-
-use crate::provider::{Package, Provider, SearchResult};
-use crate::util::{command_exists, escape_query, exec_command, sort_by_relevance};
+use crate::error::SearchError;
+use crate::provider::{Color, InstallPlan, Package, PlanAction, PlanEntry, Provider, SearchResult};
+use crate::util::{
+    cached_installed, command_exists, escape_query, exec_command, exec_search, parse_file_list,
+    shell_quote, sort_by_relevance,
+};
 
 pub struct BrewProvider;
 
-fn get_installed() -> HashSet<String> {
-    let mut installed = HashSet::new();
-    for output in [
-        exec_command("brew list --formula 2>/dev/null"),
-        exec_command("brew list --cask 2>/dev/null"),
-    ] {
-        for line in output.lines() {
-            if !line.is_empty() {
-                installed.insert(line.to_string());
+fn get_installed() -> Arc<HashSet<String>> {
+    cached_installed("brew", || {
+        let mut installed = HashSet::new();
+        for output in [
+            exec_command("brew list --formula 2>/dev/null"),
+            exec_command("brew list --cask 2>/dev/null"),
+        ] {
+            for line in output.lines() {
+                if !line.is_empty() {
+                    installed.insert(line.to_string());
+                }
+            }
+        }
+        installed
+    })
+}
+
+/// Parse `brew install --dry-run`: names (sometimes with versions) under
+/// "==> Would install ..." and "==> Would upgrade ..." headers.
+fn parse_dry_run(output: &str, plan: &mut InstallPlan) {
+    let mut action = None;
+    for line in output.lines() {
+        if let Some(header) = line.strip_prefix("==> ") {
+            action = if header.starts_with("Would install") {
+                Some(PlanAction::Install)
+            } else if header.starts_with("Would upgrade") {
+                Some(PlanAction::Upgrade)
+            } else {
+                None
+            };
+            continue;
+        }
+        let Some(action) = action else { continue };
+        for word in line.split_whitespace() {
+            match plan.entries.last_mut() {
+                Some(last) if word.starts_with(|c: char| c.is_ascii_digit()) => {
+                    last.version = word.to_string();
+                }
+                _ => plan.entries.push(PlanEntry {
+                    action,
+                    name: word.to_string(),
+                    version: String::new(),
+                }),
             }
         }
     }
-    installed
+}
+
+/// Formulae listed one per line, as `brew deps` and `brew uses` print them.
+fn formulae(output: &str) -> Vec<Package> {
+    let installed = get_installed();
+    output
+        .split_whitespace()
+        .map(|name| Package {
+            name: name.to_string(),
+            source: "formula".to_string(),
+            installed: installed.contains(name),
+            ..Default::default()
+        })
+        .collect()
 }
 
 impl Provider for BrewProvider {
@@ -41,8 +91,12 @@ impl Provider for BrewProvider {
 
         // Try exact match via brew info
         let mut exact_match: Option<Package> = None;
-        let info_output = exec_command(&format!("brew info '{escaped}' 2>/dev/null"));
-        if !info_output.is_empty() && !info_output.contains("Error:") {
+        let no_formula = ["No available formula", "No formulae or casks found"];
+        let info_output = match exec_search(&format!("brew info '{escaped}'"), &no_formula) {
+            Ok(output) => output,
+            Err(e) => return SearchResult { packages: vec![], error: Some(e) },
+        };
+        if !info_output.is_empty() {
             let mut lines = info_output.lines();
             if let Some(first_line) = lines.next() {
                 let first_line = first_line.strip_prefix("==> ").unwrap_or(first_line);
@@ -63,16 +117,22 @@ impl Provider for BrewProvider {
                     let is_installed = installed.contains(&name);
                     exact_match = Some(Package {
                         name,
-                        version: String::new(),
                         description,
                         source: "formula".to_string(),
                         installed: is_installed,
+                        ..Default::default()
                     });
                 }
             }
         }
 
-        let output = exec_command(&format!("brew search --desc '{escaped}' 2>/dev/null"));
+        let output = match exec_search(
+            &format!("brew search --desc '{escaped}'"),
+            &["No formulae or casks found"],
+        ) {
+            Ok(output) => output,
+            Err(e) => return SearchResult { packages: vec![], error: Some(e) },
+        };
 
         let mut packages = Vec::new();
 
@@ -113,10 +173,10 @@ impl Provider for BrewProvider {
             let is_installed = installed.contains(&name);
             packages.push(Package {
                 name,
-                version: String::new(),
                 description,
                 source: current_source.to_string(),
                 installed: is_installed,
+                ..Default::default()
             });
         }
 
@@ -124,6 +184,118 @@ impl Provider for BrewProvider {
         SearchResult { packages, error: None }
     }
 
+    /// Names only: descriptions would need `brew info` on every formula.
+    fn list_all(&self) -> Option<Result<Vec<Package>, SearchError>> {
+        let installed = get_installed();
+        let mut packages = Vec::new();
+        for (cmd, source) in [("brew formulae", "formula"), ("brew casks", "cask")] {
+            let output = match exec_search(cmd, &[]) {
+                Ok(output) => output,
+                Err(e) => return Some(Err(e)),
+            };
+            packages.extend(output.lines().filter(|l| !l.is_empty()).map(|name| Package {
+                name: name.to_string(),
+                source: source.to_string(),
+                installed: installed.contains(name),
+                ..Default::default()
+            }));
+        }
+        Some(Ok(packages))
+    }
+
+    fn can_list_all(&self) -> bool {
+        true
+    }
+
+    /// Formulae installed on request and all casks.
+    fn list_installed(&self) -> Option<Result<Vec<Package>, SearchError>> {
+        // "name version..." for formulae and casks alike
+        let listed = exec_command("brew list --versions 2>/dev/null");
+        let versions: HashMap<&str, &str> = listed
+            .lines()
+            .filter_map(|line| line.split_once(' '))
+            .map(|(name, versions)| (name, versions.rsplit(' ').next().unwrap_or(versions)))
+            .collect();
+        let mut packages = Vec::new();
+        for (cmd, source) in
+            [("brew leaves --installed-on-request", "formula"), ("brew list --cask", "cask")]
+        {
+            let output = match exec_search(cmd, &[]) {
+                Ok(output) => output,
+                Err(e) => return Some(Err(e)),
+            };
+            packages.extend(output.lines().filter(|l| !l.is_empty()).map(|name| Package {
+                name: name.to_string(),
+                version: versions.get(name).unwrap_or(&"").to_string(),
+                source: source.to_string(),
+                installed: true,
+                ..Default::default()
+            }));
+        }
+        Some(Ok(packages))
+    }
+
+    fn plan_install(&self, pkgs: &[Package]) -> Option<Result<InstallPlan, SearchError>> {
+        let mut plan = InstallPlan::default();
+        let (casks, formulae): (Vec<_>, Vec<_>) = pkgs.iter().partition(|p| p.source == "cask");
+        for (flag, group) in [("", formulae), ("--cask ", casks)] {
+            if group.is_empty() {
+                continue;
+            }
+            let names: Vec<String> = group.iter().map(|p| shell_quote(&p.name)).collect();
+            let cmd = format!("brew install --dry-run {flag}{}", names.join(" "));
+            match exec_search(&cmd, &[]) {
+                Ok(output) => parse_dry_run(&output, &mut plan),
+                Err(e) => return Some(Err(e)),
+            }
+        }
+        Some(Ok(plan))
+    }
+
+    fn installed_names(&self) -> Option<Arc<HashSet<String>>> {
+        Some(get_installed())
+    }
+
+    fn dependencies(&self, pkg: &Package) -> Option<Result<Vec<Package>, SearchError>> {
+        let kind = if pkg.source == "cask" { "--cask" } else { "--formula" };
+        let cmd = format!("brew deps --direct {kind} {}", shell_quote(&pkg.name));
+        Some(exec_search(&cmd, &[]).map(|output| formulae(&output)))
+    }
+
+    /// Every formula has to be evaluated, so this is slow.
+    fn reverse_dependencies(&self, pkg: &Package) -> Option<Result<Vec<Package>, SearchError>> {
+        let cmd = format!("brew uses --eval-all {}", shell_quote(&pkg.name));
+        Some(exec_search(&cmd, &[]).map(|output| formulae(&output)))
+    }
+
+    /// Only commands can be looked up, through the `command-not-found` tap;
+    /// for a path its file name is used.
+    fn search_file(&self, path: &str) -> Option<Result<Vec<Package>, SearchError>> {
+        let command = path.rsplit('/').next().unwrap_or(path);
+        let cmd = format!("brew which-formula {}", shell_quote(command));
+        let result = exec_search(&cmd, &[]).map(|output| {
+            let installed = get_installed();
+            output
+                .split_whitespace()
+                .map(|name| Package {
+                    name: name.to_string(),
+                    source: "formula".to_string(),
+                    installed: installed.contains(name),
+                    files: vec![format!("bin/{command}")],
+                    ..Default::default()
+                })
+                .collect()
+        });
+        Some(result)
+    }
+
+    /// Only installed formulae and casks have files to list.
+    fn list_files(&self, pkg: &Package) -> Option<Result<Vec<String>, SearchError>> {
+        let kind = if pkg.source == "cask" { "--cask" } else { "--formula" };
+        let cmd = format!("brew list {kind} {}", shell_quote(&pkg.name));
+        Some(exec_search(&cmd, &[]).map(|output| parse_file_list(output.lines())))
+    }
+
     fn install_command(&self, pkg: &Package) -> String {
         if pkg.source == "cask" {
             format!("brew install --cask {}", pkg.name)
@@ -132,6 +304,14 @@ impl Provider for BrewProvider {
         }
     }
 
+    fn remove_command(&self, pkg: &Package) -> Option<String> {
+        if pkg.source == "cask" {
+            Some(format!("brew uninstall --cask {}", pkg.name))
+        } else {
+            Some(format!("brew uninstall {}", pkg.name))
+        }
+    }
+
     fn source_color(&self, source: &str) -> Color {
         match source {
             "cask" => Color::Magenta,
@@ -139,3 +319,19 @@ impl Provider for BrewProvider {
         }
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn parses_dry_run() {
+        let output = "==> Would install 1 formula:\nripgrep 14.1.1\n\
+==> Would install 2 dependencies for ripgrep:\npcre2 10.44 libfoo\n";
+        let mut plan = InstallPlan::default();
+        parse_dry_run(output, &mut plan);
+        let names: Vec<_> =
+            plan.entries.iter().map(|e| (e.name.as_str(), e.version.as_str())).collect();
+        assert_eq!(names, [("ripgrep", "14.1.1"), ("pcre2", "10.44"), ("libfoo", "")]);
+    }
+}
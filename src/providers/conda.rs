@@ -0,0 +1,320 @@
+use std::collections::hash_map::Entry;
+use std::collections::{HashMap, HashSet};
+use std::sync::Arc;
+
+use serde_json::Value;
+
+use crate::error::SearchError;
+use crate::provider::{Color, Package, Provider, SearchResult};
+use crate::util::{
+    cached_installed, command_exists, escape_query, exec_command, exec_search, sort_by_relevance,
+};
+
+pub struct CondaProvider;
+
+/// Prefer the faster solvers when they are installed.
+fn conda_tool() -> &'static str {
+    ["mamba", "micromamba"]
+        .into_iter()
+        .find(|tool| command_exists(tool))
+        .unwrap_or("conda")
+}
+
+/// Path of the currently active environment, if any.
+fn active_prefix() -> Option<String> {
+    std::env::var("CONDA_PREFIX").ok().filter(|p| !p.is_empty())
+}
+
+/// Name of the currently active environment, if any.
+fn active_env_name() -> Option<String> {
+    if let Ok(name) = std::env::var("CONDA_DEFAULT_ENV")
+        && !name.is_empty()
+    {
+        return Some(name);
+    }
+    active_prefix().map(|p| p.rsplit('/').next().unwrap_or(&p).to_string())
+}
+
+fn get_installed(tool: &str, prefix: &str) -> Arc<HashSet<String>> {
+    cached_installed(&format!("conda:{prefix}"), || {
+        let escaped = escape_query(prefix);
+        let output = exec_command(&format!("{tool} list -p '{escaped}' --json 2>/dev/null"));
+        let Ok(Value::Array(entries)) = serde_json::from_str::<Value>(&output) else {
+            return HashSet::new();
+        };
+        entries
+            .iter()
+            .filter_map(|e| e.get("name").and_then(Value::as_str))
+            .map(|name| name.to_string())
+            .collect()
+    })
+}
+
+/// Names in the `--from-history` specs of `env export --json`, such as
+/// "python=3.12" or "conda-forge::numpy>=1.26": the packages the user asked for.
+fn parse_history_specs(output: &str) -> Result<Vec<String>, SearchError> {
+    let root: Value =
+        serde_json::from_str(output).map_err(|e| SearchError::ParseFailure(e.to_string()))?;
+    let specs = root.get("dependencies").and_then(Value::as_array).into_iter().flatten();
+    Ok(specs
+        .filter_map(Value::as_str)
+        .map(|spec| spec.rsplit("::").next().unwrap_or(spec))
+        .filter_map(|spec| spec.split(['=', '<', '>', '!', '~', ' ']).next())
+        .filter(|name| !name.is_empty())
+        .map(|name| name.to_string())
+        .collect())
+}
+
+/// The packages of the environment at `prefix` the user asked for, with
+/// the versions and channels `list --json` reports for them.
+fn list_requested(tool: &str, prefix: &str) -> Result<Vec<Package>, SearchError> {
+    let escaped = escape_query(prefix);
+    let export = format!("{tool} env export -p '{escaped}' --from-history --json");
+    let history = exec_search(&export, &[])?;
+    let listed = exec_search(&format!("{tool} list -p '{escaped}' --json"), &[])?;
+    let listed: Value =
+        serde_json::from_str(&listed).map_err(|e| SearchError::ParseFailure(e.to_string()))?;
+    let entries = listed.as_array().map(Vec::as_slice).unwrap_or_default();
+    let packages = parse_history_specs(&history)?
+        .into_iter()
+        .map(|name| {
+            let named = |e: &&Value| e.get("name").and_then(Value::as_str) == Some(name.as_str());
+            let entry = entries.iter().find(named);
+            let field = |key: &str| {
+                entry.and_then(|e| e.get(key)).and_then(Value::as_str).unwrap_or("")
+            };
+            Package {
+                version: field("version").to_string(),
+                source: channel_name(field("channel"), field("platform")),
+                installed: true,
+                name,
+                ..Default::default()
+            }
+        })
+        .collect();
+    Ok(packages)
+}
+
+/// Reduce a channel URL such as "https://conda.anaconda.org/conda-forge/linux-64"
+/// to its name ("conda-forge"), dropping the platform subdirectory, which is
+/// `subdir` or looks like "osx-arm64" or "noarch". Anaconda's own channels are
+/// reported as "defaults".
+fn channel_name(channel: &str, subdir: &str) -> String {
+    let trimmed = channel.trim_end_matches('/');
+    let mut segments = trimmed.rsplit('/');
+    let mut name = segments.next().unwrap_or(trimmed);
+    if segments.clone().next().is_some() && (name == subdir || is_platform(name)) {
+        name = segments.next().unwrap_or(name);
+    }
+    if trimmed.contains("repo.anaconda.com") || matches!(name, "main" | "r" | "msys2") {
+        "defaults".to_string()
+    } else {
+        name.to_string()
+    }
+}
+
+/// Whether `segment` is a conda subdir: "noarch" or "<platform>-<arch>".
+fn is_platform(segment: &str) -> bool {
+    segment == "noarch"
+        || segment.split_once('-').is_some_and(|(platform, arch)| {
+            matches!(platform, "linux" | "osx" | "win" | "emscripten" | "wasi" | "zos")
+                && !arch.is_empty()
+        })
+}
+
+/// Parse `search --json` output. conda and mamba return an object keyed by
+/// package name, micromamba wraps a flat list in `result.pkgs`. Entries are
+/// ordered oldest first, so the last one seen per name/channel wins.
+fn parse_search_json(output: &str) -> Result<Vec<Package>, SearchError> {
+    let root: Value =
+        serde_json::from_str(output).map_err(|e| SearchError::ParseFailure(e.to_string()))?;
+
+    // Failures are reported as a JSON object with the exception's message
+    if let Some(message) = root.get("message").and_then(Value::as_str)
+        && root.get("exception_name").is_some()
+    {
+        return Err(SearchError::from_command("conda search", 1, message));
+    }
+
+    let entries: Vec<&Value> = match root.pointer("/result/pkgs") {
+        Some(Value::Array(pkgs)) => pkgs.iter().collect(),
+        _ => match &root {
+            Value::Object(map) => map
+                .values()
+                .filter_map(Value::as_array)
+                .flatten()
+                .collect(),
+            _ => Vec::new(),
+        },
+    };
+
+    let mut packages: Vec<Package> = Vec::new();
+    // Index into `packages` of each name/channel pair
+    let mut seen: HashMap<(String, String), usize> = HashMap::new();
+    for entry in entries {
+        let field = |key: &str| entry.get(key).and_then(Value::as_str).unwrap_or("");
+        let name = field("name");
+        if name.is_empty() {
+            continue;
+        }
+        // The search results carry no summary, so the description stays empty
+        let pkg = Package {
+            name: name.to_string(),
+            version: field("version").to_string(),
+            source: channel_name(field("channel"), field("subdir")),
+            ..Default::default()
+        };
+        match seen.entry((pkg.name.clone(), pkg.source.clone())) {
+            Entry::Occupied(slot) => packages[*slot.get()] = pkg,
+            Entry::Vacant(slot) => {
+                slot.insert(packages.len());
+                packages.push(pkg);
+            }
+        }
+    }
+    Ok(packages)
+}
+
+impl Provider for CondaProvider {
+    fn name(&self) -> &str {
+        "conda"
+    }
+
+    fn is_available(&self) -> bool {
+        command_exists("conda") || command_exists("mamba") || command_exists("micromamba")
+    }
+
+    fn search(&self, query: &str) -> SearchResult {
+        if query.is_empty() {
+            return SearchResult { packages: vec![], error: None };
+        }
+
+        let tool = conda_tool();
+        let escaped = escape_query(query);
+        let cmd = format!("{tool} search --json '*{escaped}*'");
+        let output = match exec_search(&cmd, &["PackagesNotFoundError"]) {
+            Ok(output) => output,
+            Err(e) => return SearchResult { packages: vec![], error: Some(e) },
+        };
+        if output.is_empty() {
+            return SearchResult { packages: vec![], error: None };
+        }
+
+        let mut packages = match parse_search_json(&output) {
+            Ok(packages) => packages,
+            Err(e) => return SearchResult { packages: vec![], error: Some(e) },
+        };
+
+        if let Some(prefix) = active_prefix() {
+            let installed = get_installed(tool, &prefix);
+            for pkg in &mut packages {
+                pkg.installed = installed.contains(&pkg.name);
+            }
+        }
+
+        sort_by_relevance(&mut packages, query);
+        SearchResult { packages, error: None }
+    }
+
+    /// Packages of the active environment.
+    fn installed_names(&self) -> Option<Arc<HashSet<String>>> {
+        active_prefix().map(|prefix| get_installed(conda_tool(), &prefix))
+    }
+
+    /// The packages of the active environment the user asked for.
+    fn list_installed(&self) -> Option<Result<Vec<Package>, SearchError>> {
+        let Some(prefix) = active_prefix() else {
+            return Some(Err(SearchError::Other("No conda environment is active".to_string())));
+        };
+        Some(list_requested(conda_tool(), &prefix))
+    }
+
+    fn install_command(&self, pkg: &Package) -> String {
+        let tool = conda_tool();
+        let target = match active_prefix() {
+            Some(prefix) => format!(" -p '{}'", escape_query(&prefix)),
+            None => String::new(),
+        };
+        if pkg.source == "defaults" {
+            format!("{tool} install{target} {}", pkg.name)
+        } else {
+            format!("{tool} install{target} -c {} {}", pkg.source, pkg.name)
+        }
+    }
+
+    fn remove_command(&self, pkg: &Package) -> Option<String> {
+        let tool = conda_tool();
+        let target = match active_prefix() {
+            Some(prefix) => format!(" -p '{}'", escape_query(&prefix)),
+            None => String::new(),
+        };
+        Some(format!("{tool} remove{target} {}", pkg.name))
+    }
+
+    fn source_color(&self, source: &str) -> Color {
+        match source {
+            "conda-forge" => Color::Green,
+            "defaults" => Color::Blue,
+            "bioconda" => Color::Magenta,
+            _ => Color::Yellow,
+        }
+    }
+
+    fn status_context(&self) -> Option<String> {
+        Some(format!("env: {}", active_env_name().unwrap_or_else(|| "none".to_string())))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn parses_conda_search_json() {
+        let output = r#"{
+            "numpy": [
+                {"name": "numpy", "version": "1.26.3", "build": "py312h", "subdir": "linux-64",
+                 "channel": "https://conda.anaconda.org/conda-forge/linux-64"},
+                {"name": "numpy", "version": "2.0.0", "build": "py312h", "subdir": "linux-64",
+                 "channel": "https://conda.anaconda.org/conda-forge/linux-64"},
+                {"name": "numpy", "version": "1.26.4", "build": "py312", "subdir": "linux-64",
+                 "channel": "https://repo.anaconda.com/pkgs/main/linux-64"}
+            ]
+        }"#;
+        let packages = parse_search_json(output).unwrap();
+        assert_eq!(packages.len(), 2);
+        assert_eq!(packages[0].source, "conda-forge");
+        assert_eq!(packages[0].version, "2.0.0");
+        assert_eq!(packages[1].source, "defaults");
+        assert!(packages[0].description.is_empty());
+    }
+
+    #[test]
+    fn parses_requested_specs() {
+        let output = r#"{"name": "work", "channels": ["conda-forge"],
+            "dependencies": ["python=3.12", "conda-forge::numpy>=1.26", "scipy"]}"#;
+        assert_eq!(parse_history_specs(output).unwrap(), ["python", "numpy", "scipy"]);
+    }
+
+    #[test]
+    fn strips_platform_subdirs_from_channels() {
+        let forge = "https://conda.anaconda.org/conda-forge";
+        assert_eq!(channel_name(&format!("{forge}/osx-arm64"), ""), "conda-forge");
+        assert_eq!(channel_name(&format!("{forge}/linux-ppc64le"), "linux-ppc64le"), "conda-forge");
+        assert_eq!(channel_name(&format!("{forge}/custom-sub"), "custom-sub"), "conda-forge");
+        assert_eq!(channel_name("https://conda.anaconda.org/my-linux-64", ""), "my-linux-64");
+        assert_eq!(channel_name("bioconda", "noarch"), "bioconda");
+    }
+
+    #[test]
+    fn parses_micromamba_search_json() {
+        let output = r#"{"result": {"msg": "", "pkgs": [
+            {"name": "samtools", "version": "1.20", "build": "h50ea8bc", "subdir": "linux-64",
+             "channel": "bioconda"}
+        ], "status": "OK"}}"#;
+        let packages = parse_search_json(output).unwrap();
+        assert_eq!(packages.len(), 1);
+        assert_eq!(packages[0].name, "samtools");
+        assert_eq!(packages[0].source, "bioconda");
+    }
+}
@@ -0,0 +1,416 @@
+//! Providers declared by the user in `$XDG_CONFIG_HOME/fex/providers.toml`.
+//!
+//! ```toml
+//! [[provider]]
+//! name = "pkgin"
+//! available = "command -v pkgin"
+//! search = "pkgin search {query}"
+//! regex = '^(?P<name>\S+)-(?P<version>[0-9]\S*)\s+(?P<description>.*)$'
+//! installed = "pkgin list"
+//! installed_regex = '^(?P<name>\S+)-(?P<version>[0-9]\S*)'
+//! install = "sudo pkgin install {name}"
+//! remove = "sudo pkgin remove {name}"
+//! source = "pkgsrc"
+//! colors = { pkgsrc = "blue" }
+//! timeout = 60
+//! ```
+//!
+//! Instead of `regex`, `json_path` selects an array in JSON output (dot-separated,
+//! empty for a top-level array) and `json_fields` maps package fields to keys.
+
+use std::collections::{HashMap, HashSet};
+use std::fs;
+use std::str::FromStr;
+use std::sync::Arc;
+use std::time::Duration;
+
+use regex::Regex;
+use serde::Deserialize;
+use serde_json::Value;
+
+use crate::error::SearchError;
+use crate::provider::{Color, Package, Provider, SearchResult};
+use crate::util::{
+    cached_installed, config_dir, exec_command, exec_command_full, exec_search, exec_search_lines,
+    shell_quote, sort_by_relevance,
+};
+
+#[derive(Deserialize)]
+struct ConfigFile {
+    #[serde(default)]
+    provider: Vec<ProviderConfig>,
+}
+
+#[derive(Deserialize)]
+#[serde(deny_unknown_fields)]
+struct ProviderConfig {
+    name: String,
+    /// Shell command that exits 0 when the provider can be used.
+    available: String,
+    /// Search command template; `{query}` is replaced by the quoted query.
+    search: String,
+    regex: Option<String>,
+    json_path: Option<String>,
+    #[serde(default)]
+    json_fields: JsonFields,
+    /// Command listing installed packages, one per line.
+    installed: Option<String>,
+    installed_regex: Option<String>,
+    /// Install/remove templates; `{name}`, `{version}` and `{source}` are replaced.
+    install: String,
+    remove: Option<String>,
+    #[serde(default = "default_source")]
+    source: String,
+    #[serde(default)]
+    colors: HashMap<String, String>,
+    /// Search timeout in seconds.
+    timeout: Option<u64>,
+}
+
+#[derive(Deserialize)]
+#[serde(default)]
+struct JsonFields {
+    name: String,
+    version: String,
+    description: String,
+    source: String,
+}
+
+impl Default for JsonFields {
+    fn default() -> Self {
+        JsonFields {
+            name: "name".to_string(),
+            version: "version".to_string(),
+            description: "description".to_string(),
+            source: "source".to_string(),
+        }
+    }
+}
+
+fn default_source() -> String {
+    "custom".to_string()
+}
+
+enum LineParser {
+    Regex(Regex),
+    Json { path: String, fields: JsonFields },
+}
+
+pub struct CustomProvider {
+    config: ProviderConfig,
+    parser: LineParser,
+    installed_regex: Option<Regex>,
+    colors: HashMap<String, Color>,
+}
+
+impl CustomProvider {
+    fn from_config(mut config: ProviderConfig) -> Result<Self, String> {
+        let name = config.name.clone();
+        let compile = |pattern: &str| {
+            Regex::new(pattern).map_err(|e| format!("provider '{name}': invalid regex: {e}"))
+        };
+
+        let parser = match (config.regex.as_deref(), config.json_path.take()) {
+            (Some(pattern), None) => {
+                let re = compile(pattern)?;
+                if !re.capture_names().flatten().any(|n| n == "name") {
+                    return Err(format!("provider '{name}': regex needs a (?P<name>...) group"));
+                }
+                LineParser::Regex(re)
+            }
+            (None, Some(path)) => LineParser::Json {
+                path,
+                fields: std::mem::take(&mut config.json_fields),
+            },
+            _ => return Err(format!("provider '{name}': set exactly one of regex or json_path")),
+        };
+
+        let installed_regex = config.installed_regex.as_deref().map(compile).transpose()?;
+
+        let mut colors = HashMap::new();
+        for (source, color) in &config.colors {
+            let color = Color::from_str(color)
+                .map_err(|_| format!("provider '{name}': unknown color '{color}'"))?;
+            colors.insert(source.clone(), color);
+        }
+
+        Ok(CustomProvider { config, parser, installed_regex, colors })
+    }
+
+    fn parse_line(&self, re: &Regex, line: &str) -> Option<Package> {
+        let caps = re.captures(line)?;
+        let field =
+            |key: &str| caps.name(key).map(|m| m.as_str().trim().to_string()).unwrap_or_default();
+        let source = field("source");
+        let pkg = Package {
+            name: field("name"),
+            version: field("version"),
+            description: field("description"),
+            source: if source.is_empty() { self.config.source.clone() } else { source },
+            ..Default::default()
+        };
+        (!pkg.name.is_empty()).then_some(pkg)
+    }
+
+    fn parse(&self, output: &str) -> Result<Vec<Package>, SearchError> {
+        match &self.parser {
+            LineParser::Regex(re) => {
+                Ok(output.lines().filter_map(|line| self.parse_line(re, line)).collect())
+            }
+            LineParser::Json { path, fields } => {
+                let root: Value = serde_json::from_str(output)
+                    .map_err(|e| SearchError::ParseFailure(e.to_string()))?;
+                let pointer: String = path
+                    .split('.')
+                    .filter(|s| !s.is_empty())
+                    .map(|s| format!("/{s}"))
+                    .collect();
+                let Some(Value::Array(items)) = root.pointer(&pointer) else {
+                    return Err(SearchError::ParseFailure(format!("no array at '{path}'")));
+                };
+                Ok(items
+                    .iter()
+                    .map(|item| {
+                        let field = |key: &str| match item.get(key) {
+                            Some(Value::String(s)) => s.clone(),
+                            Some(Value::Null) | None => String::new(),
+                            Some(other) => other.to_string(),
+                        };
+                        let source = field(&fields.source);
+                        Package {
+                            name: field(&fields.name),
+                            version: field(&fields.version),
+                            description: field(&fields.description),
+                            source: if source.is_empty() {
+                                self.config.source.clone()
+                            } else {
+                                source
+                            },
+                            ..Default::default()
+                        }
+                    })
+                    .filter(|p| !p.name.is_empty())
+                    .collect())
+            }
+        }
+    }
+
+    fn get_installed(&self) -> Arc<HashSet<String>> {
+        cached_installed(&format!("custom:{}", self.config.name), || {
+            let Some(cmd) = &self.config.installed else {
+                return HashSet::new();
+            };
+            let output = exec_command(&format!("{cmd} 2>/dev/null"));
+            output
+                .lines()
+                .filter_map(|line| match &self.installed_regex {
+                    Some(re) => re.captures(line)?.name("name").map(|m| m.as_str().to_string()),
+                    None => line.split_whitespace().next().map(|s| s.to_string()),
+                })
+                .collect()
+        })
+    }
+
+    fn fill_template(template: &str, pkg: &Package) -> String {
+        template
+            .replace("{name}", &shell_quote(&pkg.name))
+            .replace("{version}", &shell_quote(&pkg.version))
+            .replace("{source}", &shell_quote(&pkg.source))
+    }
+}
+
+impl Provider for CustomProvider {
+    fn name(&self) -> &str {
+        &self.config.name
+    }
+
+    fn is_available(&self) -> bool {
+        let (_, _, code) = exec_command_full(&format!("{} >/dev/null 2>&1", self.config.available));
+        code == 0
+    }
+
+    fn search(&self, query: &str) -> SearchResult {
+        if query.is_empty() {
+            return SearchResult { packages: vec![], error: None };
+        }
+
+        let cmd = self.config.search.replace("{query}", &shell_quote(query));
+        let installed = self.get_installed();
+        // Line-oriented output can be shown while the command runs, JSON only once complete
+        let result = match &self.parser {
+            LineParser::Regex(re) => exec_search_lines(&cmd, &[], |line| {
+                let mut pkg = self.parse_line(re, line)?;
+                pkg.installed = installed.contains(&pkg.name);
+                Some(pkg)
+            }),
+            LineParser::Json { .. } => exec_search(&cmd, &[]).and_then(|output| {
+                if output.is_empty() {
+                    return Ok(Vec::new());
+                }
+                let mut packages = self.parse(&output)?;
+                for pkg in &mut packages {
+                    pkg.installed = installed.contains(&pkg.name);
+                }
+                Ok(packages)
+            }),
+        };
+        let mut packages = match result {
+            Ok(packages) => packages,
+            Err(e) => return SearchResult { packages: vec![], error: Some(e) },
+        };
+
+        sort_by_relevance(&mut packages, query);
+        SearchResult { packages, error: None }
+    }
+
+    fn installed_names(&self) -> Option<Arc<HashSet<String>>> {
+        self.config.installed.is_some().then(|| self.get_installed())
+    }
+
+    /// The packages the `installed` command lists, with a version where
+    /// `installed_regex` has a `version` group.
+    fn list_installed(&self) -> Option<Result<Vec<Package>, SearchError>> {
+        let cmd = self.config.installed.as_deref()?;
+        let result = exec_search(cmd, &[]).map(|output| {
+            output
+                .lines()
+                .filter_map(|line| {
+                    let (name, version) = match &self.installed_regex {
+                        Some(re) => {
+                            let caps = re.captures(line)?;
+                            let version = caps.name("version").map_or("", |m| m.as_str());
+                            (caps.name("name")?.as_str(), version)
+                        }
+                        None => (line.split_whitespace().next()?, ""),
+                    };
+                    Some(Package {
+                        name: name.trim().to_string(),
+                        version: version.trim().to_string(),
+                        source: self.config.source.clone(),
+                        installed: true,
+                        ..Default::default()
+                    })
+                })
+                .collect()
+        });
+        Some(result)
+    }
+
+    fn install_command(&self, pkg: &Package) -> String {
+        Self::fill_template(&self.config.install, pkg)
+    }
+
+    fn remove_command(&self, pkg: &Package) -> Option<String> {
+        self.config.remove.as_deref().map(|t| Self::fill_template(t, pkg))
+    }
+
+    fn search_timeout(&self) -> Duration {
+        Duration::from_secs(self.config.timeout.unwrap_or(30))
+    }
+
+    fn source_color(&self, source: &str) -> Color {
+        self.colors.get(source).copied().unwrap_or(Color::White)
+    }
+}
+
+/// Load user-defined providers. Problems are reported on stderr and the
+/// offending entries skipped, so a typo never prevents fex from starting.
+pub fn load_custom_providers() -> Vec<CustomProvider> {
+    let path = config_dir().join("providers.toml");
+    let Ok(data) = fs::read_to_string(&path) else {
+        return Vec::new();
+    };
+    let config: ConfigFile = match toml::from_str(&data) {
+        Ok(config) => config,
+        Err(e) => {
+            eprintln!("Warning: ignoring {}: {e}", path.display());
+            return Vec::new();
+        }
+    };
+    config
+        .provider
+        .into_iter()
+        .filter_map(|p| match CustomProvider::from_config(p) {
+            Ok(provider) => Some(provider),
+            Err(e) => {
+                eprintln!("Warning: {}: {e}", path.display());
+                None
+            }
+        })
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn provider(toml_src: &str) -> CustomProvider {
+        let config: ConfigFile = toml::from_str(toml_src).unwrap();
+        CustomProvider::from_config(config.provider.into_iter().next().unwrap()).unwrap()
+    }
+
+    #[test]
+    fn parses_lines_with_regex() {
+        let p = provider(
+            r#"
+            [[provider]]
+            name = "pkgin"
+            available = "true"
+            search = "pkgin search {query}"
+            regex = '^(?P<name>\S+)-(?P<version>[0-9]\S*)\s+(?P<description>.*)$'
+            install = "pkgin install {name}"
+            source = "pkgsrc"
+            "#,
+        );
+        let packages = p.parse("ripgrep-14.1.0   Fast grep\nnot a package line\n").unwrap();
+        assert_eq!(packages.len(), 1);
+        assert_eq!(packages[0].name, "ripgrep");
+        assert_eq!(packages[0].version, "14.1.0");
+        assert_eq!(packages[0].description, "Fast grep");
+        assert_eq!(packages[0].source, "pkgsrc");
+        assert_eq!(p.install_command(&packages[0]), "pkgin install 'ripgrep'");
+    }
+
+    #[test]
+    fn parses_json_with_path_and_fields() {
+        let p = provider(
+            r#"
+            [[provider]]
+            name = "feed"
+            available = "true"
+            search = "feed-cli --json {query}"
+            json_path = "data.results"
+            json_fields = { description = "summary", source = "repo" }
+            install = "feed-cli add {name}@{version}"
+            "#,
+        );
+        let packages = p.parse(
+            r#"{"data": {"results": [
+                {"name": "tool", "version": "1.2", "summary": "A tool", "repo": "internal"},
+                {"name": "other", "version": 3}
+            ]}}"#,
+        )
+        .unwrap();
+        assert_eq!(packages.len(), 2);
+        assert_eq!(packages[0].description, "A tool");
+        assert_eq!(packages[0].source, "internal");
+        assert_eq!(packages[1].version, "3");
+        assert_eq!(packages[1].source, "custom");
+    }
+
+    #[test]
+    fn rejects_ambiguous_parser() {
+        let config: ConfigFile = toml::from_str(
+            r#"
+            [[provider]]
+            name = "bad"
+            available = "true"
+            search = "x"
+            install = "y"
+            "#,
+        )
+        .unwrap();
+        let p = config.provider.into_iter().next().unwrap();
+        assert!(CustomProvider::from_config(p).is_err());
+    }
+}
@@ -1,15 +1,178 @@
 use std::collections::HashSet;
+use std::sync::Arc;
 
-use ratatui::style::Color;
-
-use crate::provider::{Package, Provider, SearchResult};
-use crate::util::{command_exists, escape_query, exec_command, sort_by_relevance};
+use crate::error::SearchError;
+use crate::provider::{Color, InstallPlan, Package, PlanAction, PlanEntry, Provider, SearchResult};
+use crate::util::{
+    cached_installed, command_exists, escape_query, exec_command, exec_search, exec_search_lines,
+    group_file_matches, parse_file_list, parse_size, shell_quote, sort_by_relevance,
+};
 
 pub struct DnfProvider;
 
-fn get_installed() -> HashSet<String> {
-    let output = exec_command("rpm -qa --qf '%{NAME}\\n' 2>/dev/null");
-    output.lines().filter(|l| !l.is_empty()).map(|l| l.to_string()).collect()
+fn get_installed() -> Arc<HashSet<String>> {
+    cached_installed("rpm", || {
+        let output = exec_command("rpm -qa --qf '%{NAME}\\n' 2>/dev/null");
+        output.lines().filter(|l| !l.is_empty()).map(|l| l.to_string()).collect()
+    })
+}
+
+/// Parse a line of `dnf search` output, skipping headers.
+fn parse_line(line: &str) -> Option<Package> {
+    // Lines start with a space
+    if !line.starts_with(' ') {
+        return None;
+    }
+    if line.contains("Matched fields:")
+        || line.contains("Updating")
+        || line.contains("Repositories")
+    {
+        return None;
+    }
+
+    let line = line.trim_start();
+
+    // Format: "name.arch   description"
+    let dot = line.find('.')?;
+    let arch_end = line.find(' ')?;
+    if arch_end <= dot {
+        return None;
+    }
+    let name_arch = &line[..arch_end];
+    let description = line[arch_end..].trim_start().to_string();
+
+    // Strip .arch suffix
+    let name = match name_arch.rfind('.') {
+        Some(last_dot) => name_arch[..last_dot].to_string(),
+        None => name_arch.to_string(),
+    };
+
+    Some(Package {
+        name,
+        description,
+        source: "fedora".to_string(),
+        ..Default::default()
+    })
+}
+
+/// Split "name-version-release.arch" into the name and "version-release".
+fn split_nevra(nevra: &str) -> (&str, String) {
+    let nevr = nevra.rsplit_once('.').map_or(nevra, |(nevr, _arch)| nevr);
+    let mut parts = nevr.rsplitn(3, '-');
+    match (parts.next(), parts.next(), parts.next()) {
+        (Some(release), Some(version), Some(name)) => (name, format!("{version}-{release}")),
+        _ => (nevr, String::new()),
+    }
+}
+
+/// Parse `dnf provides`: blocks of a "nevra : summary" line followed by
+/// "Key : value" lines, of which "Repo" and "Filename" are used.
+fn parse_provides(output: &str, installed: &HashSet<String>) -> Vec<Package> {
+    let mut matches = Vec::new();
+    let mut current: Option<Package> = None;
+    for line in output.lines() {
+        if line.trim().is_empty() {
+            current = None;
+        }
+        let Some((key, value)) = line.split_once(" : ") else { continue };
+        let value = value.trim();
+        match key.trim() {
+            "Repo" => {
+                if let Some(pkg) = &mut current {
+                    pkg.source = value.to_string();
+                }
+            }
+            "Filename" => {
+                if let Some(pkg) = &current {
+                    matches.push((pkg.clone(), value.to_string()));
+                }
+            }
+            nevra if current.is_none() && !line.starts_with(' ') => {
+                let (name, version) = split_nevra(nevra);
+                current = Some(Package {
+                    name: name.to_string(),
+                    version,
+                    description: value.to_string(),
+                    source: "fedora".to_string(),
+                    installed: installed.contains(name),
+                    ..Default::default()
+                });
+            }
+            _ => {}
+        }
+    }
+    group_file_matches(matches)
+}
+
+/// Parse the transaction table and size summary printed by
+/// `dnf install --assumeno`, in either the dnf4 or the dnf5 layout.
+fn parse_transaction(output: &str) -> InstallPlan {
+    let mut plan = InstallPlan::default();
+    let mut action = None;
+    for line in output.lines() {
+        let trimmed = line.trim();
+        if let Some(rest) = trimmed.strip_prefix("Total download size:") {
+            plan.download_size = parse_size(rest);
+        } else if let Some(rest) = trimmed.strip_prefix("Installed size:") {
+            plan.install_size = parse_size(rest).map(|s| s as i64);
+        } else if let Some(rest) = trimmed.split("Need to download ").nth(1) {
+            // dnf5: "Total size of inbound packages is 65 MiB. Need to download 65 MiB."
+            plan.download_size = parse_size(rest.trim_end_matches('.'));
+        } else if let Some(rest) = trimmed.strip_prefix("After this operation, ") {
+            // dnf5: "... 250 MiB extra will be used (...)." or "... 3 MiB will be freed (...)."
+            let size = rest.split([' ', '(']).take(2).collect::<Vec<_>>().join(" ");
+            let size = parse_size(&size).map(|s| s as i64);
+            plan.install_size = if rest.contains("freed") { size.map(|s| -s) } else { size };
+        } else if !line.starts_with(' ') {
+            // Section headers such as "Installing dependencies:"
+            let header = trimmed.to_lowercase();
+            action = if !header.ends_with(':') {
+                None
+            } else if header.starts_with("installing") || header.starts_with("reinstalling") {
+                Some(PlanAction::Install)
+            } else if header.starts_with("upgrading") {
+                Some(PlanAction::Upgrade)
+            } else if header.starts_with("removing") || header.starts_with("erasing") {
+                Some(PlanAction::Remove)
+            } else {
+                None
+            };
+        } else if let Some(action) = action {
+            // " name arch version repository size"
+            let fields: Vec<&str> = trimmed.split_whitespace().collect();
+            if fields.len() < 4 || fields[0] == "replacing" {
+                continue;
+            }
+            plan.entries.push(PlanEntry {
+                action,
+                name: fields[0].to_string(),
+                version: fields[2].to_string(),
+            });
+        }
+    }
+    plan
+}
+
+/// The packages `dnf repoquery` lists with `args`, once per name.
+fn repoquery(args: &str) -> Result<Vec<Package>, SearchError> {
+    // dnf5 needs the newline; dnf4 adds its own, leaving blank lines
+    let cmd = format!("dnf repoquery --quiet --qf '%{{name}} %{{evr}}\\n' {args}");
+    let output = exec_search(&cmd, &[])?;
+    let installed = get_installed();
+    let mut packages: Vec<Package> = Vec::new();
+    for (name, version) in output.lines().filter_map(|line| line.trim().split_once(' ')) {
+        if packages.iter().any(|p| p.name == name) {
+            continue;
+        }
+        packages.push(Package {
+            name: name.to_string(),
+            version: version.to_string(),
+            source: "fedora".to_string(),
+            installed: installed.contains(name),
+            ..Default::default()
+        });
+    }
+    Ok(packages)
 }
 
 impl Provider for DnfProvider {
@@ -27,64 +190,138 @@ impl Provider for DnfProvider {
         }
 
         let escaped = escape_query(query);
-        let output = exec_command(&format!("dnf search '{escaped}' 2>/dev/null"));
-        if output.is_empty() {
-            return SearchResult { packages: vec![], error: None };
-        }
+        let installed = get_installed();
+        let mut packages = match exec_search_lines(
+            &format!("dnf search '{escaped}'"),
+            &["No matches found", "No match found"],
+            |line| {
+                let mut pkg = parse_line(line)?;
+                pkg.installed = installed.contains(&pkg.name);
+                Some(pkg)
+            },
+        ) {
+            Ok(packages) => packages,
+            Err(e) => return SearchResult { packages: vec![], error: Some(e) },
+        };
 
+        sort_by_relevance(&mut packages, query);
+        SearchResult { packages, error: None }
+    }
+
+    fn list_all(&self) -> Option<Result<Vec<Package>, SearchError>> {
         let installed = get_installed();
-        let mut packages = Vec::new();
+        let mut seen = HashSet::new();
+        // The format ends in a real newline: dnf5 needs one, dnf4 adds its own
+        let result = exec_search_lines(
+            "dnf repoquery --quiet --qf '%{name}|%{version}|%{repoid}|%{summary}\n'",
+            &[],
+            |line| {
+                let mut fields = line.splitn(4, '|');
+                let name = fields.next()?.trim();
+                // One entry per architecture; keep the first
+                if name.is_empty() || !seen.insert(name.to_string()) {
+                    return None;
+                }
+                let version = fields.next().unwrap_or("");
+                let repo = fields.next().filter(|r| !r.is_empty()).unwrap_or("fedora");
+                Some(Package {
+                    name: name.to_string(),
+                    version: version.to_string(),
+                    description: fields.next().unwrap_or("").to_string(),
+                    source: repo.to_string(),
+                    installed: installed.contains(name),
+                    ..Default::default()
+                })
+            },
+        );
+        Some(result)
+    }
 
-        for line in output.lines() {
-            if line.is_empty() {
-                continue;
-            }
-            // Lines start with a space
-            if !line.starts_with(' ') {
-                continue;
-            }
-            if line.contains("Matched fields:")
-                || line.contains("Updating")
-                || line.contains("Repositories")
-            {
-                continue;
-            }
+    fn can_list_all(&self) -> bool {
+        true
+    }
 
-            let line = line.trim_start();
+    fn list_installed(&self) -> Option<Result<Vec<Package>, SearchError>> {
+        // dnf5 needs the newline; dnf4 adds its own, leaving blank lines
+        let cmd = "dnf repoquery --userinstalled --qf '%{name} %{evr}\\n'";
+        let result = exec_search(cmd, &[]).map(|output| {
+            output
+                .lines()
+                .filter_map(|line| line.trim().split_once(' '))
+                .map(|(name, version)| Package {
+                    name: name.to_string(),
+                    version: version.to_string(),
+                    source: "fedora".to_string(),
+                    installed: true,
+                    ..Default::default()
+                })
+                .collect()
+        });
+        Some(result)
+    }
 
-            // Format: "name.arch   description"
-            let Some(dot) = line.find('.') else { continue };
-            let Some(arch_end) = line.find(' ') else { continue };
-            if arch_end <= dot {
-                continue;
-            }
-            let name_arch = &line[..arch_end];
-            let description = line[arch_end..].trim_start().to_string();
+    fn plan_install(&self, pkgs: &[Package]) -> Option<Result<InstallPlan, SearchError>> {
+        let names: Vec<String> = pkgs.iter().map(|p| shell_quote(&p.name)).collect();
+        // Answers "no" to the prompt, so only the transaction is printed
+        let cmd = format!("LANG=C dnf install --assumeno {}", names.join(" "));
+        Some(exec_search(&cmd, &[]).map(|output| parse_transaction(&output)))
+    }
 
-            // Strip .arch suffix
-            let name = match name_arch.rfind('.') {
-                Some(last_dot) => name_arch[..last_dot].to_string(),
-                None => name_arch.to_string(),
-            };
+    fn installed_names(&self) -> Option<Arc<HashSet<String>>> {
+        Some(get_installed())
+    }
 
-            let is_installed = installed.contains(&name);
-            packages.push(Package {
-                name,
-                version: String::new(),
-                description,
-                source: "fedora".to_string(),
-                installed: is_installed,
-            });
-        }
+    /// Resolved to the packages providing each requirement.
+    fn dependencies(&self, pkg: &Package) -> Option<Result<Vec<Package>, SearchError>> {
+        let name = shell_quote(&pkg.name);
+        Some(repoquery(&format!("--requires --resolve {name}")))
+    }
 
-        sort_by_relevance(&mut packages, query);
-        SearchResult { packages, error: None }
+    fn reverse_dependencies(&self, pkg: &Package) -> Option<Result<Vec<Package>, SearchError>> {
+        let name = shell_quote(&pkg.name);
+        Some(repoquery(&format!("--whatrequires {name}")))
+    }
+
+    fn search_file(&self, path: &str) -> Option<Result<Vec<Package>, SearchError>> {
+        let patterns = if path.contains('/') {
+            shell_quote(path)
+        } else {
+            let bin = shell_quote(&format!("*/bin/{path}"));
+            format!("{bin} {}", shell_quote(&format!("*/sbin/{path}")))
+        };
+        let cmd = format!("LANG=C dnf -q provides {patterns}");
+        let result = exec_search(&cmd, &["No Matches found", "No matches found"])
+            .map(|output| parse_provides(&output, &get_installed()));
+        Some(result)
+    }
+
+    /// Installed packages are listed with their file modes, so that the
+    /// directories they own can be left out too.
+    fn list_files(&self, pkg: &Package) -> Option<Result<Vec<String>, SearchError>> {
+        let name = shell_quote(&pkg.name);
+        let result = if pkg.installed {
+            let cmd = format!("rpm -q --qf '[%{{FILEMODES:perms}} %{{FILENAMES}}\\n]' {name}");
+            exec_search(&cmd, &[]).map(|output| {
+                parse_file_list(output.lines().filter_map(|line| {
+                    let (mode, path) = line.split_once(' ')?;
+                    (!mode.starts_with('d')).then_some(path)
+                }))
+            })
+        } else {
+            let cmd = format!("dnf repoquery --quiet --list {name}");
+            exec_search(&cmd, &[]).map(|output| parse_file_list(output.lines()))
+        };
+        Some(result)
     }
 
     fn install_command(&self, pkg: &Package) -> String {
         format!("sudo dnf install {}", pkg.name)
     }
 
+    fn remove_command(&self, pkg: &Package) -> Option<String> {
+        Some(format!("sudo dnf remove {}", pkg.name))
+    }
+
     fn source_color(&self, source: &str) -> Color {
         match source {
             "fedora" => Color::Blue,
@@ -94,3 +331,82 @@ impl Provider for DnfProvider {
         }
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn parses_dnf4_and_dnf5_transactions() {
+        let dnf4 = "Dependencies resolved.
+================================================================================
+ Package            Arch       Version              Repository           Size
+================================================================================
+Installing:
+ ripgrep            x86_64     14.1.0-1.fc40        updates             1.7 M
+Installing dependencies:
+ pcre2              x86_64     10.42-2.fc40         fedora              233 k
+Upgrading:
+ glibc              x86_64     2.39-6.fc40          updates             2.2 M
+
+Transaction Summary
+================================================================================
+Install  2 Packages
+
+Total download size: 4.1 M
+Installed size: 6.0 M
+Operation aborted.
+";
+        let plan = parse_transaction(dnf4);
+        let names: Vec<_> = plan.entries.iter().map(|e| (e.action, e.name.as_str())).collect();
+        assert_eq!(
+            names,
+            [
+                (PlanAction::Install, "ripgrep"),
+                (PlanAction::Install, "pcre2"),
+                (PlanAction::Upgrade, "glibc")
+            ]
+        );
+        assert_eq!(plan.entries[0].version, "14.1.0-1.fc40");
+        assert_eq!(plan.download_size, parse_size("4.1 M"));
+        assert_eq!(plan.install_size, Some(6 * 1024 * 1024));
+
+        let dnf5 = "Package  Arch   Version  Repository  Size
+Installing:
+ ripgrep x86_64 14.1.0-1.fc41 updates 4.2 MiB
+Removing:
+ old-rg  x86_64 13.0.0-1.fc41 @System 3.0 MiB
+
+Transaction Summary:
+ Installing:         1 package
+
+Total size of inbound packages is 1 MiB. Need to download 1 MiB.
+After this operation, 1 MiB extra will be used (install 4 MiB, remove 3 MiB).
+";
+        let plan = parse_transaction(dnf5);
+        assert_eq!(plan.entries.len(), 2);
+        assert_eq!(plan.entries[1].action, PlanAction::Remove);
+        assert_eq!(plan.download_size, Some(1024 * 1024));
+        assert_eq!(plan.install_size, Some(1024 * 1024));
+    }
+
+    #[test]
+    fn parses_provides_blocks() {
+        let output = "coreutils-9.5-1.fc41.x86_64 : coreutils - GNU core utilities
+Repo        : fedora
+Matched from:
+Filename    : /usr/bin/ls
+
+coreutils-9.5-3.fc41.x86_64 : coreutils - GNU core utilities
+Repo        : updates
+Matched from:
+Filename    : /usr/bin/ls
+";
+        let packages = parse_provides(output, &HashSet::new());
+        assert_eq!(packages.len(), 2);
+        assert_eq!(packages[0].name, "coreutils");
+        assert_eq!(packages[0].version, "9.5-1.fc41");
+        assert_eq!(packages[1].source, "updates");
+        assert_eq!(packages[1].files, ["/usr/bin/ls"]);
+    }
+}
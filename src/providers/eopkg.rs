@@ -0,0 +1,199 @@
+use std::collections::HashSet;
+use std::sync::Arc;
+
+use crate::error::SearchError;
+use crate::provider::{Color, Package, Provider, SearchResult};
+use crate::util::{
+    cached_installed, command_exists, escape_query, exec_command, exec_search, sort_by_relevance,
+};
+
+pub struct EopkgProvider;
+
+fn get_installed() -> Arc<HashSet<String>> {
+    cached_installed("eopkg", || {
+        let output = exec_command("eopkg li -N 2>/dev/null");
+        output
+            .lines()
+            .filter_map(|l| l.split_whitespace().next())
+            .map(|name| name.to_string())
+            .collect()
+    })
+}
+
+/// Parse `eopkg search` lines of the form "name   - summary".
+fn parse_search_output(output: &str) -> Vec<Package> {
+    let mut packages = Vec::new();
+    for line in output.lines() {
+        let Some(sep) = line.find(" - ") else { continue };
+        let name = line[..sep].trim();
+        if name.is_empty() || name.contains(' ') {
+            continue;
+        }
+        packages.push(Package {
+            name: name.to_string(),
+            description: line[sep + 3..].trim().to_string(),
+            source: "solus".to_string(),
+            ..Default::default()
+        });
+    }
+    packages
+}
+
+/// Parse `eopkg info` output. When the package is both installed and in a
+/// repository, the repository section comes last and wins.
+fn parse_info_output(output: &str) -> Option<Package> {
+    let mut pkg = Package {
+        name: String::new(),
+        source: "solus".to_string(),
+        ..Default::default()
+    };
+    for line in output.lines() {
+        if line.starts_with("Installed package") {
+            pkg.installed = true;
+        } else if let Some(repo) = line
+            .strip_prefix("Package found in ")
+            .and_then(|rest| rest.strip_suffix(" repository:"))
+        {
+            pkg.source = repo.to_lowercase();
+        } else if let Some(val) = field_value(line, "Name") {
+            // Format: "Name : firefox, version: 128.0, release: 250"
+            let mut parts = val.split(", ");
+            pkg.name = parts.next().unwrap_or("").to_string();
+            if let Some(version) = parts.find_map(|p| p.strip_prefix("version: ")) {
+                pkg.version = version.to_string();
+            }
+        } else if let Some(val) = field_value(line, "Summary") {
+            pkg.description = val;
+        }
+    }
+    if pkg.name.is_empty() { None } else { Some(pkg) }
+}
+
+fn field_value(line: &str, key: &str) -> Option<String> {
+    if line.starts_with(key)
+        && let Some(colon) = line.find(':')
+    {
+        return Some(line[colon + 1..].trim().to_string());
+    }
+    None
+}
+
+impl Provider for EopkgProvider {
+    fn name(&self) -> &str {
+        "eopkg"
+    }
+
+    fn is_available(&self) -> bool {
+        command_exists("eopkg")
+    }
+
+    fn search(&self, query: &str) -> SearchResult {
+        if query.is_empty() {
+            return SearchResult { packages: vec![], error: None };
+        }
+
+        let escaped = escape_query(query);
+
+        // Try exact match via eopkg info, which also carries the version
+        let exact_match = match exec_search(&format!("eopkg info -N '{escaped}'"), &["not found"]) {
+            Ok(output) => parse_info_output(&output),
+            Err(e) => return SearchResult { packages: vec![], error: Some(e) },
+        };
+
+        let output = match exec_search(&format!("eopkg search -N '{escaped}'"), &[]) {
+            Ok(output) => output,
+            Err(e) => return SearchResult { packages: vec![], error: Some(e) },
+        };
+        if output.is_empty() && exact_match.is_none() {
+            return SearchResult { packages: vec![], error: None };
+        }
+
+        let installed = get_installed();
+        let mut packages = parse_search_output(&output);
+        for pkg in &mut packages {
+            pkg.installed = installed.contains(&pkg.name);
+        }
+
+        if let Some(em) = exact_match {
+            packages.retain(|p| p.name != em.name);
+            packages.insert(0, em);
+        }
+
+        sort_by_relevance(&mut packages, query);
+        SearchResult { packages, error: None }
+    }
+
+    fn installed_names(&self) -> Option<Arc<HashSet<String>>> {
+        Some(get_installed())
+    }
+
+    /// eopkg doesn't record which packages were asked for, so this is every
+    /// installed one, listed like search results.
+    fn list_installed(&self) -> Option<Result<Vec<Package>, SearchError>> {
+        let result = exec_search("eopkg li -N", &[]).map(|output| {
+            let mut packages = parse_search_output(&output);
+            packages.iter_mut().for_each(|pkg| pkg.installed = true);
+            packages
+        });
+        Some(result)
+    }
+
+    fn install_command(&self, pkg: &Package) -> String {
+        format!("sudo eopkg install {}", pkg.name)
+    }
+
+    fn remove_command(&self, pkg: &Package) -> Option<String> {
+        Some(format!("sudo eopkg remove {}", pkg.name))
+    }
+
+    fn source_color(&self, source: &str) -> Color {
+        match source {
+            "solus" => Color::Blue,
+            "unstable" => Color::Yellow,
+            _ => Color::Cyan,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const SEARCH_OUTPUT: &str = "\
+firefox                        - Mozilla Firefox web browser
+firefox-devel                  - Development files for firefox
+";
+
+    const INFO_OUTPUT: &str = "\
+Installed package:
+Name                : firefox, version: 127.0, release: 249
+Summary             : Mozilla Firefox web browser
+Package found in Solus repository:
+Name                : firefox, version: 128.0, release: 250
+Summary             : Mozilla Firefox web browser
+Component           : network.web.browser
+";
+
+    #[test]
+    fn parses_search_lines() {
+        let packages = parse_search_output(SEARCH_OUTPUT);
+        assert_eq!(packages.len(), 2);
+        assert_eq!(packages[0].name, "firefox");
+        assert_eq!(packages[0].description, "Mozilla Firefox web browser");
+        assert_eq!(packages[1].name, "firefox-devel");
+    }
+
+    #[test]
+    fn parses_info_with_installed_and_repo_sections() {
+        let pkg = parse_info_output(INFO_OUTPUT).unwrap();
+        assert_eq!(pkg.name, "firefox");
+        assert_eq!(pkg.version, "128.0");
+        assert_eq!(pkg.source, "solus");
+        assert!(pkg.installed);
+    }
+
+    #[test]
+    fn info_without_package_is_none() {
+        assert!(parse_info_output("").is_none());
+    }
+}
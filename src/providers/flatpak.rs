@@ -1,15 +1,56 @@
 use std::collections::HashSet;
+use std::sync::Arc;
 
-use ratatui::style::Color;
-
-use crate::provider::{Package, Provider, SearchResult};
-use crate::util::{command_exists, escape_query, exec_command, sort_by_relevance};
+use crate::error::SearchError;
+use crate::provider::{Color, InstallPlan, Package, PlanAction, PlanEntry, Provider, SearchResult};
+use crate::util::{
+    cached_installed, command_exists, escape_query, exec_command, exec_search, parse_size,
+    shell_quote, sort_by_relevance,
+};
 
 pub struct FlatpakProvider;
 
-fn get_installed() -> HashSet<String> {
-    let output = exec_command("flatpak list --columns=application 2>/dev/null");
-    output.lines().filter(|l| !l.is_empty()).map(|l| l.to_string()).collect()
+fn get_installed() -> Arc<HashSet<String>> {
+    cached_installed("flatpak", || {
+        let output = exec_command("flatpak list --columns=application 2>/dev/null");
+        output.lines().filter(|l| !l.is_empty()).map(|l| l.to_string()).collect()
+    })
+}
+
+/// Parse the numbered operation table `flatpak install` shows before asking
+/// to proceed, e.g. ` 1. [✓] org.gnome.Platform  46  i  flathub  < 362.9 MB`.
+/// Returns the number of operations found.
+fn parse_operations(output: &str, plan: &mut InstallPlan) -> usize {
+    let before = plan.entries.len();
+    for line in output.lines() {
+        let mut fields = line.split_whitespace().peekable();
+        let Some(number) = fields.next() else { continue };
+        if number.strip_suffix('.').is_none_or(|n| n.parse::<u32>().is_err()) {
+            continue;
+        }
+        // Progress marker of newer versions
+        fields.next_if(|f| f.starts_with('['));
+        let (Some(id), Some(branch), Some(op)) = (fields.next(), fields.next(), fields.next())
+        else {
+            continue;
+        };
+        let action = match op {
+            "u" => PlanAction::Upgrade,
+            "r" => PlanAction::Remove,
+            _ => PlanAction::Install,
+        };
+        plan.entries.push(PlanEntry {
+            action,
+            name: id.to_string(),
+            version: branch.to_string(),
+        });
+        // Remote, then "< 362.9 MB" and maybe "(partial)"
+        let size: Vec<&str> = fields.skip(1).filter(|f| *f != "<" && !f.starts_with('(')).collect();
+        if let Some(size) = parse_size(&size.join(" ")) {
+            *plan.download_size.get_or_insert(0) += size;
+        }
+    }
+    plan.entries.len() - before
 }
 
 impl Provider for FlatpakProvider {
@@ -27,7 +68,13 @@ impl Provider for FlatpakProvider {
         }
 
         let escaped = escape_query(query);
-        let output = exec_command(&format!("flatpak search '{escaped}' 2>/dev/null"));
+        let output = match exec_search(
+            &format!("flatpak search '{escaped}'"),
+            &["No matches found"],
+        ) {
+            Ok(output) => output,
+            Err(e) => return SearchResult { packages: vec![], error: Some(e) },
+        };
         if output.is_empty() {
             return SearchResult { packages: vec![], error: None };
         }
@@ -69,6 +116,7 @@ impl Provider for FlatpakProvider {
                 description,
                 source: remote,
                 installed: is_installed,
+                ..Default::default()
             });
         }
 
@@ -76,11 +124,120 @@ impl Provider for FlatpakProvider {
         SearchResult { packages, error: None }
     }
 
+    /// Applications only; runtimes come with them.
+    fn list_installed(&self) -> Option<Result<Vec<Package>, SearchError>> {
+        let cmd = "flatpak list --app --columns=application,version,origin";
+        let result = exec_search(cmd, &[]).map(|output| {
+            output
+                .lines()
+                .filter_map(|line| {
+                    let mut fields = line.split('\t');
+                    let (id, version) = (fields.next()?, fields.next().unwrap_or(""));
+                    Some(Package {
+                        name: id.to_string(),
+                        version: version.to_string(),
+                        source: fields.next().unwrap_or("flathub").to_string(),
+                        installed: true,
+                        ..Default::default()
+                    })
+                })
+                .collect()
+        });
+        Some(result)
+    }
+
+    fn plan_install(&self, pkgs: &[Package]) -> Option<Result<InstallPlan, SearchError>> {
+        let mut plan = InstallPlan::default();
+        let mut remotes: Vec<&str> = pkgs.iter().map(|p| p.source.as_str()).collect();
+        remotes.sort_unstable();
+        remotes.dedup();
+        for remote in remotes {
+            let ids: Vec<String> =
+                pkgs.iter().filter(|p| p.source == remote).map(|p| shell_quote(&p.name)).collect();
+            // Declining the prompt leaves just the table of planned operations.
+            // Any other question flatpak asks first is declined, or reads the
+            // end of input, and aborts without a table.
+            let cmd = format!(
+                "echo n | flatpak install --no-deploy {} {}",
+                shell_quote(remote),
+                ids.join(" ")
+            );
+            let output = match exec_search(&cmd, &[]) {
+                Ok(output) => output,
+                Err(e) => return Some(Err(e)),
+            };
+            if parse_operations(&output, &mut plan) == 0 && !output.contains("already installed") {
+                let message = "no operations in the output of flatpak install".to_string();
+                return Some(Err(SearchError::ParseFailure(message)));
+            }
+        }
+        Some(Ok(plan))
+    }
+
+    fn installed_names(&self) -> Option<Arc<HashSet<String>>> {
+        Some(get_installed())
+    }
+
     fn install_command(&self, pkg: &Package) -> String {
         format!("flatpak install {} {}", pkg.source, pkg.name)
     }
 
+    fn remove_command(&self, pkg: &Package) -> Option<String> {
+        Some(format!("flatpak uninstall {}", pkg.name))
+    }
+
     fn source_color(&self, _source: &str) -> Color {
         Color::Blue
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn parses_operation_table() {
+        let output = "Looking for matches…
+
+        ID                                 Branch   Op   Remote    Download
+ 1.     org.gnome.Platform.Locale          46       i    flathub   < 371.1 MB (partial)
+ 2. [✓] org.gnome.Platform                 46       u    flathub   < 362.9 MB
+ 3.     org.gnome.Calculator               stable   i    flathub     < 2.0 MB
+
+Proceed with these changes to the user installation? [Y/n]: n
+";
+        let mut plan = InstallPlan::default();
+        assert_eq!(parse_operations(output, &mut plan), 3);
+        assert_eq!(plan.entries[1].name, "org.gnome.Platform");
+        assert_eq!(plan.entries[1].action, PlanAction::Upgrade);
+        assert_eq!(plan.entries[2].version, "stable");
+        let total = ["371.1 MB", "362.9 MB", "2.0 MB"].iter().filter_map(|s| parse_size(s)).sum();
+        assert_eq!(plan.download_size, Some(total));
+    }
+
+    #[test]
+    fn parses_operation_table_without_terminal() {
+        // Piped, flatpak leaves out the progress column and prints the prompt
+        // without a newline after the answer it read
+        let output = "Looking for matches…
+
+
+        ID                       Branch    Op    Remote     Download
+ 1.     org.gnome.Calculator     stable    i     flathub    < 2.0 MB
+
+Proceed with these changes to the user installation? [Y/n]: ";
+        let mut plan = InstallPlan::default();
+        assert_eq!(parse_operations(output, &mut plan), 1);
+        assert_eq!(plan.entries[0].name, "org.gnome.Calculator");
+        assert_eq!(plan.download_size, Some(2_000_000));
+
+        // Declining an earlier question leaves no table
+        let output = "Similar refs found for ‘calc’ in remote ‘flathub’:
+
+   1) app/org.gnome.Calculator/x86_64/stable
+   2) app/org.kde.kcalc/x86_64/stable
+
+Which do you want to use (0 to abort)? [0-2]: ";
+        assert_eq!(parse_operations(output, &mut InstallPlan::default()), 0);
+    }
+}
@@ -0,0 +1,233 @@
+use std::collections::HashSet;
+use std::sync::Arc;
+
+use crate::error::SearchError;
+use crate::provider::{Color, Package, Provider, SearchResult};
+use crate::util::{
+    cached_installed, command_exists, escape_query, exec_command, exec_search, sort_by_relevance,
+};
+
+pub struct GuixProvider;
+
+fn get_installed() -> Arc<HashSet<String>> {
+    cached_installed("guix", || {
+        // Format: name\tversion\toutput\tstore-path
+        let output = exec_command("guix package -I 2>/dev/null");
+        output
+            .lines()
+            .filter_map(|l| l.split('\t').next())
+            .filter(|name| !name.is_empty())
+            .map(|name| name.to_string())
+            .collect()
+    })
+}
+
+/// Parse `guix package -I`, one "name\tversion\toutput\tstore-path" line
+/// per installed output of a package. The channel isn't listed.
+fn parse_installed(output: &str) -> Vec<Package> {
+    let mut packages: Vec<Package> = Vec::new();
+    for line in output.lines() {
+        let mut fields = line.split('\t');
+        let (Some(name), Some(version)) = (fields.next(), fields.next()) else { continue };
+        if name.is_empty() || packages.iter().any(|p| p.name == name) {
+            continue;
+        }
+        packages.push(Package {
+            name: name.to_string(),
+            version: version.to_string(),
+            installed: true,
+            ..Default::default()
+        });
+    }
+    packages
+}
+
+/// Derive the channel from a location such as "gnu/packages/base.scm:94:2".
+/// Packages from other load paths have absolute locations, such as
+/// "/home/me/chan/my/packages/tools.scm:12:2"; their channel is named after
+/// the directory holding `packages` ("my"), or else the file's directory.
+fn channel_from_location(location: &str) -> String {
+    let path = location.split(':').next().unwrap_or(location);
+    let dirs: Vec<&str> = path.split('/').filter(|s| !s.is_empty()).collect();
+    let dirs = &dirs[..dirs.len().saturating_sub(1)];
+    let channel = match dirs.iter().rposition(|dir| *dir == "packages") {
+        Some(i) if i > 0 => dirs[i - 1],
+        _ => dirs.last().copied().unwrap_or(""),
+    };
+    match channel {
+        "gnu" | "" => "gnu".to_string(),
+        "nongnu" => "nonguix".to_string(),
+        other => other.to_string(),
+    }
+}
+
+/// Parse recutils records from `guix search`, separated by blank lines.
+/// Continuation lines start with "+ " and belong to the previous field.
+fn parse_search_output(output: &str) -> Vec<Package> {
+    let mut packages = Vec::new();
+
+    for record in output.split("\n\n") {
+        let mut pkg = Package {
+            name: String::new(),
+            source: "gnu".to_string(),
+            ..Default::default()
+        };
+        for line in record.lines() {
+            if line.starts_with('+') {
+                continue;
+            }
+            let Some((key, val)) = line.split_once(':') else { continue };
+            let val = val.trim();
+            match key {
+                "name" => pkg.name = val.to_string(),
+                "version" => pkg.version = val.to_string(),
+                "synopsis" => pkg.description = val.to_string(),
+                "location" => pkg.source = channel_from_location(val),
+                _ => {}
+            }
+        }
+        if !pkg.name.is_empty() {
+            packages.push(pkg);
+        }
+    }
+
+    packages
+}
+
+impl Provider for GuixProvider {
+    fn name(&self) -> &str {
+        "guix"
+    }
+
+    fn is_available(&self) -> bool {
+        command_exists("guix")
+    }
+
+    fn search(&self, query: &str) -> SearchResult {
+        if query.is_empty() {
+            return SearchResult { packages: vec![], error: None };
+        }
+
+        let escaped = escape_query(query);
+        let output = match exec_search(&format!("guix search '{escaped}'"), &[]) {
+            Ok(output) => output,
+            Err(e) => return SearchResult { packages: vec![], error: Some(e) },
+        };
+        if output.is_empty() {
+            return SearchResult { packages: vec![], error: None };
+        }
+
+        let installed = get_installed();
+        let mut packages = parse_search_output(&output);
+        for pkg in &mut packages {
+            pkg.installed = installed.contains(&pkg.name);
+        }
+
+        sort_by_relevance(&mut packages, query);
+        SearchResult { packages, error: None }
+    }
+
+    fn installed_names(&self) -> Option<Arc<HashSet<String>>> {
+        Some(get_installed())
+    }
+
+    /// The packages of the user's profile.
+    fn list_installed(&self) -> Option<Result<Vec<Package>, SearchError>> {
+        Some(exec_search("guix package -I", &[]).map(|output| parse_installed(&output)))
+    }
+
+    fn install_command(&self, pkg: &Package) -> String {
+        format!("guix install {}", pkg.name)
+    }
+
+    fn remove_command(&self, pkg: &Package) -> Option<String> {
+        Some(format!("guix remove {}", pkg.name))
+    }
+
+    fn source_color(&self, source: &str) -> Color {
+        match source {
+            "gnu" => Color::Yellow,
+            "nonguix" => Color::Red,
+            _ => Color::Magenta,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const SEARCH_OUTPUT: &str = "\
+name: hello
+version: 2.12.1
+outputs:
++ out: everything
+systems: x86_64-linux i686-linux
+dependencies:
+location: gnu/packages/base.scm:94:2
+homepage: https://www.gnu.org/software/hello/
+license: GPL 3+
+synopsis: Example GNU package
+description: GNU Hello prints the message \"Hello, World!\" and then exits.  It
++ serves as an example of standard GNU coding practices.
+relevance: 15
+
+name: firefox
+version: 128.0
+outputs:
++ out: everything
+location: nongnu/packages/mozilla.scm:120:2
+synopsis: Trademarkless version of Firefox
+description: Full-featured browser client built from Firefox source tree.
+relevance: 4
+
+";
+
+    #[test]
+    fn lists_each_installed_package_once() {
+        let output = "git\t2.45.2\tout\t/gnu/store/abc-git-2.45.2\n\
+                      git\t2.45.2\tsend-email\t/gnu/store/def-git-2.45.2-send-email\n\
+                      htop\t3.3.0\tout\t/gnu/store/ghi-htop-3.3.0\n";
+        let packages = parse_installed(output);
+        assert_eq!(packages.len(), 2);
+        assert_eq!((packages[0].name.as_str(), packages[0].version.as_str()), ("git", "2.45.2"));
+        assert_eq!(packages[1].name, "htop");
+    }
+
+    #[test]
+    fn parses_multiple_records() {
+        let packages = parse_search_output(SEARCH_OUTPUT);
+        assert_eq!(packages.len(), 2);
+
+        assert_eq!(packages[0].name, "hello");
+        assert_eq!(packages[0].version, "2.12.1");
+        assert_eq!(packages[0].description, "Example GNU package");
+        assert_eq!(packages[0].source, "gnu");
+
+        assert_eq!(packages[1].name, "firefox");
+        assert_eq!(packages[1].version, "128.0");
+        assert_eq!(packages[1].source, "nonguix");
+    }
+
+    #[test]
+    fn names_channels_of_absolute_locations() {
+        assert_eq!(channel_from_location("/home/me/chan/my/packages/tools.scm:12:2"), "my");
+        assert_eq!(channel_from_location("/srv/guix/extra/emacs.scm:3:2"), "extra");
+        assert_eq!(channel_from_location("gnu/packages/base.scm:94:2"), "gnu");
+    }
+
+    #[test]
+    fn parses_last_record_without_trailing_blank_line() {
+        let packages = parse_search_output("name: guile\nversion: 3.0.9\nsynopsis: Scheme");
+        assert_eq!(packages.len(), 1);
+        assert_eq!(packages[0].name, "guile");
+        assert_eq!(packages[0].source, "gnu");
+    }
+
+    #[test]
+    fn continuation_lines_do_not_start_records() {
+        let packages = parse_search_output("name: a\n+ name: b\nsynopsis: x\n");
+        assert_eq!(packages.len(), 1);
+        assert_eq!(packages[0].name, "a");
+    }
+}
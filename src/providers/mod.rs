@@ -1,76 +1,116 @@
 pub mod apk;
+pub mod appimage;
 pub mod apt;
 pub mod brew;
+pub mod conda;
+pub mod custom;
 pub mod dnf;
+pub mod eopkg;
 pub mod flatpak;
+pub mod guix;
 pub mod nix;
 pub mod pacman;
 pub mod paru;
+pub mod sbopkg;
+pub mod slackpkg;
 pub mod snap;
 pub mod xbps;
 pub mod yay;
 pub mod zerobrew;
 pub mod zypper;
 
-use crate::provider::BoxedProvider;
+use crate::provider::{BoxedProvider, Provider as _};
 
 /// Auto-detection priority order:
-/// paru → yay → pacman → xbps → zerobrew → brew → dnf → apk → zypper → nix → apt → snap → flatpak
+/// paru → yay → pacman → xbps → eopkg → slackpkg → sbopkg → zerobrew → brew → dnf → apk → zypper → nix → guix → apt → snap → flatpak → conda → custom providers
+/// appimage is left out: curl is on nearly every Linux machine, so it would
+/// shadow the custom providers. Select it with `-p appimage`.
 pub fn auto_detect_provider() -> Option<BoxedProvider> {
-    let candidates: Vec<BoxedProvider> = vec![
+    let mut candidates: Vec<BoxedProvider> = vec![
         Box::new(paru::ParuProvider),
         Box::new(yay::YayProvider),
         Box::new(pacman::PacmanProvider),
         Box::new(xbps::XbpsProvider),
+        Box::new(eopkg::EopkgProvider),
+        Box::new(slackpkg::SlackpkgProvider),
+        Box::new(sbopkg::SbopkgProvider),
         Box::new(zerobrew::ZerobrewProvider),
         Box::new(brew::BrewProvider),
         Box::new(dnf::DnfProvider),
         Box::new(apk::ApkProvider),
         Box::new(zypper::ZypperProvider),
         Box::new(nix::NixProvider),
+        Box::new(guix::GuixProvider),
         Box::new(apt::AptProvider),
         Box::new(snap::SnapProvider),
         Box::new(flatpak::FlatpakProvider),
+        Box::new(conda::CondaProvider),
     ];
+    for custom in custom::load_custom_providers() {
+        candidates.push(Box::new(custom));
+    }
     candidates.into_iter().find(|p| p.is_available())
 }
 
-/// Create a provider by name.
+/// Create a provider by name. Built-in providers take precedence over
+/// custom providers of the same name.
 pub fn create_provider(name: &str) -> Option<BoxedProvider> {
     match name {
         "paru" => Some(Box::new(paru::ParuProvider)),
         "yay" => Some(Box::new(yay::YayProvider)),
         "pacman" => Some(Box::new(pacman::PacmanProvider)),
         "xbps" => Some(Box::new(xbps::XbpsProvider)),
+        "eopkg" => Some(Box::new(eopkg::EopkgProvider)),
+        "slackpkg" => Some(Box::new(slackpkg::SlackpkgProvider)),
+        "sbopkg" => Some(Box::new(sbopkg::SbopkgProvider)),
         "zerobrew" => Some(Box::new(zerobrew::ZerobrewProvider)),
         "brew" => Some(Box::new(brew::BrewProvider)),
         "dnf" => Some(Box::new(dnf::DnfProvider)),
         "apk" => Some(Box::new(apk::ApkProvider)),
         "zypper" => Some(Box::new(zypper::ZypperProvider)),
         "nix" => Some(Box::new(nix::NixProvider)),
+        "guix" => Some(Box::new(guix::GuixProvider)),
         "apt" => Some(Box::new(apt::AptProvider)),
         "snap" => Some(Box::new(snap::SnapProvider)),
         "flatpak" => Some(Box::new(flatpak::FlatpakProvider)),
-        _ => None,
+        "conda" => Some(Box::new(conda::CondaProvider)),
+        "appimage" => Some(Box::new(appimage::AppImageProvider)),
+        _ => custom::load_custom_providers()
+            .into_iter()
+            .find(|p| p.name() == name)
+            .map(|p| Box::new(p) as BoxedProvider),
     }
 }
 
 /// Returns a list of (name, provider) for every available provider.
-pub fn get_available_providers() -> Vec<(&'static str, BoxedProvider)> {
+pub fn get_available_providers() -> Vec<(String, BoxedProvider)> {
     let candidates: Vec<(&'static str, BoxedProvider)> = vec![
         ("paru", Box::new(paru::ParuProvider)),
         ("yay", Box::new(yay::YayProvider)),
         ("pacman", Box::new(pacman::PacmanProvider)),
         ("xbps", Box::new(xbps::XbpsProvider)),
+        ("eopkg", Box::new(eopkg::EopkgProvider)),
+        ("slackpkg", Box::new(slackpkg::SlackpkgProvider)),
+        ("sbopkg", Box::new(sbopkg::SbopkgProvider)),
         ("zerobrew", Box::new(zerobrew::ZerobrewProvider)),
         ("brew", Box::new(brew::BrewProvider)),
         ("dnf", Box::new(dnf::DnfProvider)),
         ("apk", Box::new(apk::ApkProvider)),
         ("zypper", Box::new(zypper::ZypperProvider)),
         ("nix", Box::new(nix::NixProvider)),
+        ("guix", Box::new(guix::GuixProvider)),
         ("apt", Box::new(apt::AptProvider)),
         ("snap", Box::new(snap::SnapProvider)),
         ("flatpak", Box::new(flatpak::FlatpakProvider)),
+        ("conda", Box::new(conda::CondaProvider)),
+        ("appimage", Box::new(appimage::AppImageProvider)),
     ];
-    candidates.into_iter().filter(|(_, p)| p.is_available()).collect()
+    let mut all: Vec<(String, BoxedProvider)> =
+        candidates.into_iter().map(|(name, p)| (name.to_string(), p)).collect();
+    for custom in custom::load_custom_providers() {
+        if !all.iter().any(|(name, _)| name == custom.name()) {
+            all.push((custom.name().to_string(), Box::new(custom)));
+        }
+    }
+    all.into_iter().filter(|(_, p)| p.is_available()).collect()
 }
@@ -1,7 +1,13 @@
-use ratatui::style::Color;
+use std::time::Duration;
 
-use crate::provider::{Package, Provider, SearchResult};
-use crate::util::{command_exists, escape_query, exec_command, sort_by_relevance};
+use serde_json::Value;
+
+use crate::error::SearchError;
+use crate::provider::{Color, Package, Provider, SearchResult};
+use crate::util::{
+    command_exists, escape_query, exec_command, exec_search, exec_search_lines,
+    group_file_matches, shell_quote, sort_by_relevance,
+};
 
 pub struct NixProvider;
 
@@ -23,6 +29,109 @@ fn extract_version(name_version: &str) -> String {
     String::new()
 }
 
+/// Name part of a derivation name, split like `builtins.parseDrvName`:
+/// "python3.12-requests-2.32.3" → "python3.12-requests".
+fn drv_pname(name: &str) -> &str {
+    name.match_indices('-')
+        .find(|(i, _)| !name[i + 1..].starts_with(|c: char| c.is_ascii_alphabetic()))
+        .map_or(name, |(i, _)| &name[..i])
+}
+
+/// Derivation name of the installed package built from attribute `attr`.
+/// `nix-env --uninstall` takes these rather than attribute paths.
+fn installed_drv_name(attr: &str) -> Option<String> {
+    let available = exec_command(&format!("nix-env -qaA {} 2>/dev/null", shell_quote(attr)));
+    let pname = drv_pname(available.lines().next()?.trim());
+    let installed = exec_command("nix-env -q 2>/dev/null");
+    installed
+        .lines()
+        .map(str::trim)
+        .find(|name| drv_pname(name) == pname)
+        .map(str::to_string)
+}
+
+/// Parse `nix-env -q --json`, an object of installed derivations keyed by
+/// name. Packages are named by `pname`, which for top-level packages is
+/// also their attribute.
+fn parse_installed_json(output: &str) -> Result<Vec<Package>, SearchError> {
+    let root: Value =
+        serde_json::from_str(output).map_err(|e| SearchError::ParseFailure(e.to_string()))?;
+    let Value::Object(entries) = root else {
+        return Err(SearchError::ParseFailure("expected an object of derivations".into()));
+    };
+    let mut packages: Vec<Package> = entries
+        .values()
+        .filter_map(|entry| {
+            let field = |key: &str| entry.get(key).and_then(Value::as_str);
+            let name = field("pname").or_else(|| field("name").map(drv_pname))?;
+            Some(Package {
+                name: name.to_string(),
+                version: field("version").unwrap_or("").to_string(),
+                source: "nixpkgs".to_string(),
+                installed: true,
+                ..Default::default()
+            })
+        })
+        .collect();
+    packages.sort_by(|a, b| a.name.cmp(&b.name));
+    Ok(packages)
+}
+
+/// Parse a line of `nix-env -qaP --description` output.
+fn parse_line(line: &str) -> Option<Package> {
+    // Format: "nixpkgs.name    name-version    Description"
+    // Columns are space-padded (no tabs), so we can't use splitn on whitespace chars.
+    // Instead, find the boundary of each field manually.
+    let ws1 = line.find(|c: char| c.is_ascii_whitespace())?;
+    let attr = &line[..ws1];
+    if attr.is_empty() { return None; }
+
+    let rest = line[ws1..].trim_start();
+    if rest.is_empty() { return None; }
+
+    let (name_version, description) = match rest.find(|c: char| c.is_ascii_whitespace()) {
+        Some(ws2) => (&rest[..ws2], rest[ws2..].trim_start()),
+        None => (rest, ""),
+    };
+
+    let name = extract_pkg_name(attr);
+    if name.is_empty() || name_version.is_empty() {
+        return None;
+    }
+    let version = extract_version(name_version);
+
+    Some(Package {
+        name,
+        version,
+        description: description.to_string(),
+        source: "nixpkgs".to_string(),
+        ..Default::default()
+    })
+}
+
+/// Outputs a derivation can have, as in the "coreutils.out" attributes
+/// printed by nix-locate.
+const OUTPUTS: &[&str] = &["out", "bin", "dev", "lib", "man", "doc", "info"];
+
+/// Parse a line of `nix-locate` output: "attr.output size type path".
+fn parse_locate_line(line: &str) -> Option<(Package, String)> {
+    let fields: Vec<&str> = line.split_whitespace().collect();
+    let [attr, _size, _kind, path] = fields[..] else { return None };
+    let name = match attr.rsplit_once('.') {
+        Some((name, output)) if OUTPUTS.contains(&output) => name,
+        _ => attr,
+    };
+    // "/nix/store/<hash>-name-version/bin/ls"
+    let store_name = path.strip_prefix("/nix/store/")?.split('/').next()?;
+    let pkg = Package {
+        name: name.to_string(),
+        version: extract_version(store_name),
+        source: "nixpkgs".to_string(),
+        ..Default::default()
+    };
+    Some((pkg, path.to_string()))
+}
+
 impl Provider for NixProvider {
     fn name(&self) -> &str {
         "nix"
@@ -39,46 +148,14 @@ impl Provider for NixProvider {
 
         let escaped = escape_query(query);
         // nix-env -qaP --description '.*query.*'
-        let output = exec_command(&format!(
-            "nix-env -qaP --description '.*{escaped}.*' 2>/dev/null"
-        ));
-
-        let mut packages = Vec::new();
-
-        for line in output.lines() {
-            if line.is_empty() {
-                continue;
-            }
-
-            // Format: "nixpkgs.name    name-version    Description"
-            // Columns are space-padded (no tabs), so we can't use splitn on whitespace chars.
-            // Instead, find the boundary of each field manually.
-            let Some(ws1) = line.find(|c: char| c.is_ascii_whitespace()) else { continue };
-            let attr = &line[..ws1];
-            if attr.is_empty() { continue; }
-
-            let rest = line[ws1..].trim_start();
-            if rest.is_empty() { continue; }
-
-            let (name_version, description) = match rest.find(|c: char| c.is_ascii_whitespace()) {
-                Some(ws2) => (&rest[..ws2], rest[ws2..].trim_start()),
-                None => (rest, ""),
-            };
-
-            let name = extract_pkg_name(attr);
-            if name.is_empty() || name_version.is_empty() {
-                continue;
-            }
-            let version = extract_version(name_version);
-
-            packages.push(Package {
-                name,
-                version,
-                description: description.to_string(),
-                source: "nixpkgs".to_string(),
-                installed: false,
-            });
-        }
+        let mut packages = match exec_search_lines(
+            &format!("nix-env -qaP --description '.*{escaped}.*'"),
+            &["matches no derivations"],
+            parse_line,
+        ) {
+            Ok(packages) => packages,
+            Err(e) => return SearchResult { packages: vec![], error: Some(e) },
+        };
 
         sort_by_relevance(&mut packages, query);
         SearchResult { packages, error: None }
@@ -88,6 +165,36 @@ impl Provider for NixProvider {
         format!("nix-env -iA nixpkgs.{}", pkg.name)
     }
 
+    fn remove_command(&self, pkg: &Package) -> Option<String> {
+        let name = installed_drv_name(&format!("nixpkgs.{}", pkg.name))?;
+        Some(format!("nix-env --uninstall {}", shell_quote(&name)))
+    }
+
+    /// The packages of the user's nix-env profile.
+    fn list_installed(&self) -> Option<Result<Vec<Package>, SearchError>> {
+        Some(exec_search("nix-env -q --json", &[]).and_then(|output| parse_installed_json(&output)))
+    }
+
+    /// Uses the nix-index database, built with `nix-index`. Paths are matched
+    /// inside the store paths, which have no `/usr`.
+    fn search_file(&self, path: &str) -> Option<Result<Vec<Package>, SearchError>> {
+        let pattern = if path.contains('/') {
+            path.strip_prefix("/usr").unwrap_or(path).to_string()
+        } else {
+            format!("/bin/{path}")
+        };
+        let cmd =
+            format!("nix-locate --top-level --whole-name --at-root {}", shell_quote(&pattern));
+        let result = exec_search(&cmd, &[])
+            .map(|output| group_file_matches(output.lines().filter_map(parse_locate_line)));
+        Some(result)
+    }
+
+    /// Evaluating all of nixpkgs is slow, especially on a cold cache.
+    fn search_timeout(&self) -> Duration {
+        Duration::from_secs(120)
+    }
+
     fn source_color(&self, source: &str) -> Color {
         match source {
             "nixpkgs" => Color::Blue,
@@ -96,3 +203,31 @@ impl Provider for NixProvider {
         }
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn parses_installed_profile() {
+        let output = r#"{
+            "ripgrep-14.1.1": {"name": "ripgrep-14.1.1", "pname": "ripgrep",
+                               "version": "14.1.1", "outputName": "out"},
+            "hello-2.12.1": {"name": "hello-2.12.1", "outputName": "out"}
+        }"#;
+        let packages = parse_installed_json(output).unwrap();
+        assert_eq!(packages.len(), 2);
+        assert_eq!(packages[0].name, "hello");
+        assert_eq!(packages[1].name, "ripgrep");
+        assert_eq!(packages[1].version, "14.1.1");
+        assert!(parse_installed_json("[]").is_err());
+    }
+
+    #[test]
+    fn splits_derivation_names_like_nix() {
+        assert_eq!(drv_pname("python3.12-requests-2.32.3"), "python3.12-requests");
+        assert_eq!(drv_pname("hello-2.12.1"), "hello");
+        assert_eq!(drv_pname("nix-output-monitor-2.1.2"), "nix-output-monitor");
+        assert_eq!(drv_pname("unversioned"), "unversioned");
+    }
+}
@@ -1,15 +1,19 @@
-use crate::provider::{Package, Provider, SearchResult};
-use crate::util::{command_exists, escape_query, exec_command, sort_by_relevance};
+use std::collections::HashSet;
+use std::sync::Arc;
+
+use crate::error::SearchError;
+use crate::provider::{InstallPlan, Package, PlanAction, PlanEntry, Provider, SearchResult};
+use crate::util::{
+    cached_installed, command_exists, escape_query, exec_command, exec_search,
+    group_file_matches, parse_file_list, shell_quote, sort_by_relevance,
+};
 
 pub struct PacmanProvider;
 
 fn parse_si_output(output: &str) -> Option<Package> {
     let mut pkg = Package {
         name: String::new(),
-        version: String::new(),
-        description: String::new(),
-        source: String::new(),
-        installed: false,
+        ..Default::default()
     };
     for line in output.lines() {
         if let Some(val) = field_value(line, "Repository") {
@@ -26,10 +30,10 @@ fn parse_si_output(output: &str) -> Option<Package> {
 }
 
 fn field_value(line: &str, key: &str) -> Option<String> {
-    if line.starts_with(key) {
-        if let Some(colon) = line.find(':') {
-            return Some(line[colon + 1..].trim().to_string());
-        }
+    if line.starts_with(key)
+        && let Some(colon) = line.find(':')
+    {
+        return Some(line[colon + 1..].trim().to_string());
     }
     None
 }
@@ -59,7 +63,13 @@ fn parse_ss_output(output: &str) -> Vec<Package> {
                 .unwrap_or("")
                 .to_string();
             let installed = line.contains("[installed]") || line.contains("[Installed]");
-            current = Some(Package { name, version, description: String::new(), source, installed });
+            current = Some(Package {
+                name,
+                version,
+                source,
+                installed,
+                ..Default::default()
+            });
         } else if let Some(ref mut pkg) = current {
             let desc = line.trim_start();
             if !desc.is_empty() {
@@ -73,6 +83,160 @@ fn parse_ss_output(output: &str) -> Vec<Package> {
     packages
 }
 
+/// Parse `pacman -Sp --print-format '%n %v %s'`: the packages that would be
+/// downloaded, with their download sizes.
+fn parse_print_format(output: &str, installed: &HashSet<&str>) -> InstallPlan {
+    let mut plan = InstallPlan::default();
+    let mut download = 0;
+    for line in output.lines() {
+        let fields: Vec<&str> = line.split_whitespace().collect();
+        let [name, version, size] = fields[..] else { continue };
+        let Ok(size) = size.parse::<u64>() else { continue };
+        download += size;
+        let action =
+            if installed.contains(name) { PlanAction::Upgrade } else { PlanAction::Install };
+        plan.entries.push(PlanEntry {
+            action,
+            name: name.to_string(),
+            version: version.to_string(),
+        });
+    }
+    plan.download_size = Some(download);
+    plan
+}
+
+/// Parse `pacman -Qe`, "name version" per line. Packages in `foreign` (not
+/// from a sync database) get the source "aur", the rest "local".
+fn parse_explicit(output: &str, foreign: &HashSet<&str>) -> Vec<Package> {
+    output
+        .lines()
+        .filter_map(|line| {
+            let (name, version) = line.split_once(' ')?;
+            let source = if foreign.contains(name) { "aur" } else { "local" };
+            Some(Package {
+                name: name.to_string(),
+                version: version.trim().to_string(),
+                source: source.to_string(),
+                installed: true,
+                ..Default::default()
+            })
+        })
+        .collect()
+}
+
+/// Parse `pacman -F --machinereadable`: "repo\0name\0version\0path" per
+/// line, paths relative to the root. For a command only executables are kept.
+fn parse_file_matches(output: &str, command: bool, installed: &HashSet<String>) -> Vec<Package> {
+    let matches = output.lines().filter_map(|line| {
+        let mut fields = line.split('\0');
+        let (repo, name, version) = (fields.next()?, fields.next()?, fields.next()?);
+        let path = fields.next()?;
+        if command && !path.contains("bin/") {
+            return None;
+        }
+        let pkg = Package {
+            name: name.to_string(),
+            version: version.to_string(),
+            source: repo.to_string(),
+            installed: installed.contains(name),
+            ..Default::default()
+        };
+        Some((pkg, format!("/{path}")))
+    });
+    group_file_matches(matches)
+}
+
+/// The package names in field `key` of `pacman -Si`/`-Qi` output, without
+/// version constraints. Long fields continue on indented lines.
+fn field_names(output: &str, key: &str) -> Vec<String> {
+    let mut names: Vec<String> = Vec::new();
+    let mut in_field = false;
+    for line in output.lines() {
+        let value = match field_value(line, key) {
+            Some(value) => {
+                in_field = true;
+                value
+            }
+            None if in_field && line.starts_with(' ') => line.to_string(),
+            None => {
+                in_field = false;
+                continue;
+            }
+        };
+        for word in value.split_whitespace() {
+            let name = word.split(['<', '>', '=']).next().unwrap_or(word);
+            if name != "None" && !names.iter().any(|n| n == name) {
+                names.push(name.to_string());
+            }
+        }
+    }
+    names
+}
+
+/// The AUR popularity from a search line such as
+/// `paru 2.0.4-1 [+1234 ~12.34]` or `yay 12.4.2-1 (+2300 21.43)`. Shared with
+/// the AUR helpers.
+pub(crate) fn aur_popularity(rest: &str) -> Option<f64> {
+    let mut tokens = rest.split_whitespace().skip_while(|t| !t.starts_with(['[', '(']));
+    tokens.next().filter(|votes| votes[1..].starts_with('+'))?;
+    tokens.next()?.trim_matches(['~', ']', ')']).parse().ok()
+}
+
+/// Every installed package, shared with the AUR helpers.
+pub(crate) fn get_installed() -> Arc<HashSet<String>> {
+    cached_installed("pacman", || {
+        let output = exec_command("pacman -Qq 2>/dev/null");
+        output.lines().filter(|l| !l.is_empty()).map(|l| l.to_string()).collect()
+    })
+}
+
+/// Explicitly installed packages, shared with the AUR helpers.
+pub(crate) fn list_explicit() -> Result<Vec<Package>, SearchError> {
+    let output = exec_search("pacman -Qe", &[])?;
+    let foreign = exec_command("pacman -Qqm");
+    Ok(parse_explicit(&output, &foreign.lines().collect()))
+}
+
+/// The packages named in field `key` of the info that `sync` (e.g.
+/// "pacman -Si") prints for `pkg`, or of `pacman -Qi` for packages in no
+/// sync database. Shared with the AUR helpers.
+pub(crate) fn info_packages(
+    sync: &str,
+    pkg: &Package,
+    key: &str,
+) -> Result<Vec<Package>, SearchError> {
+    let name = shell_quote(&pkg.name);
+    let mut output = exec_search(&format!("{sync} {name}"), &["was not found"])?;
+    if output.trim().is_empty() {
+        output = exec_search(&format!("pacman -Qi {name}"), &["was not found"])?;
+    }
+    let installed = get_installed();
+    let packages = field_names(&output, key)
+        .into_iter()
+        .map(|name| Package {
+            installed: installed.contains(&name),
+            name,
+            ..Default::default()
+        })
+        .collect();
+    Ok(packages)
+}
+
+/// The files of `pkg`, from the local database if it is installed and the
+/// sync databases' file lists otherwise. Shared with the AUR helpers.
+pub(crate) fn list_files(pkg: &Package) -> Result<Vec<String>, SearchError> {
+    let flag = if pkg.installed { "-Qlq" } else { "-Flq" };
+    let cmd = format!("pacman {flag} {}", shell_quote(&pkg.name));
+    exec_search(&cmd, &["was not found"]).map(|output| parse_file_list(output.lines()))
+}
+
+/// Packages of the sync databases containing `path`, shared with the AUR
+/// helpers. Needs the file databases, downloaded with `pacman -Fy`.
+pub(crate) fn search_file(path: &str) -> Result<Vec<Package>, SearchError> {
+    let output = exec_search(&format!("pacman -F --machinereadable {}", shell_quote(path)), &[])?;
+    Ok(parse_file_matches(&output, !path.contains('/'), &get_installed()))
+}
+
 impl Provider for PacmanProvider {
     fn name(&self) -> &str {
         "pacman"
@@ -90,20 +254,19 @@ impl Provider for PacmanProvider {
         let escaped = escape_query(query);
 
         // Try exact match via pacman -Si
-        let info_output = exec_command(&format!("pacman -Si '{escaped}' 2>/dev/null"));
-        let mut exact_match: Option<Package> = None;
-        if !info_output.is_empty() && !info_output.contains("error:") {
-            if let Some(mut pkg) = parse_si_output(&info_output) {
-                // Check installed
-                let q_out = exec_command(&format!("pacman -Q '{escaped}' 2>/dev/null"));
-                if !q_out.is_empty() && !q_out.contains("error:") {
-                    pkg.installed = true;
-                }
-                exact_match = Some(pkg);
-            }
-        }
+        let info_output = match exec_search(&format!("pacman -Si '{escaped}'"), &["not found"]) {
+            Ok(output) => output,
+            Err(e) => return SearchResult { packages: vec![], error: Some(e) },
+        };
+        let exact_match = parse_si_output(&info_output).map(|mut pkg| {
+            pkg.installed = get_installed().contains(&pkg.name);
+            pkg
+        });
 
-        let output = exec_command(&format!("pacman -Ss '{escaped}' 2>/dev/null"));
+        let output = match exec_search(&format!("pacman -Ss '{escaped}'"), &[]) {
+            Ok(output) => output,
+            Err(e) => return SearchResult { packages: vec![], error: Some(e) },
+        };
         if output.is_empty() && exact_match.is_none() {
             return SearchResult { packages: vec![], error: None };
         }
@@ -122,7 +285,107 @@ impl Provider for PacmanProvider {
         SearchResult { packages, error: None }
     }
 
+    fn list_all(&self) -> Option<Result<Vec<Package>, SearchError>> {
+        // Without a search term, -Ss lists every package in the sync databases
+        Some(exec_search("pacman -Ss", &[]).map(|output| parse_ss_output(&output)))
+    }
+
+    fn can_list_all(&self) -> bool {
+        true
+    }
+
+    fn list_installed(&self) -> Option<Result<Vec<Package>, SearchError>> {
+        Some(list_explicit())
+    }
+
+    fn plan_install(&self, pkgs: &[Package]) -> Option<Result<InstallPlan, SearchError>> {
+        let names: Vec<String> = pkgs.iter().map(|p| shell_quote(&p.name)).collect();
+        let cmd = format!("pacman -Sp --print-format '%n %v %s' {}", names.join(" "));
+        Some(exec_search(&cmd, &[]).map(|output| {
+            let local = exec_command("pacman -Qq");
+            parse_print_format(&output, &local.lines().collect())
+        }))
+    }
+
+    fn installed_names(&self) -> Option<Arc<HashSet<String>>> {
+        Some(get_installed())
+    }
+
+    fn search_file(&self, path: &str) -> Option<Result<Vec<Package>, SearchError>> {
+        Some(search_file(path))
+    }
+
+    fn dependencies(&self, pkg: &Package) -> Option<Result<Vec<Package>, SearchError>> {
+        Some(info_packages("pacman -Si", pkg, "Depends On"))
+    }
+
+    /// -Sii adds the packages requiring it to the sync database info.
+    fn reverse_dependencies(&self, pkg: &Package) -> Option<Result<Vec<Package>, SearchError>> {
+        Some(info_packages("pacman -Sii", pkg, "Required By"))
+    }
+
+    fn list_files(&self, pkg: &Package) -> Option<Result<Vec<String>, SearchError>> {
+        Some(list_files(pkg))
+    }
+
     fn install_command(&self, pkg: &Package) -> String {
         format!("sudo pacman -S {}", pkg.name)
     }
+
+    fn remove_command(&self, pkg: &Package) -> Option<String> {
+        Some(format!("sudo pacman -R {}", pkg.name))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn parses_print_format_plan() {
+        let output = ":: warning: something\nlibfoo 1.2-1 1048576\nfirefox 128.0-1 70000000\n";
+        let plan = parse_print_format(output, &HashSet::from(["firefox"]));
+        assert_eq!(plan.entries.len(), 2);
+        assert_eq!(plan.entries[0].action, PlanAction::Install);
+        assert_eq!(plan.entries[0].version, "1.2-1");
+        assert_eq!(plan.entries[1].action, PlanAction::Upgrade);
+        assert_eq!(plan.download_size, Some(71048576));
+    }
+
+    #[test]
+    fn parses_explicit_packages() {
+        let output = "firefox 128.0-1\nparu-bin 2.0.3-1\n";
+        let packages = parse_explicit(output, &HashSet::from(["paru-bin"]));
+        assert_eq!(packages.len(), 2);
+        assert_eq!(packages[0].version, "128.0-1");
+        assert_eq!(packages[0].source, "local");
+        assert_eq!(packages[1].source, "aur");
+    }
+
+    #[test]
+    fn parses_file_matches() {
+        let output = "core\x00coreutils\x009.5-1\x00usr/bin/ls\n\
+                      extra\x00bash-completion\x002.14-1\x00usr/share/bash-completion/ls\n\
+                      core\x00coreutils\x009.5-1\x00usr/bin/ls\n";
+        let installed = HashSet::from(["coreutils".to_string()]);
+        let packages = parse_file_matches(output, true, &installed);
+        assert_eq!(packages.len(), 1);
+        assert_eq!(packages[0].source, "core");
+        assert_eq!(packages[0].files, ["/usr/bin/ls"]);
+        assert!(packages[0].installed);
+        assert_eq!(parse_file_matches(output, false, &installed).len(), 2);
+    }
+
+    #[test]
+    fn parses_dependency_fields() {
+        let output = "Name            : firefox
+Depends On      : gtk3  libxt  nss>=3.90  libfoo.so=1-64
+                  dbus-glib
+Optional Deps   : hunspell: spell checking
+Required By     : None
+";
+        let deps = field_names(output, "Depends On");
+        assert_eq!(deps, ["gtk3", "libxt", "nss", "libfoo.so", "dbus-glib"]);
+        assert!(field_names(output, "Required By").is_empty());
+    }
 }
@@ -0,0 +1,156 @@
+use std::collections::HashSet;
+
+use crate::error::SearchError;
+use crate::provider::{Color, Package, Provider, SearchResult};
+use crate::util::{command_exists, exec_search, sort_by_relevance};
+
+use super::slackpkg::{get_installed, list_packages};
+
+pub struct SbopkgProvider;
+
+/// Parse the SlackBuilds.org SLACKBUILDS.TXT index that sbopkg syncs locally.
+/// Records are separated by blank lines:
+/// "SLACKBUILD NAME: foo", "SLACKBUILD LOCATION: ./system/foo",
+/// "SLACKBUILD VERSION: 1.0", "SLACKBUILD SHORT DESCRIPTION:  foo (a thing)"
+///
+/// The indexes of all synced repositories are read one after another, so a
+/// name listed by several of them is kept from the first only.
+fn parse_slackbuilds(output: &str) -> Vec<Package> {
+    let mut packages = Vec::new();
+    let mut seen = HashSet::new();
+
+    for record in output.split("\n\n") {
+        let mut pkg = Package {
+            name: String::new(),
+            source: "sbo".to_string(),
+            ..Default::default()
+        };
+        for line in record.lines() {
+            let Some(rest) = line.strip_prefix("SLACKBUILD ") else { continue };
+            let Some((key, val)) = rest.split_once(':') else { continue };
+            let val = val.trim();
+            match key {
+                "NAME" => pkg.name = val.to_string(),
+                "VERSION" => pkg.version = val.to_string(),
+                "SHORT DESCRIPTION" => {
+                    // Descriptions repeat the name: "foo (a thing)"
+                    pkg.description = val
+                        .strip_prefix(pkg.name.as_str())
+                        .map(|d| d.trim().trim_start_matches('(').trim_end_matches(')'))
+                        .unwrap_or(val)
+                        .to_string();
+                }
+                _ => {}
+            }
+        }
+        if !pkg.name.is_empty() && seen.insert(pkg.name.clone()) {
+            packages.push(pkg);
+        }
+    }
+
+    packages
+}
+
+impl Provider for SbopkgProvider {
+    fn name(&self) -> &str {
+        "sbopkg"
+    }
+
+    fn is_available(&self) -> bool {
+        command_exists("sbopkg")
+    }
+
+    fn search(&self, query: &str) -> SearchResult {
+        if query.is_empty() {
+            return SearchResult { packages: vec![], error: None };
+        }
+
+        let cmd = "cat /var/lib/sbopkg/*/*/SLACKBUILDS.TXT";
+        let output = match exec_search(cmd, &["No such file"]) {
+            Ok(output) => output,
+            Err(e) => return SearchResult { packages: vec![], error: Some(e) },
+        };
+        if output.is_empty() {
+            return SearchResult {
+                packages: vec![],
+                error: Some(SearchError::Other(
+                    "SlackBuilds index not found. Run 'sudo sbopkg -r' first.".to_string(),
+                )),
+            };
+        }
+
+        let query_lower = query.to_lowercase();
+        let installed = get_installed();
+        let mut packages: Vec<Package> = parse_slackbuilds(&output)
+            .into_iter()
+            .filter(|p| {
+                p.name.to_lowercase().contains(&query_lower)
+                    || p.description.to_lowercase().contains(&query_lower)
+            })
+            .collect();
+        for pkg in &mut packages {
+            pkg.installed = installed.contains(&pkg.name);
+        }
+
+        sort_by_relevance(&mut packages, query);
+        SearchResult { packages, error: None }
+    }
+
+    /// Installed packages built from SlackBuilds ("_SBo" build tags).
+    fn list_installed(&self) -> Option<Result<Vec<Package>, SearchError>> {
+        let result = list_packages().map(|packages| {
+            packages.into_iter().filter(|pkg| pkg.source == "sbo").collect()
+        });
+        Some(result)
+    }
+
+    fn install_command(&self, pkg: &Package) -> String {
+        format!("sudo sbopkg -i {}", pkg.name)
+    }
+
+    fn remove_command(&self, pkg: &Package) -> Option<String> {
+        Some(format!("sudo removepkg {}", pkg.name))
+    }
+
+    fn source_color(&self, _source: &str) -> Color {
+        Color::Yellow
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const SLACKBUILDS_TXT: &str = "\
+SLACKBUILD NAME: ffmpegthumbnailer
+SLACKBUILD LOCATION: ./multimedia/ffmpegthumbnailer
+SLACKBUILD FILES: README ffmpegthumbnailer.SlackBuild slack-desc
+SLACKBUILD VERSION: 2.2.2
+SLACKBUILD DOWNLOAD: https://example.org/ffmpegthumbnailer-2.2.2.tar.bz2
+SLACKBUILD MD5SUM: 0123456789abcdef
+SLACKBUILD SHORT DESCRIPTION:  ffmpegthumbnailer (lightweight video thumbnailer)
+
+SLACKBUILD NAME: neovim
+SLACKBUILD LOCATION: ./development/neovim
+SLACKBUILD VERSION: 0.10.0
+SLACKBUILD SHORT DESCRIPTION:  neovim (Vim-fork focused on extensibility)
+";
+
+    #[test]
+    fn parses_slackbuild_records() {
+        let packages = parse_slackbuilds(SLACKBUILDS_TXT);
+        assert_eq!(packages.len(), 2);
+
+        assert_eq!(packages[0].name, "ffmpegthumbnailer");
+        assert_eq!(packages[0].version, "2.2.2");
+        assert_eq!(packages[0].description, "lightweight video thumbnailer");
+        assert_eq!(packages[0].source, "sbo");
+
+        assert_eq!(packages[1].name, "neovim");
+        assert_eq!(packages[1].description, "Vim-fork focused on extensibility");
+
+        // Another repository listing the same SlackBuild
+        let both = format!("{SLACKBUILDS_TXT}\n{SLACKBUILDS_TXT}");
+        assert_eq!(parse_slackbuilds(&both).len(), 2);
+    }
+}
@@ -0,0 +1,195 @@
+use std::collections::HashSet;
+use std::sync::Arc;
+
+use crate::error::SearchError;
+use crate::provider::{Color, Package, Provider, SearchResult};
+use crate::util::{
+    cached_installed, command_exists, escape_query, exec_command, exec_search, sort_by_relevance,
+};
+
+pub struct SlackpkgProvider;
+
+/// Installed package names, read from the pkgtools database.
+pub(crate) fn get_installed() -> Arc<HashSet<String>> {
+    cached_installed("slackpkg", || {
+        let output = exec_command("ls /var/log/packages 2>/dev/null");
+        output
+            .lines()
+            .filter_map(split_package)
+            .map(|(name, _, _)| name.to_string())
+            .collect()
+    })
+}
+
+/// Every package in the pkgtools database, whichever tool installed it.
+/// Slackware doesn't tell packages asked for from dependencies.
+pub(crate) fn list_packages() -> Result<Vec<Package>, SearchError> {
+    exec_search("ls /var/log/packages", &[]).map(|output| {
+        output
+            .lines()
+            .filter_map(split_package)
+            .map(|(name, version, build)| Package {
+                name: name.to_string(),
+                version: version.to_string(),
+                source: source_from_build(build).to_string(),
+                installed: true,
+                ..Default::default()
+            })
+            .collect()
+    })
+}
+
+/// Split "name-version-arch-build" into (name, version, build).
+pub(crate) fn split_package(s: &str) -> Option<(&str, &str, &str)> {
+    let mut parts = s.rsplitn(4, '-');
+    let build = parts.next()?;
+    let _arch = parts.next()?;
+    let version = parts.next()?;
+    let name = parts.next()?;
+    Some((name, version, build))
+}
+
+/// Map a build tag such as "1_slack15.0" or "1_SBo" to a source label.
+fn source_from_build(build: &str) -> &'static str {
+    if build.ends_with("_SBo") {
+        "sbo"
+    } else if build.ends_with("alien") {
+        "alien"
+    } else {
+        "slackware"
+    }
+}
+
+/// Parse `slackpkg search` lines:
+/// "[ installed ] - name-version-arch-build"
+/// "[uninstalled] - name-version-arch-build"
+/// "[ upgrade   ] - old-package --> new-package"
+fn parse_search_output(output: &str) -> Vec<Package> {
+    let mut packages = Vec::new();
+    for line in output.lines() {
+        let Some(rest) = line.strip_prefix('[') else { continue };
+        let Some((status, rest)) = rest.split_once(']') else { continue };
+        let status = status.trim();
+        let Some(rest) = rest.trim_start().strip_prefix("- ") else { continue };
+        // For upgrades show the version that would be installed
+        let full = match rest.split_once(" --> ") {
+            Some((_, new)) => new.trim(),
+            None => rest.trim(),
+        };
+        let Some((name, version, build)) = split_package(full) else { continue };
+        packages.push(Package {
+            name: name.to_string(),
+            version: version.to_string(),
+            source: source_from_build(build).to_string(),
+            installed: status != "uninstalled",
+            ..Default::default()
+        });
+    }
+    packages
+}
+
+impl Provider for SlackpkgProvider {
+    fn name(&self) -> &str {
+        "slackpkg"
+    }
+
+    fn is_available(&self) -> bool {
+        command_exists("slackpkg")
+    }
+
+    fn search(&self, query: &str) -> SearchResult {
+        if query.is_empty() {
+            return SearchResult { packages: vec![], error: None };
+        }
+
+        let escaped = escape_query(query);
+        let output = match exec_search(
+            &format!("slackpkg search '{escaped}'"),
+            &["No package name matches the pattern"],
+        ) {
+            Ok(output) => output,
+            Err(e) => return SearchResult { packages: vec![], error: Some(e) },
+        };
+        if output.is_empty() {
+            return SearchResult { packages: vec![], error: None };
+        }
+
+        let mut packages = parse_search_output(&output);
+
+        sort_by_relevance(&mut packages, query);
+        SearchResult { packages, error: None }
+    }
+
+    fn installed_names(&self) -> Option<Arc<HashSet<String>>> {
+        Some(get_installed())
+    }
+
+    /// Packages from slackware and alien; SlackBuilds belong to sbopkg.
+    fn list_installed(&self) -> Option<Result<Vec<Package>, SearchError>> {
+        let result = list_packages().map(|packages| {
+            packages.into_iter().filter(|pkg| pkg.source != "sbo").collect()
+        });
+        Some(result)
+    }
+
+    fn install_command(&self, pkg: &Package) -> String {
+        format!("sudo slackpkg install {}", pkg.name)
+    }
+
+    fn remove_command(&self, pkg: &Package) -> Option<String> {
+        Some(format!("sudo slackpkg remove {}", pkg.name))
+    }
+
+    fn source_color(&self, source: &str) -> Color {
+        match source {
+            "slackware" => Color::Blue,
+            "sbo" => Color::Yellow,
+            "alien" => Color::Magenta,
+            _ => Color::Cyan,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const SEARCH_OUTPUT: &str = "\
+Looking for firefox in package list. Please wait... DONE
+
+The list below shows all packages with name matching \"firefox\".
+
+[uninstalled] - mozilla-firefox-115.9.1esr-x86_64-1_slack15.0
+[ installed ] - firefox-pgo-bin-128.0-x86_64-1alien
+[ upgrade   ] - glibc-2.33-x86_64-5_slack15.0 --> glibc-2.33-x86_64-6_slack15.0
+
+You can search specific files using \"slackpkg file-search file\".
+";
+
+    #[test]
+    fn parses_search_statuses() {
+        let packages = parse_search_output(SEARCH_OUTPUT);
+        assert_eq!(packages.len(), 3);
+
+        assert_eq!(packages[0].name, "mozilla-firefox");
+        assert_eq!(packages[0].version, "115.9.1esr");
+        assert_eq!(packages[0].source, "slackware");
+        assert!(!packages[0].installed);
+
+        assert_eq!(packages[1].name, "firefox-pgo-bin");
+        assert_eq!(packages[1].source, "alien");
+        assert!(packages[1].installed);
+
+        assert_eq!(packages[2].name, "glibc");
+        assert!(packages[2].installed);
+    }
+
+    #[test]
+    fn splits_package_names_with_hyphens() {
+        assert_eq!(
+            split_package("xf86-video-intel-20230223_ae93d5e-x86_64-1_SBo"),
+            Some(("xf86-video-intel", "20230223_ae93d5e", "1_SBo"))
+        );
+        assert_eq!(split_package("bash"), None);
+    }
+}
@@ -1,23 +1,53 @@
 use std::collections::HashSet;
+use std::sync::Arc;
 
-use ratatui::style::Color;
-
-use crate::provider::{Package, Provider, SearchResult};
-use crate::util::{command_exists, escape_query, exec_command, sort_by_relevance};
+use crate::error::SearchError;
+use crate::provider::{Color, Package, Provider, SearchResult};
+use crate::util::{
+    cached_installed, command_exists, escape_query, exec_command, exec_search, sort_by_relevance,
+};
 
 pub struct SnapProvider;
 
-fn get_installed() -> HashSet<String> {
-    let output = exec_command("snap list 2>/dev/null");
-    let mut installed = HashSet::new();
-    let mut lines = output.lines();
-    lines.next(); // skip header
-    for line in lines {
-        if let Some(name) = line.split_whitespace().next() {
-            installed.insert(name.to_string());
+fn get_installed() -> Arc<HashSet<String>> {
+    cached_installed("snap", || {
+        let output = exec_command("snap list 2>/dev/null");
+        let mut installed = HashSet::new();
+        let mut lines = output.lines();
+        lines.next(); // skip header
+        for line in lines {
+            if let Some(name) = line.split_whitespace().next() {
+                installed.insert(name.to_string());
+            }
         }
-    }
-    installed
+        installed
+    })
+}
+
+/// Parse `snap list`: "Name Version Rev Tracking Publisher Notes" rows.
+/// Bases and snapd itself are installed along with the snaps that need
+/// them, so they are left out.
+fn parse_snap_list(output: &str) -> Vec<Package> {
+    output
+        .lines()
+        .skip(1) // header
+        .filter_map(|line| {
+            let fields: Vec<&str> = line.split_whitespace().collect();
+            let [name, version, _rev, _tracking, _publisher, notes, ..] = fields[..] else {
+                return None;
+            };
+            if notes.split(',').any(|note| matches!(note, "base" | "core" | "snapd")) {
+                return None;
+            }
+            Some(Package {
+                name: name.to_string(),
+                version: version.to_string(),
+                source: "snap".to_string(),
+                installed: true,
+                ..Default::default()
+            })
+        })
+        .collect()
 }
 
 impl Provider for SnapProvider {
@@ -35,7 +65,10 @@ impl Provider for SnapProvider {
         }
 
         let escaped = escape_query(query);
-        let output = exec_command(&format!("snap find '{escaped}' 2>/dev/null"));
+        let output = match exec_search(&format!("snap find '{escaped}'"), &["No matching snaps"]) {
+            Ok(output) => output,
+            Err(e) => return SearchResult { packages: vec![], error: Some(e) },
+        };
         if output.is_empty() {
             return SearchResult { packages: vec![], error: None };
         }
@@ -76,6 +109,7 @@ impl Provider for SnapProvider {
                 description,
                 source: "snap".to_string(),
                 installed: is_installed,
+                ..Default::default()
             });
         }
 
@@ -83,11 +117,43 @@ impl Provider for SnapProvider {
         SearchResult { packages, error: None }
     }
 
+    fn installed_names(&self) -> Option<Arc<HashSet<String>>> {
+        Some(get_installed())
+    }
+
+    fn list_installed(&self) -> Option<Result<Vec<Package>, SearchError>> {
+        Some(exec_search("snap list", &["No snaps are installed"]).map(|out| parse_snap_list(&out)))
+    }
+
     fn install_command(&self, pkg: &Package) -> String {
         format!("sudo snap install {}", pkg.name)
     }
 
+    fn remove_command(&self, pkg: &Package) -> Option<String> {
+        Some(format!("sudo snap remove {}", pkg.name))
+    }
+
     fn source_color(&self, _source: &str) -> Color {
         Color::Yellow
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn lists_snaps_without_bases() {
+        let output = "\
+Name      Version   Rev    Tracking       Publisher     Notes
+core22    20240408  1380   latest/stable  canonical✓    base
+firefox   127.0-2   4451   latest/stable  mozilla✓      -
+snapd     2.63      21759  latest/stable  canonical✓    snapd
+code      ea3b9c5   161    latest/stable  vscode✓       classic
+";
+        let packages = parse_snap_list(output);
+        let names: Vec<&str> = packages.iter().map(|p| p.name.as_str()).collect();
+        assert_eq!(names, ["firefox", "code"]);
+        assert_eq!(packages[0].version, "127.0-2");
+    }
+}
@@ -1,7 +1,9 @@
-use ratatui::style::Color;
-
-use crate::provider::{Package, Provider, SearchResult};
-use crate::util::{command_exists, escape_query, exec_command, sort_by_relevance};
+use crate::error::SearchError;
+use crate::provider::{Color, Package, Provider, SearchResult};
+use crate::util::{
+    command_exists, escape_query, exec_search, exec_search_lines, group_file_matches,
+    parse_file_list, shell_quote, sort_by_relevance,
+};
 
 pub struct XbpsProvider;
 
@@ -13,6 +15,59 @@ fn split_name_version(s: &str) -> (&str, &str) {
     }
 }
 
+/// Parse a line of `xbps-query -Rs` output.
+fn parse_line(line: &str) -> Option<Package> {
+    if line.len() < 5 {
+        return None;
+    }
+
+    let installed = if line.starts_with("[*]") {
+        true
+    } else if line.starts_with("[-]") {
+        false
+    } else {
+        return None;
+    };
+
+    // Skip "[*] " or "[-] " prefix (4 chars)
+    let rest = &line[4..];
+
+    // Find description (after two consecutive spaces)
+    let (name_version, description) = match rest.find("  ") {
+        Some(sep) => {
+            let nv = rest[..sep].trim_end();
+            let desc = rest[sep..].trim_start();
+            (nv, desc.to_string())
+        }
+        None => (rest.trim_end(), String::new()),
+    };
+
+    let (name, version) = split_name_version(name_version);
+    Some(Package {
+        name: name.to_string(),
+        version: version.to_string(),
+        description,
+        source: "void".to_string(),
+        installed,
+        ..Default::default()
+    })
+}
+
+/// Parse `xbps-query -Ro`: "pkgver: path (type)" per line, where the type
+/// is e.g. "regular file" or "symlink".
+fn parse_owned_line(line: &str) -> Option<(Package, String)> {
+    let (pkgver, rest) = line.split_once(": ")?;
+    let path = rest.rsplit_once(" (").map_or(rest, |(path, _kind)| path);
+    let (name, version) = split_name_version(pkgver);
+    let pkg = Package {
+        name: name.to_string(),
+        version: version.to_string(),
+        source: "void".to_string(),
+        ..Default::default()
+    };
+    Some((pkg, path.to_string()))
+}
+
 impl Provider for XbpsProvider {
     fn name(&self) -> &str {
         "xbps"
@@ -28,58 +83,78 @@ impl Provider for XbpsProvider {
         }
 
         let escaped = escape_query(query);
-        let output = exec_command(&format!("xbps-query -Rs '{escaped}' 2>/dev/null"));
-        if output.is_empty() {
-            return SearchResult { packages: vec![], error: None };
-        }
-
-        let mut packages = Vec::new();
-
-        for line in output.lines() {
-            if line.len() < 5 {
-                continue;
-            }
-
-            let installed = if line.starts_with("[*]") {
-                true
-            } else if line.starts_with("[-]") {
-                false
-            } else {
-                continue;
-            };
-
-            // Skip "[*] " or "[-] " prefix (4 chars)
-            let rest = &line[4..];
-
-            // Find description (after two consecutive spaces)
-            let (name_version, description) = match rest.find("  ") {
-                Some(sep) => {
-                    let nv = rest[..sep].trim_end();
-                    let desc = rest[sep..].trim_start();
-                    (nv, desc.to_string())
-                }
-                None => (rest.trim_end(), String::new()),
+        let mut packages =
+            match exec_search_lines(&format!("xbps-query -Rs '{escaped}'"), &[], parse_line) {
+                Ok(packages) => packages,
+                Err(e) => return SearchResult { packages: vec![], error: Some(e) },
             };
 
-            let (name, version) = split_name_version(name_version);
-            packages.push(Package {
-                name: name.to_string(),
-                version: version.to_string(),
-                description,
-                source: "void".to_string(),
-                installed,
-            });
-        }
-
         sort_by_relevance(&mut packages, query);
         SearchResult { packages, error: None }
     }
 
+    fn search_file(&self, path: &str) -> Option<Result<Vec<Package>, SearchError>> {
+        let pattern = if path.contains('/') { path.to_string() } else { format!("*/bin/{path}") };
+        let cmd = format!("xbps-query -Ro {}", shell_quote(&pattern));
+        let result = exec_search(&cmd, &[])
+            .map(|output| group_file_matches(output.lines().filter_map(parse_owned_line)));
+        Some(result)
+    }
+
+    fn list_files(&self, pkg: &Package) -> Option<Result<Vec<String>, SearchError>> {
+        let remote = if pkg.installed { "" } else { "-R " };
+        let cmd = format!("xbps-query {remote}-f {}", shell_quote(&pkg.name));
+        // Symlinks are printed as "path -> target"
+        let result = exec_search(&cmd, &[]).map(|output| {
+            parse_file_list(output.lines().map(|line| line.split(" -> ").next().unwrap_or(line)))
+        });
+        Some(result)
+    }
+
+    /// Packages installed manually rather than as dependencies.
+    fn list_installed(&self) -> Option<Result<Vec<Package>, SearchError>> {
+        let result = exec_search("xbps-query -m", &[]).map(|output| {
+            output
+                .lines()
+                .map(str::trim)
+                .filter(|pkgver| !pkgver.is_empty())
+                .map(|pkgver| {
+                    let (name, version) = split_name_version(pkgver);
+                    Package {
+                        name: name.to_string(),
+                        version: version.to_string(),
+                        source: "void".to_string(),
+                        installed: true,
+                        ..Default::default()
+                    }
+                })
+                .collect()
+        });
+        Some(result)
+    }
+
     fn install_command(&self, pkg: &Package) -> String {
         format!("sudo xbps-install {}", pkg.name)
     }
 
+    fn remove_command(&self, pkg: &Package) -> Option<String> {
+        Some(format!("sudo xbps-remove {}", pkg.name))
+    }
+
     fn source_color(&self, _source: &str) -> Color {
         Color::Green
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn parses_owned_files() {
+        let (pkg, path) = parse_owned_line("coreutils-9.5_1: /usr/bin/ls (regular file)").unwrap();
+        assert_eq!(pkg.name, "coreutils");
+        assert_eq!(pkg.version, "9.5_1");
+        assert_eq!(path, "/usr/bin/ls");
+    }
+}
@@ -1,13 +1,17 @@
+use std::collections::HashSet;
+use std::sync::Arc;
+
+use crate::error::SearchError;
 use crate::provider::{Package, Provider, SearchResult};
 use crate::util::{command_exists, escape_query, exec_command_full, sort_by_relevance};
 
 pub struct YayProvider;
 
 fn field_value(line: &str, key: &str) -> Option<String> {
-    if line.starts_with(key) {
-        if let Some(colon) = line.find(':') {
-            return Some(line[colon + 1..].trim().to_string());
-        }
+    if line.starts_with(key)
+        && let Some(colon) = line.find(':')
+    {
+        return Some(line[colon + 1..].trim().to_string());
     }
     None
 }
@@ -15,10 +19,7 @@ fn field_value(line: &str, key: &str) -> Option<String> {
 fn parse_si_output(output: &str) -> Option<Package> {
     let mut pkg = Package {
         name: String::new(),
-        version: String::new(),
-        description: String::new(),
-        source: String::new(),
-        installed: false,
+        ..Default::default()
     };
     for line in output.lines() {
         if let Some(val) = field_value(line, "Repository") {
@@ -59,7 +60,14 @@ fn parse_ss_output(output: &str) -> Vec<Package> {
                 .unwrap_or("")
                 .to_string();
             let installed = line.contains("[installed]") || line.contains("[Installed]");
-            current = Some(Package { name, version, description: String::new(), source, installed });
+            current = Some(Package {
+                name,
+                version,
+                source,
+                installed,
+                popularity: super::pacman::aur_popularity(rest),
+                ..Default::default()
+            });
         } else if let Some(ref mut pkg) = current {
             let desc = line.trim_start();
             if !desc.is_empty() {
@@ -98,8 +106,8 @@ impl Provider for YayProvider {
         }
 
         // Main search with --topdown to show repo packages first
-        let (stdout, stderr, _) =
-            exec_command_full(&format!("yay --topdown -Ss '{escaped}'"));
+        let cmd = format!("yay --topdown -Ss '{escaped}'");
+        let (stdout, stderr, code) = exec_command_full(&cmd);
 
         if stderr.contains("Query arg too small")
             || stderr.contains("Too many package results")
@@ -108,7 +116,15 @@ impl Provider for YayProvider {
         {
             return SearchResult {
                 packages: vec![],
-                error: Some("Too many results! Try a more specific search.".to_string()),
+                error: Some(SearchError::Other(
+                    "Too many results! Try a more specific search.".to_string(),
+                )),
+            };
+        }
+        if code > 0 && stdout.trim().is_empty() && !stderr.trim().is_empty() {
+            return SearchResult {
+                packages: vec![],
+                error: Some(SearchError::from_command(&cmd, code, &stderr)),
             };
         }
 
@@ -125,7 +141,36 @@ impl Provider for YayProvider {
         SearchResult { packages, error: None }
     }
 
+    fn list_installed(&self) -> Option<Result<Vec<Package>, SearchError>> {
+        Some(super::pacman::list_explicit())
+    }
+
+    fn installed_names(&self) -> Option<Arc<HashSet<String>>> {
+        Some(super::pacman::get_installed())
+    }
+
+    fn search_file(&self, path: &str) -> Option<Result<Vec<Package>, SearchError>> {
+        // AUR packages have no file lists to search
+        Some(super::pacman::search_file(path))
+    }
+
+    fn dependencies(&self, pkg: &Package) -> Option<Result<Vec<Package>, SearchError>> {
+        Some(super::pacman::info_packages("yay -Si", pkg, "Depends On"))
+    }
+
+    fn reverse_dependencies(&self, pkg: &Package) -> Option<Result<Vec<Package>, SearchError>> {
+        Some(super::pacman::info_packages("pacman -Sii", pkg, "Required By"))
+    }
+
+    fn list_files(&self, pkg: &Package) -> Option<Result<Vec<String>, SearchError>> {
+        Some(super::pacman::list_files(pkg))
+    }
+
     fn install_command(&self, pkg: &Package) -> String {
         format!("yay -S {}", pkg.name)
     }
+
+    fn remove_command(&self, pkg: &Package) -> Option<String> {
+        Some(format!("yay -R {}", pkg.name))
+    }
 }
@@ -1,25 +1,29 @@
 use std::collections::HashSet;
+use std::sync::Arc;
 
-use ratatui::style::Color;
-
-use crate::provider::{Package, Provider, SearchResult};
-use crate::util::{command_exists, escape_query, exec_command, sort_by_relevance};
+use crate::error::SearchError;
+use crate::provider::{Color, Package, Provider, SearchResult};
+use crate::util::{
+    cached_installed, command_exists, escape_query, exec_command, exec_search, sort_by_relevance,
+};
 
 pub struct ZerobrewProvider;
 
-fn get_installed() -> HashSet<String> {
-    let mut installed = HashSet::new();
-    for output in [
-        exec_command("brew list --formula 2>/dev/null"),
-        exec_command("brew list --cask 2>/dev/null"),
-    ] {
-        for line in output.lines() {
-            if !line.is_empty() {
-                installed.insert(line.to_string());
+fn get_installed() -> Arc<HashSet<String>> {
+    cached_installed("brew", || {
+        let mut installed = HashSet::new();
+        for output in [
+            exec_command("brew list --formula 2>/dev/null"),
+            exec_command("brew list --cask 2>/dev/null"),
+        ] {
+            for line in output.lines() {
+                if !line.is_empty() {
+                    installed.insert(line.to_string());
+                }
             }
         }
-    }
-    installed
+        installed
+    })
 }
 
 impl Provider for ZerobrewProvider {
@@ -41,8 +45,12 @@ impl Provider for ZerobrewProvider {
 
         // Try exact match via brew info
         let mut exact_match: Option<Package> = None;
-        let info_output = exec_command(&format!("brew info '{escaped}' 2>/dev/null"));
-        if !info_output.is_empty() && !info_output.contains("Error:") {
+        let no_formula = ["No available formula", "No formulae or casks found"];
+        let info_output = match exec_search(&format!("brew info '{escaped}'"), &no_formula) {
+            Ok(output) => output,
+            Err(e) => return SearchResult { packages: vec![], error: Some(e) },
+        };
+        if !info_output.is_empty() {
             let mut lines = info_output.lines();
             if let Some(first_line) = lines.next() {
                 let first_line = first_line.strip_prefix("==> ").unwrap_or(first_line);
@@ -62,16 +70,22 @@ impl Provider for ZerobrewProvider {
                     let is_installed = installed.contains(&name);
                     exact_match = Some(Package {
                         name,
-                        version: String::new(),
                         description,
                         source: "formula".to_string(),
                         installed: is_installed,
+                        ..Default::default()
                     });
                 }
             }
         }
 
-        let output = exec_command(&format!("brew search --desc '{escaped}' 2>/dev/null"));
+        let output = match exec_search(
+            &format!("brew search --desc '{escaped}'"),
+            &["No formulae or casks found"],
+        ) {
+            Ok(output) => output,
+            Err(e) => return SearchResult { packages: vec![], error: Some(e) },
+        };
 
         let mut packages = Vec::new();
 
@@ -110,10 +124,10 @@ impl Provider for ZerobrewProvider {
             let is_installed = installed.contains(&name);
             packages.push(Package {
                 name,
-                version: String::new(),
                 description,
                 source: current_source.to_string(),
                 installed: is_installed,
+                ..Default::default()
             });
         }
 
@@ -121,6 +135,15 @@ impl Provider for ZerobrewProvider {
         SearchResult { packages, error: None }
     }
 
+    fn installed_names(&self) -> Option<Arc<HashSet<String>>> {
+        Some(get_installed())
+    }
+
+    /// Installed through the same `brew` as the brew provider.
+    fn list_installed(&self) -> Option<Result<Vec<Package>, SearchError>> {
+        super::brew::BrewProvider.list_installed()
+    }
+
     fn install_command(&self, pkg: &Package) -> String {
         if pkg.source == "cask" {
             format!("brew install --cask {}", pkg.name)
@@ -129,6 +152,14 @@ impl Provider for ZerobrewProvider {
         }
     }
 
+    fn remove_command(&self, pkg: &Package) -> Option<String> {
+        if pkg.source == "cask" {
+            Some(format!("brew uninstall --cask {}", pkg.name))
+        } else {
+            Some(format!("brew uninstall {}", pkg.name))
+        }
+    }
+
     fn source_color(&self, source: &str) -> Color {
         match source {
             "cask" => Color::Magenta,
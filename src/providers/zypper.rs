@@ -1,19 +1,135 @@
-use ratatui::style::Color;
-
-use crate::provider::{Package, Provider, SearchResult};
-use crate::util::{command_exists, escape_query, exec_command, sort_by_relevance};
+use crate::error::SearchError;
+use crate::provider::{Color, InstallPlan, Package, PlanAction, PlanEntry, Provider, SearchResult};
+use crate::util::{
+    command_exists, escape_query, exec_search, parse_size, shell_quote,
+    sort_by_relevance,
+};
 
 pub struct ZypperProvider;
 
 fn field_value(line: &str, key: &str) -> Option<String> {
-    if line.starts_with(key) {
-        if let Some(colon) = line.find(':') {
-            return Some(line[colon + 1..].trim().to_string());
-        }
+    if line.starts_with(key)
+        && let Some(colon) = line.find(':')
+    {
+        return Some(line[colon + 1..].trim().to_string());
     }
     None
 }
 
+/// Parse `zypper install --dry-run`: "The following ... going to be
+/// installed:" sections listing names, and the size summary.
+fn parse_dry_run(output: &str) -> InstallPlan {
+    let mut plan = InstallPlan::default();
+    let mut action = None;
+    for line in output.lines() {
+        if line.starts_with("The following ") {
+            // Recommended packages are listed again among the new ones
+            action = if line.contains("going to be installed") {
+                Some(PlanAction::Install)
+            } else if line.contains("going to be upgraded") {
+                Some(PlanAction::Upgrade)
+            } else if line.contains("going to be REMOVED") {
+                Some(PlanAction::Remove)
+            } else {
+                None
+            };
+        } else if line.starts_with(' ')
+            && let Some(action) = action
+        {
+            plan.entries.extend(line.split_whitespace().map(|name| PlanEntry {
+                action,
+                name: name.to_string(),
+                version: String::new(),
+            }));
+        } else if line.starts_with("Overall download size:") {
+            // "Overall download size: 1.2 MiB. Already cached: 0 B. After the
+            // operation, additional 4.5 MiB will be used."
+            for part in line.split(". ") {
+                if let Some(size) = part.strip_prefix("Overall download size:") {
+                    plan.download_size = parse_size(size);
+                } else if let Some(rest) = part.strip_prefix("After the operation, ") {
+                    let rest = rest.trim_start_matches("additional ");
+                    let size = parse_size(rest.split(" will").next().unwrap_or(""));
+                    let size = size.map(|s| s as i64);
+                    plan.install_size =
+                        if rest.contains("freed") { size.map(|s| -s) } else { size };
+                }
+            }
+        } else {
+            action = None;
+        }
+    }
+    plan
+}
+
+/// Parse the table of `zypper search`: "S | Name | Summary | Type" rows.
+fn parse_search_table(output: &str) -> Vec<Package> {
+    let mut packages = Vec::new();
+
+    for line in output.lines() {
+        if line.is_empty() {
+            continue;
+        }
+        // Skip header and separator lines
+        if line.contains("Name") && line.contains("Summary") {
+            continue;
+        }
+        if line.starts_with("---") || line.contains("--+") {
+            continue;
+        }
+
+        // Parse pipe-delimited: "S | Name | Summary | Type"
+        let fields: Vec<&str> = line.split('|').map(|f| f.trim()).collect();
+        if fields.len() < 3 {
+            continue;
+        }
+
+        let name = fields[1].to_string();
+        if name.is_empty() {
+            continue;
+        }
+
+        let is_installed = fields[0] == "i" || fields[0] == "i+";
+        let description = fields[2].to_string();
+        let source = if fields.len() > 3 {
+            fields[3].to_string()
+        } else {
+            "zypper".to_string()
+        };
+
+        packages.push(Package {
+            name,
+            description,
+            source,
+            installed: is_installed,
+            ..Default::default()
+        });
+    }
+    packages
+}
+
+/// Parse `zypper search --installed-only --details`, keeping the packages
+/// the user installed ("i+") rather than those pulled in ("i"). Rows are
+/// "S | Name | Type | Version | Arch | Repository", one per installed version.
+fn parse_user_installed(output: &str) -> Vec<Package> {
+    let mut packages: Vec<Package> = Vec::new();
+    for line in output.lines() {
+        let fields: Vec<&str> = line.split('|').map(|f| f.trim()).collect();
+        let [status, name, _kind, version, _arch, repo, ..] = fields[..] else { continue };
+        if status != "i+" || packages.iter().any(|p| p.name == name) {
+            continue;
+        }
+        packages.push(Package {
+            name: name.to_string(),
+            version: version.to_string(),
+            source: repo.to_string(),
+            installed: true,
+            ..Default::default()
+        });
+    }
+    packages
+}
+
 impl Provider for ZypperProvider {
     fn name(&self) -> &str {
         "zypper"
@@ -31,16 +147,15 @@ impl Provider for ZypperProvider {
         let escaped = escape_query(query);
 
         // Try exact match via zypper info
-        let info_output =
-            exec_command(&format!("zypper --quiet info '{escaped}' 2>/dev/null"));
+        let info_output = match exec_search(&format!("zypper --quiet info '{escaped}'"), &[]) {
+            Ok(output) => output,
+            Err(e) => return SearchResult { packages: vec![], error: Some(e) },
+        };
         let mut exact_match: Option<Package> = None;
         if !info_output.is_empty() && !info_output.contains("not found") {
             let mut pkg = Package {
                 name: String::new(),
-                version: String::new(),
-                description: String::new(),
-                source: String::new(),
-                installed: false,
+                ..Default::default()
             };
             for line in info_output.lines() {
                 if let Some(val) = field_value(line, "Repository") {
@@ -60,53 +175,18 @@ impl Provider for ZypperProvider {
             }
         }
 
-        let output =
-            exec_command(&format!("zypper --quiet search '{escaped}' 2>/dev/null"));
+        let output = match exec_search(
+            &format!("zypper --quiet search '{escaped}'"),
+            &["No matching items found"],
+        ) {
+            Ok(output) => output,
+            Err(e) => return SearchResult { packages: vec![], error: Some(e) },
+        };
         if output.is_empty() && exact_match.is_none() {
             return SearchResult { packages: vec![], error: None };
         }
 
-        let mut packages = Vec::new();
-
-        for line in output.lines() {
-            if line.is_empty() {
-                continue;
-            }
-            // Skip header and separator lines
-            if line.contains("Name") && line.contains("Summary") {
-                continue;
-            }
-            if line.starts_with("---") || line.contains("--+") {
-                continue;
-            }
-
-            // Parse pipe-delimited: "S | Name | Summary | Type"
-            let fields: Vec<&str> = line.split('|').map(|f| f.trim()).collect();
-            if fields.len() < 3 {
-                continue;
-            }
-
-            let name = fields[1].to_string();
-            if name.is_empty() {
-                continue;
-            }
-
-            let is_installed = fields[0] == "i" || fields[0] == "i+";
-            let description = fields[2].to_string();
-            let source = if fields.len() > 3 {
-                fields[3].to_string()
-            } else {
-                "zypper".to_string()
-            };
-
-            packages.push(Package {
-                name,
-                version: String::new(),
-                description,
-                source,
-                installed: is_installed,
-            });
-        }
+        let mut packages = parse_search_table(&output);
 
         if let Some(em) = exact_match {
             let already = packages.iter().any(|p| p.name == em.name);
@@ -119,10 +199,47 @@ impl Provider for ZypperProvider {
         SearchResult { packages, error: None }
     }
 
+    /// zypper only does dry runs as root.
+    fn plan_install(&self, pkgs: &[Package]) -> Option<Result<InstallPlan, SearchError>> {
+        let names: Vec<String> = pkgs.iter().map(|p| shell_quote(&p.name)).collect();
+        let cmd = format!("LANG=C zypper -n install -D {}", names.join(" "));
+        let result = exec_search(&cmd, &[]).map_err(|e| match e {
+            SearchError::PermissionDenied => SearchError::Other(
+                "zypper needs root for dry runs; run fex with sudo to see one".to_string(),
+            ),
+            e => e,
+        });
+        Some(result.map(|output| parse_dry_run(&output)))
+    }
+
+    fn search_file(&self, path: &str) -> Option<Result<Vec<Package>, SearchError>> {
+        // zypper matches file provides by full path only
+        let path =
+            if path.contains('/') { path.to_string() } else { format!("/usr/bin/{path}") };
+        let cmd = format!("zypper --quiet search --provides --match-exact {}", shell_quote(&path));
+        let result = exec_search(&cmd, &["No matching items found"]).map(|output| {
+            let mut packages = parse_search_table(&output);
+            for pkg in &mut packages {
+                pkg.files = vec![path.clone()];
+            }
+            packages
+        });
+        Some(result)
+    }
+
+    fn list_installed(&self) -> Option<Result<Vec<Package>, SearchError>> {
+        let cmd = "zypper --quiet search --installed-only --type package --details";
+        Some(exec_search(cmd, &["No matching items found"]).map(|out| parse_user_installed(&out)))
+    }
+
     fn install_command(&self, pkg: &Package) -> String {
         format!("sudo zypper install {}", pkg.name)
     }
 
+    fn remove_command(&self, pkg: &Package) -> Option<String> {
+        Some(format!("sudo zypper remove {}", pkg.name))
+    }
+
     fn source_color(&self, source: &str) -> Color {
         match source {
             "repo-oss" => Color::Green,
@@ -133,3 +250,52 @@ impl Provider for ZypperProvider {
         }
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn parses_dry_run() {
+        let output = "Loading repository data...
+Resolving package dependencies...
+
+The following 2 recommended packages were automatically selected:
+  gvim vim-data
+
+The following 3 NEW packages are going to be installed:
+  gvim vim
+  vim-data
+
+The following package is going to be upgraded:
+  vim-small
+
+3 new packages to install.
+Overall download size: 2.0 MiB. Already cached: 0 B. After the operation, additional 8.0 MiB \
+will be used.
+";
+        let plan = parse_dry_run(output);
+        let names: Vec<&str> = plan.entries.iter().map(|e| e.name.as_str()).collect();
+        assert_eq!(names, ["gvim", "vim", "vim-data", "vim-small"]);
+        assert_eq!(plan.entries[3].action, PlanAction::Upgrade);
+        assert_eq!(plan.download_size, Some(2 * 1024 * 1024));
+        assert_eq!(plan.install_size, Some(8 * 1024 * 1024));
+    }
+
+    #[test]
+    fn parses_user_installed_packages() {
+        let output = "
+S  | Name        | Type    | Version     | Arch   | Repository
+---+-------------+---------+-------------+--------+-------------------
+i+ | git         | package | 2.45.2-1.1  | x86_64 | Main Repository (OSS)
+i  | git-core    | package | 2.45.2-1.1  | x86_64 | Main Repository (OSS)
+i+ | kernel-default | package | 6.9.7-1.1 | x86_64 | Main Repository (OSS)
+i+ | kernel-default | package | 6.9.5-1.1 | x86_64 | (System Packages)
+";
+        let packages = parse_user_installed(output);
+        let names: Vec<&str> = packages.iter().map(|p| p.name.as_str()).collect();
+        assert_eq!(names, ["git", "kernel-default"]);
+        assert_eq!(packages[0].version, "2.45.2-1.1");
+        assert_eq!(packages[0].source, "Main Repository (OSS)");
+    }
+}
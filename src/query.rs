@@ -0,0 +1,226 @@
+//! Search box syntax.
+//!
+//! Besides free text, a query may hold qualifiers that narrow the results
+//! client-side:
+//!
+//! - `source:aur` — only packages from that source
+//! - `installed:yes` / `installed:no`
+//! - `name:fire` — the name must contain the word
+//! - `desc:browser` — the description must contain the word
+//! - `/^lib.*-dev$/` — the name must match the regex
+//!
+//! Any qualifier can be negated with a leading `-`, e.g. `-source:cask`.
+//! The words of `name:` and `desc:` are also searched for, since the
+//! provider needs something to look up, unless they are negated; other
+//! qualifiers never reach it.
+//!
+//! An absolute path under one of the usual top-level directories, such as
+//! `/usr/bin/rg`, searches for the packages containing that file instead
+//! (see `Provider::search_file`).
+
+use std::fmt;
+
+use regex::Regex;
+
+use crate::provider::Package;
+
+#[derive(Clone, Debug)]
+pub enum FilterKind {
+    Source(String),
+    Installed(bool),
+    Name(String),
+    Description(String),
+    Regex(Regex),
+}
+
+#[derive(Clone, Debug)]
+pub struct Filter {
+    pub kind: FilterKind,
+    pub negated: bool,
+}
+
+#[derive(Clone, Debug, Default)]
+pub struct Query {
+    /// What is sent to `Provider::search`.
+    pub text: String,
+    pub filters: Vec<Filter>,
+    /// Qualifiers that could not be understood, e.g. a bad regex.
+    pub invalid: Vec<String>,
+    /// A path to look up with `Provider::search_file` instead of searching.
+    pub file: Option<String>,
+}
+
+/// Directories a path must start in to be taken for a file search rather
+/// than a regex.
+const PATH_ROOTS: &[&str] =
+    &["/usr/", "/bin/", "/sbin/", "/etc/", "/opt/", "/lib/", "/lib64/", "/lib32/", "/var/"];
+
+impl Filter {
+    pub fn matches(&self, pkg: &Package) -> bool {
+        let contains = |haystack: &str, needle: &str| {
+            haystack.to_lowercase().contains(&needle.to_lowercase())
+        };
+        let hit = match &self.kind {
+            FilterKind::Source(source) => pkg.source.eq_ignore_ascii_case(source),
+            FilterKind::Installed(installed) => pkg.installed == *installed,
+            FilterKind::Name(word) => contains(&pkg.name, word),
+            FilterKind::Description(word) => contains(&pkg.description, word),
+            FilterKind::Regex(re) => re.is_match(&pkg.name),
+        };
+        hit != self.negated
+    }
+}
+
+impl fmt::Display for Filter {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        if self.negated {
+            write!(f, "-")?;
+        }
+        match &self.kind {
+            FilterKind::Source(source) => write!(f, "source:{source}"),
+            FilterKind::Installed(true) => write!(f, "installed:yes"),
+            FilterKind::Installed(false) => write!(f, "installed:no"),
+            FilterKind::Name(word) => write!(f, "name:{word}"),
+            FilterKind::Description(word) => write!(f, "desc:{word}"),
+            FilterKind::Regex(re) => write!(f, "/{}/", re.as_str()),
+        }
+    }
+}
+
+impl Query {
+    pub fn parse(input: &str) -> Query {
+        let mut query = Query::default();
+        let mut words: Vec<&str> = Vec::new();
+
+        for token in input.split_whitespace() {
+            if PATH_ROOTS.iter().any(|root| token.starts_with(root)) {
+                // Still being typed while it ends in a slash
+                if !token.ends_with('/') {
+                    query.file = Some(token.to_string());
+                }
+                continue;
+            }
+            let (negated, body) = match token.strip_prefix('-') {
+                Some(rest) if is_qualifier(rest) => (true, rest),
+                _ => (false, token),
+            };
+            if !is_qualifier(body) {
+                words.push(token);
+                continue;
+            }
+
+            let kind = if let Some(pattern) = body.strip_prefix('/') {
+                // Still being typed until the closing slash
+                let Some(pattern) = pattern.strip_suffix('/') else { continue };
+                if pattern.is_empty() {
+                    continue;
+                }
+                match Regex::new(pattern) {
+                    Ok(re) => FilterKind::Regex(re),
+                    Err(_) => {
+                        query.invalid.push(token.to_string());
+                        continue;
+                    }
+                }
+            } else {
+                let (key, value) = body.split_once(':').unwrap_or((body, ""));
+                if value.is_empty() {
+                    continue;
+                }
+                match key {
+                    "source" => FilterKind::Source(value.to_string()),
+                    "installed" => match value.to_lowercase().as_str() {
+                        "yes" | "y" | "true" | "1" => FilterKind::Installed(true),
+                        "no" | "n" | "false" | "0" => FilterKind::Installed(false),
+                        _ => {
+                            query.invalid.push(token.to_string());
+                            continue;
+                        }
+                    },
+                    "name" => {
+                        if !negated {
+                            words.push(value);
+                        }
+                        FilterKind::Name(value.to_string())
+                    }
+                    _ => {
+                        if !negated {
+                            words.push(value);
+                        }
+                        FilterKind::Description(value.to_string())
+                    }
+                }
+            };
+            query.filters.push(Filter { kind, negated });
+        }
+
+        query.text = words.join(" ");
+        query
+    }
+
+    pub fn matches(&self, pkg: &Package) -> bool {
+        self.filters.iter().all(|f| f.matches(pkg))
+    }
+}
+
+fn is_qualifier(token: &str) -> bool {
+    if token.starts_with('/') {
+        return true;
+    }
+    matches!(token.split_once(':'), Some(("source" | "installed" | "name" | "desc", _)))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn pkg(name: &str, source: &str, installed: bool) -> Package {
+        Package {
+            name: name.to_string(),
+            description: "A web browser".to_string(),
+            source: source.to_string(),
+            installed,
+            ..Default::default()
+        }
+    }
+
+    #[test]
+    fn separates_free_text_from_qualifiers() {
+        let q = Query::parse("fire source:aur -installed:yes name:fox perl:Foo -dev");
+        assert_eq!(q.text, "fire fox perl:Foo -dev");
+        let shown: Vec<String> = q.filters.iter().map(ToString::to_string).collect();
+        assert_eq!(shown, ["source:aur", "-installed:yes", "name:fox"]);
+        assert!(q.invalid.is_empty());
+
+        let q = Query::parse("fire -name:fox -desc:nightly");
+        assert_eq!(q.text, "fire");
+        let shown: Vec<String> = q.filters.iter().map(ToString::to_string).collect();
+        assert_eq!(shown, ["-name:fox", "-desc:nightly"]);
+    }
+
+    #[test]
+    fn ignores_incomplete_and_reports_invalid_qualifiers() {
+        let q = Query::parse("vim source: /^lib installed:maybe /[/");
+        assert_eq!(q.text, "vim");
+        assert!(q.filters.is_empty());
+        assert_eq!(q.invalid, ["installed:maybe", "/[/"]);
+    }
+
+    #[test]
+    fn takes_paths_for_file_searches() {
+        let q = Query::parse("/usr/bin/rg installed:no");
+        assert_eq!(q.file.as_deref(), Some("/usr/bin/rg"));
+        assert_eq!(q.filters.len(), 1);
+        assert!(Query::parse("/usr/bin/").file.is_none());
+        assert!(Query::parse("/usr.*/").file.is_none());
+    }
+
+    #[test]
+    fn filters_packages() {
+        let q = Query::parse("-source:cask installed:no desc:browser /^fire/");
+        assert!(q.matches(&pkg("firefox", "formula", false)));
+        assert!(!q.matches(&pkg("firefox", "cask", false)));
+        assert!(!q.matches(&pkg("firefox", "formula", true)));
+        assert!(!q.matches(&pkg("librewolf", "formula", false)));
+    }
+}
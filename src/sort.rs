@@ -0,0 +1,225 @@
+//! Orders for the result list.
+//!
+//! Every mode other than relevance is a stable sort on top of the relevance
+//! order, so ties keep the best matches first.
+
+use std::cmp::Ordering;
+use std::fmt;
+use std::fs;
+use std::io;
+use std::path::PathBuf;
+use std::str::FromStr;
+
+use crate::provider::Package;
+use crate::util::{sort_by_relevance, state_dir};
+
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum SortMode {
+    #[default]
+    Relevance,
+    Name,
+    Source,
+    InstalledFirst,
+    /// Newest first.
+    Version,
+    /// Most popular first, for providers that report popularity.
+    Popularity,
+}
+
+fn sort_path() -> PathBuf {
+    state_dir().join("sort")
+}
+
+impl SortMode {
+    pub const ALL: [SortMode; 6] = [
+        SortMode::Relevance,
+        SortMode::Name,
+        SortMode::Source,
+        SortMode::InstalledFirst,
+        SortMode::Version,
+        SortMode::Popularity,
+    ];
+
+    pub fn label(self) -> &'static str {
+        match self {
+            SortMode::Relevance => "relevance",
+            SortMode::Name => "name",
+            SortMode::Source => "source",
+            SortMode::InstalledFirst => "installed",
+            SortMode::Version => "version",
+            SortMode::Popularity => "popularity",
+        }
+    }
+
+    /// The mode after this one that `usable` accepts, wrapping around.
+    pub fn next(self, usable: impl Fn(SortMode) -> bool) -> SortMode {
+        let idx = SortMode::ALL.iter().position(|&m| m == self).unwrap_or(0);
+        (1..=SortMode::ALL.len())
+            .map(|step| SortMode::ALL[(idx + step) % SortMode::ALL.len()])
+            .find(|&mode| usable(mode))
+            .unwrap_or_default()
+    }
+
+    /// Whether the mode can order `packages`: popularity needs results that
+    /// carry it, which only AUR results do.
+    pub fn applies_to(self, packages: &[Package]) -> bool {
+        match self {
+            SortMode::Popularity => packages.iter().any(|p| p.popularity.is_some()),
+            _ => true,
+        }
+    }
+
+    pub fn sort(self, packages: &mut [Package], query: &str) {
+        sort_by_relevance(packages, query);
+        match self {
+            SortMode::Relevance => {}
+            SortMode::Name => packages.sort_by_cached_key(|p| p.name.to_lowercase()),
+            SortMode::Source => packages.sort_by_cached_key(|p| p.source.to_lowercase()),
+            SortMode::InstalledFirst => packages.sort_by_key(|p| !p.installed),
+            SortMode::Version => packages.sort_by(|a, b| {
+                // Packages without a version go last
+                (a.version.is_empty().cmp(&b.version.is_empty()))
+                    .then_with(|| compare_versions(&b.version, &a.version))
+            }),
+            SortMode::Popularity => packages.sort_by(|a, b| match (a.popularity, b.popularity) {
+                (Some(a), Some(b)) => b.total_cmp(&a),
+                (a, b) => b.is_some().cmp(&a.is_some()),
+            }),
+        }
+    }
+
+    /// The mode saved by the last session, or relevance.
+    pub fn load() -> SortMode {
+        fs::read_to_string(sort_path()).ok().and_then(|s| s.parse().ok()).unwrap_or_default()
+    }
+
+    pub fn save(self) -> io::Result<()> {
+        let path = sort_path();
+        if let Some(dir) = path.parent() {
+            fs::create_dir_all(dir)?;
+        }
+        fs::write(path, self.label())
+    }
+}
+
+impl fmt::Display for SortMode {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(self.label())
+    }
+}
+
+impl FromStr for SortMode {
+    type Err = ();
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let s = s.trim();
+        SortMode::ALL.into_iter().find(|m| m.label() == s).ok_or(())
+    }
+}
+
+/// Compare version strings piecewise, numbers numerically, so that
+/// "1.10" > "1.9" and "2:1.0" > "1:9.9".
+pub fn compare_versions(a: &str, b: &str) -> Ordering {
+    let (mut a, mut b) = (pieces(a), pieces(b));
+    loop {
+        match (a.next(), b.next()) {
+            (None, None) => return Ordering::Equal,
+            // A trailing letter piece marks a pre-release: 1.0rc1 < 1.0 < 1.0.1
+            (Some(x), None) => return if is_number(x) { Ordering::Greater } else { Ordering::Less },
+            (None, Some(y)) => return if is_number(y) { Ordering::Less } else { Ordering::Greater },
+            (Some(x), Some(y)) => {
+                let ord = match (is_number(x), is_number(y)) {
+                    (true, true) => {
+                        let (x, y) = (x.trim_start_matches('0'), y.trim_start_matches('0'));
+                        x.len().cmp(&y.len()).then_with(|| x.cmp(y))
+                    }
+                    // A number outranks a letter suffix: 1.0 > 1.0rc
+                    (true, false) => Ordering::Greater,
+                    (false, true) => Ordering::Less,
+                    (false, false) => x.cmp(y),
+                };
+                if ord != Ordering::Equal {
+                    return ord;
+                }
+            }
+        }
+    }
+}
+
+fn is_number(piece: &str) -> bool {
+    piece.starts_with(|c: char| c.is_ascii_digit())
+}
+
+/// Runs of digits or of letters, skipping separators.
+fn pieces(version: &str) -> impl Iterator<Item = &str> {
+    let mut rest = version;
+    std::iter::from_fn(move || {
+        rest = rest.trim_start_matches(|c: char| !c.is_ascii_alphanumeric());
+        let first = rest.chars().next()?;
+        let digits = first.is_ascii_digit();
+        let end = rest
+            .find(|c: char| !c.is_ascii_alphanumeric() || c.is_ascii_digit() != digits)
+            .unwrap_or(rest.len());
+        let (piece, tail) = rest.split_at(end);
+        rest = tail;
+        Some(piece)
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn pkg(name: &str, version: &str, installed: bool, popularity: Option<f64>) -> Package {
+        Package {
+            name: name.to_string(),
+            version: version.to_string(),
+            installed,
+            popularity,
+            ..Default::default()
+        }
+    }
+
+    fn names(packages: &[Package]) -> Vec<&str> {
+        packages.iter().map(|p| p.name.as_str()).collect()
+    }
+
+    #[test]
+    fn compares_versions_numerically() {
+        assert_eq!(compare_versions("1.10", "1.9"), Ordering::Greater);
+        assert_eq!(compare_versions("2:1.0-1", "1:9.9-1"), Ordering::Greater);
+        assert_eq!(compare_versions("1.0", "1.0rc1"), Ordering::Greater);
+        assert_eq!(compare_versions("1.01", "1.1"), Ordering::Equal);
+        assert_eq!(compare_versions("1.0", "1.0.1"), Ordering::Less);
+    }
+
+    #[test]
+    fn sorts_by_mode_with_relevance_breaking_ties() {
+        let mut packages = vec![
+            pkg("vim-plug", "0.14", false, Some(3.0)),
+            pkg("neovim", "", true, None),
+            pkg("vim", "9.1", true, Some(1.0)),
+            pkg("gvim", "9.10", false, None),
+        ];
+        SortMode::Relevance.sort(&mut packages, "vim");
+        assert_eq!(names(&packages), ["vim", "vim-plug", "gvim", "neovim"]);
+        SortMode::InstalledFirst.sort(&mut packages, "vim");
+        assert_eq!(names(&packages), ["vim", "neovim", "vim-plug", "gvim"]);
+        SortMode::Version.sort(&mut packages, "vim");
+        assert_eq!(names(&packages), ["gvim", "vim", "vim-plug", "neovim"]);
+        SortMode::Popularity.sort(&mut packages, "vim");
+        assert_eq!(names(&packages), ["vim-plug", "vim", "gvim", "neovim"]);
+    }
+
+    #[test]
+    fn cycles_and_round_trips_labels() {
+        assert_eq!(SortMode::Popularity.next(|_| true), SortMode::Relevance);
+        let unrated = [pkg("vim", "9.1", true, None)];
+        assert_eq!(SortMode::Version.next(|m| m.applies_to(&unrated)), SortMode::Relevance);
+        let rated = [pkg("vim", "9.1", true, Some(1.0))];
+        assert_eq!(SortMode::Version.next(|m| m.applies_to(&rated)), SortMode::Popularity);
+        for mode in SortMode::ALL {
+            assert_eq!(mode.label().parse(), Ok(mode));
+        }
+    }
+}
@@ -0,0 +1,174 @@
+//! A command running in a pseudo-terminal, rendered inside the TUI.
+//!
+//! Output is fed through a vt100 emulator so colors, progress bars and
+//! prompts (sudo passwords, pacman confirmations) work as in a real terminal.
+
+use std::io::{self, Read, Write};
+use std::sync::{Arc, Mutex};
+use std::thread;
+
+use crossterm::event::{KeyCode, KeyEvent, KeyModifiers};
+use portable_pty::{Child, CommandBuilder, MasterPty, PtySize, native_pty_system};
+
+/// Lines kept above the visible screen.
+const SCROLLBACK_LINES: usize = 5000;
+
+pub struct TerminalPane {
+    pub title: String,
+    parser: Arc<Mutex<vt100::Parser>>,
+    master: Box<dyn MasterPty + Send>,
+    writer: Box<dyn Write + Send>,
+    child: Box<dyn Child + Send + Sync>,
+    /// Whether the command succeeded, once it has exited.
+    pub exit: Option<bool>,
+    /// The command's exit code, once it has exited; stays `None` if it was
+    /// killed by a signal.
+    pub exit_code: Option<i32>,
+}
+
+fn pty_error(e: impl std::fmt::Display) -> io::Error {
+    io::Error::other(e.to_string())
+}
+
+impl TerminalPane {
+    /// Start `program` with `args` on a new pseudo-terminal of the given size.
+    pub fn spawn(
+        title: String,
+        program: &std::ffi::OsStr,
+        args: &[String],
+        rows: u16,
+        cols: u16,
+    ) -> io::Result<TerminalPane> {
+        let size = PtySize { rows, cols, pixel_width: 0, pixel_height: 0 };
+        let pair = native_pty_system().openpty(size).map_err(pty_error)?;
+
+        let mut cmd = CommandBuilder::new(program);
+        cmd.args(args);
+        cmd.cwd(std::env::current_dir()?);
+        cmd.env("TERM", "xterm-256color");
+        let child = pair.slave.spawn_command(cmd).map_err(pty_error)?;
+        // Only the child should hold the slave end, so reads hit EOF when it exits
+        drop(pair.slave);
+
+        let parser = Arc::new(Mutex::new(vt100::Parser::new(rows, cols, SCROLLBACK_LINES)));
+        let mut reader = pair.master.try_clone_reader().map_err(pty_error)?;
+        let writer = pair.master.take_writer().map_err(pty_error)?;
+
+        let output = Arc::clone(&parser);
+        thread::spawn(move || {
+            let mut buf = [0u8; 8192];
+            while let Ok(n) = reader.read(&mut buf) {
+                if n == 0 {
+                    break;
+                }
+                if let Ok(mut parser) = output.lock() {
+                    parser.process(&buf[..n]);
+                }
+            }
+        });
+
+        Ok(TerminalPane {
+            title,
+            parser,
+            master: pair.master,
+            writer,
+            child,
+            exit: None,
+            exit_code: None,
+        })
+    }
+
+    /// Check whether the command has exited. Returns true once it has.
+    pub fn poll(&mut self) -> bool {
+        if self.exit.is_none()
+            && let Ok(Some(status)) = self.child.try_wait()
+        {
+            self.exit = Some(status.success());
+            self.exit_code = status.signal().is_none().then(|| status.exit_code() as i32);
+        }
+        self.exit.is_some()
+    }
+
+    /// Match the pseudo-terminal to the pane's inner size.
+    pub fn resize(&mut self, rows: u16, cols: u16) {
+        let Ok(mut parser) = self.parser.lock() else { return };
+        if parser.screen().size() == (rows, cols) || rows == 0 || cols == 0 {
+            return;
+        }
+        parser.screen_mut().set_size(rows, cols);
+        self.master.resize(PtySize { rows, cols, pixel_width: 0, pixel_height: 0 }).ok();
+    }
+
+    /// Scroll the view back (positive) or towards the live screen (negative).
+    pub fn scroll(&mut self, delta: isize) {
+        let Ok(mut parser) = self.parser.lock() else { return };
+        let current = parser.screen().scrollback();
+        parser.screen_mut().set_scrollback(current.saturating_add_signed(delta));
+    }
+
+    /// Forward a key press to the command, as a terminal would encode it.
+    pub fn send_key(&mut self, key: KeyEvent) {
+        let application_cursor =
+            self.parser.lock().map(|p| p.screen().application_cursor()).unwrap_or(false);
+        let Some(bytes) = encode_key(key, application_cursor) else { return };
+        // Typing jumps back to the live screen
+        if let Ok(mut parser) = self.parser.lock() {
+            parser.screen_mut().set_scrollback(0);
+        }
+        self.writer.write_all(&bytes).ok();
+        self.writer.flush().ok();
+    }
+
+    /// Run `f` with the current screen contents.
+    pub fn with_screen<R>(&self, f: impl FnOnce(&vt100::Screen) -> R) -> Option<R> {
+        self.parser.lock().ok().map(|parser| f(parser.screen()))
+    }
+}
+
+impl Drop for TerminalPane {
+    fn drop(&mut self) {
+        if self.exit.is_none() {
+            self.child.kill().ok();
+        }
+    }
+}
+
+fn encode_key(key: KeyEvent, application_cursor: bool) -> Option<Vec<u8>> {
+    let cursor = |c: char| {
+        let prefix = if application_cursor { "\x1bO" } else { "\x1b[" };
+        format!("{prefix}{c}").into_bytes()
+    };
+    let bytes = match key.code {
+        KeyCode::Char(c) if key.modifiers.contains(KeyModifiers::CONTROL) => {
+            let c = c.to_ascii_lowercase();
+            if !c.is_ascii_lowercase() {
+                return None;
+            }
+            vec![c as u8 - b'a' + 1]
+        }
+        KeyCode::Char(c) => {
+            let mut bytes = Vec::new();
+            if key.modifiers.contains(KeyModifiers::ALT) {
+                bytes.push(0x1b);
+            }
+            bytes.extend(c.to_string().into_bytes());
+            bytes
+        }
+        KeyCode::Enter => vec![b'\r'],
+        KeyCode::Backspace => vec![0x7f],
+        KeyCode::Tab => vec![b'\t'],
+        KeyCode::BackTab => b"\x1b[Z".to_vec(),
+        KeyCode::Esc => vec![0x1b],
+        KeyCode::Up => cursor('A'),
+        KeyCode::Down => cursor('B'),
+        KeyCode::Right => cursor('C'),
+        KeyCode::Left => cursor('D'),
+        KeyCode::Home => cursor('H'),
+        KeyCode::End => cursor('F'),
+        KeyCode::PageUp => b"\x1b[5~".to_vec(),
+        KeyCode::PageDown => b"\x1b[6~".to_vec(),
+        KeyCode::Delete => b"\x1b[3~".to_vec(),
+        _ => return None,
+    };
+    Some(bytes)
+}
@@ -0,0 +1,172 @@
+//! Names of the same package in different package managers, e.g. apt's
+//! `libssl-dev` is dnf's `openssl-devel`.
+//!
+//! The shipped table (`translations.toml` next to this file) can be extended
+//! and corrected in `$XDG_CONFIG_HOME/fex/translations.toml`, in the same
+//! format:
+//!
+//! ```toml
+//! [[package]]
+//! apt = "libfoo-dev"
+//! dnf = "foo-devel"
+//! pacman = "foo"
+//! ```
+
+use std::collections::BTreeMap;
+use std::fs;
+
+use serde::Deserialize;
+
+use crate::util::config_dir;
+
+const SHIPPED: &str = include_str!("translations.toml");
+
+/// Ecosystem → package name.
+type Group = BTreeMap<String, String>;
+
+#[derive(Deserialize)]
+#[serde(deny_unknown_fields)]
+struct TranslationFile {
+    #[serde(rename = "package", default)]
+    groups: Vec<Group>,
+}
+
+#[derive(Debug, Default)]
+pub struct Translations {
+    groups: Vec<Group>,
+}
+
+/// The ecosystem whose names a provider uses: AUR helpers share pacman's
+/// names and zerobrew shares Homebrew's.
+pub fn ecosystem(provider: &str) -> &str {
+    match provider {
+        "paru" | "yay" => "pacman",
+        "zerobrew" => "brew",
+        other => other,
+    }
+}
+
+impl Translations {
+    /// The shipped table with the user's entries merged in. Problems with the
+    /// user's file are reported on stderr and the file ignored.
+    pub fn load() -> Translations {
+        let mut translations = Translations::shipped();
+        let path = config_dir().join("translations.toml");
+        if let Ok(data) = fs::read_to_string(&path) {
+            match toml::from_str::<TranslationFile>(&data) {
+                Ok(file) => translations.merge(file.groups),
+                Err(e) => eprintln!("Warning: ignoring {}: {e}", path.display()),
+            }
+        }
+        translations
+    }
+
+    pub fn shipped() -> Translations {
+        let file: TranslationFile = toml::from_str(SHIPPED).expect("shipped translations parse");
+        Translations { groups: file.groups }
+    }
+
+    /// Add `groups`. One that shares a name with an existing group updates
+    /// that group instead, its names taking precedence.
+    fn merge(&mut self, groups: Vec<Group>) {
+        for group in groups {
+            let existing = self.groups.iter_mut().find(|known| {
+                group.iter().any(|(eco, name)| known.get(eco) == Some(name))
+            });
+            match existing {
+                Some(known) => known.extend(group),
+                None => self.groups.push(group),
+            }
+        }
+    }
+
+    /// Groups with `name`, as the name in `from` or in any ecosystem.
+    fn groups_with(&self, name: &str, from: Option<&str>) -> Vec<&Group> {
+        let matches = |group: &&Group| match from {
+            Some(from) => group.get(ecosystem(from)).is_some_and(|n| n == name),
+            None => group.values().any(|n| n == name),
+        };
+        self.groups.iter().filter(matches).collect()
+    }
+
+    /// What `name` is called in the ecosystem of provider `to`. There can be
+    /// several answers: pacman's `xz` is both apt's `xz-utils` and
+    /// `liblzma-dev`.
+    pub fn translate(&self, name: &str, from: Option<&str>, to: &str) -> Vec<&str> {
+        let mut names: Vec<&str> = Vec::new();
+        for group in self.groups_with(name, from) {
+            if let Some(target) = group.get(ecosystem(to))
+                && !names.contains(&target.as_str())
+            {
+                names.push(target);
+            }
+        }
+        names
+    }
+
+    /// Every known name of `name`, as (ecosystem, name) pairs.
+    pub fn equivalents(&self, name: &str, from: Option<&str>) -> Vec<(&str, &str)> {
+        let mut pairs: Vec<(&str, &str)> = Vec::new();
+        for group in self.groups_with(name, from) {
+            for (eco, other) in group {
+                if !pairs.contains(&(eco.as_str(), other.as_str())) {
+                    pairs.push((eco, other));
+                }
+            }
+        }
+        pairs
+    }
+
+    /// A hint for searching `query` with `provider` when it is a package name
+    /// from another ecosystem, e.g. "libssl-dev is openssl-devel on dnf".
+    pub fn hint(&self, query: &str, provider: &str) -> Option<String> {
+        let eco = ecosystem(provider);
+        // Native names need no hint
+        if query.is_empty() || !self.groups_with(query, Some(eco)).is_empty() {
+            return None;
+        }
+        let names = self.translate(query, None, eco);
+        if names.is_empty() {
+            return None;
+        }
+        Some(format!("{query} is {} on {eco}", names.join(" or ")))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn translates_between_ecosystems() {
+        let t = Translations::shipped();
+        assert_eq!(t.translate("libssl-dev", None, "dnf"), ["openssl-devel"]);
+        assert_eq!(t.translate("libssl-dev", Some("apt"), "paru"), ["openssl"]);
+        assert!(t.translate("libssl-dev", Some("dnf"), "apt").is_empty());
+        // pacman's xz stands for two apt packages
+        assert_eq!(t.translate("xz", Some("pacman"), "apt"), ["liblzma-dev", "xz-utils"]);
+        let hint = t.hint("libssl-dev", "dnf");
+        assert_eq!(hint.as_deref(), Some("libssl-dev is openssl-devel on dnf"));
+        assert_eq!(t.hint("openssl-devel", "dnf"), None);
+    }
+
+    #[test]
+    fn user_entries_extend_and_override() {
+        let mut t = Translations::shipped();
+        let user: TranslationFile = toml::from_str(
+            r#"
+            [[package]]
+            apt = "libssl-dev"
+            dnf = "openssl3-devel"
+            [[package]]
+            apt = "libfoo-dev"
+            pacman = "foo"
+            "#,
+        )
+        .unwrap();
+        t.merge(user.groups);
+        assert_eq!(t.translate("libssl-dev", None, "dnf"), ["openssl3-devel"]);
+        assert_eq!(t.translate("libssl-dev", None, "apk"), ["openssl-dev"]);
+        assert_eq!(t.translate("foo", Some("yay"), "apt"), ["libfoo-dev"]);
+    }
+}
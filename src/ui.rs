@@ -3,10 +3,41 @@ use ratatui::{
     layout::{Constraint, Direction, Layout},
     style::{Color, Modifier, Style},
     text::{Line, Span, Text},
-    widgets::{Block, Borders, Paragraph},
+    widgets::{Block, Borders, Clear, Paragraph},
 };
 
-use crate::app::{App, SearchState};
+use fex::{PlanAction, SearchError};
+use fex::util::format_size;
+use fex::journal::format_timestamp;
+use fex::manifest::ExportFormat;
+use crate::app::{
+    App, Confirmation, DependencyTree, FileList, HistoryView, IndexState, Operation, PlanState,
+    SearchState,
+};
+use crate::terminal::TerminalPane;
+
+/// Map the library's badge color onto the terminal palette.
+fn to_tui_color(color: fex::Color) -> Color {
+    match color {
+        fex::Color::Black => Color::Black,
+        fex::Color::Red => Color::Red,
+        fex::Color::Green => Color::Green,
+        fex::Color::Yellow => Color::Yellow,
+        fex::Color::Blue => Color::Blue,
+        fex::Color::Magenta => Color::Magenta,
+        fex::Color::Cyan => Color::Cyan,
+        fex::Color::Gray => Color::Gray,
+        fex::Color::DarkGray => Color::DarkGray,
+        fex::Color::LightRed => Color::LightRed,
+        fex::Color::LightGreen => Color::LightGreen,
+        fex::Color::LightYellow => Color::LightYellow,
+        fex::Color::LightBlue => Color::LightBlue,
+        fex::Color::LightMagenta => Color::LightMagenta,
+        fex::Color::LightCyan => Color::LightCyan,
+        fex::Color::White => Color::White,
+        fex::Color::Rgb(r, g, b) => Color::Rgb(r, g, b),
+    }
+}
 
 pub fn render(f: &mut Frame, app: &App) {
     let area = f.area();
@@ -22,7 +53,293 @@ pub fn render(f: &mut Frame, app: &App) {
 
     render_search(f, app, chunks[0]);
     render_status(f, app, chunks[1]);
-    render_results(f, app, chunks[2]);
+    if let Some(running) = &app.operation {
+        render_terminal(f, &running.pane, chunks[2]);
+    } else if let Some(history) = &app.history {
+        render_history(f, history, chunks[2]);
+    } else if let Some(tree) = &app.tree {
+        render_tree(f, tree, chunks[2]);
+    } else if let Some(list) = &app.files {
+        render_files(f, list, chunks[2]);
+    } else {
+        render_results(f, app, chunks[2]);
+    }
+    if let Some(confirm) = &app.confirmation {
+        render_confirmation(f, app, confirm, chunks[2]);
+    }
+    if let Some(selected) = app.export {
+        render_export(f, app, selected, chunks[2]);
+    }
+}
+
+/// Dialog choosing the format to export the marked or installed packages in.
+fn render_export(f: &mut Frame, app: &App, selected: usize, area: ratatui::layout::Rect) {
+    let what = match app.marked.len() {
+        0 => "installed packages".to_string(),
+        1 => "1 marked package".to_string(),
+        n => format!("{n} marked packages"),
+    };
+    let mut lines = vec![Line::raw("")];
+    for (idx, format) in ExportFormat::ALL.into_iter().enumerate() {
+        let text = format!(" {:<10}{:<14}", format.label(), format.file_name());
+        let style = if idx == selected {
+            Style::new().add_modifier(Modifier::REVERSED)
+        } else {
+            Style::new()
+        };
+        lines.push(Line::styled(text, style));
+    }
+    lines.push(Line::raw(""));
+    let key = Style::new().fg(Color::Cyan);
+    lines.push(Line::from(vec![
+        Span::styled(" Enter", key),
+        Span::raw(" export   "),
+        Span::styled("Esc", key),
+        Span::raw(" cancel"),
+    ]));
+
+    let width = area.width.min(40);
+    let height = (lines.len() as u16 + 2).min(area.height);
+    let popup = ratatui::layout::Rect {
+        x: area.x + (area.width - width) / 2,
+        y: area.y + (area.height - height) / 2,
+        width,
+        height,
+    };
+    let block = Block::default()
+        .borders(Borders::ALL)
+        .border_style(Style::new().fg(Color::Blue))
+        .title(format!(" Export {what} "));
+    let inner = block.inner(popup);
+    f.render_widget(Clear, popup);
+    f.render_widget(block, popup);
+    f.render_widget(Paragraph::new(lines), inner);
+}
+
+/// Dialog asking whether to run an install or removal, centered over `area`.
+fn render_confirmation(
+    f: &mut Frame,
+    app: &App,
+    confirm: &Confirmation,
+    area: ratatui::layout::Rect,
+) {
+    let label = Style::new().fg(Color::DarkGray);
+    let field = |name: &'static str, value: String| {
+        Line::from(vec![Span::styled(format!(" {name:<9}"), label), Span::raw(value)])
+    };
+    let (verb, color) = match confirm.op {
+        Operation::Install => ("Install", Color::Green),
+        Operation::Remove => ("Remove", Color::Red),
+    };
+
+    let width = area.width.saturating_sub(4).min(90);
+
+    // Long commands scroll sideways to keep the cursor in view
+    let room = width.saturating_sub(3) as usize;
+    let start = confirm.editing.map_or(0, |cursor| (cursor + 1).saturating_sub(room));
+    let visible: String = confirm.command.chars().skip(start).collect();
+    let command = if confirm.editing.is_some() {
+        visible.chars().take(room).collect()
+    } else {
+        truncate(&visible, room)
+    };
+    let mut command_style = Style::new().add_modifier(Modifier::BOLD);
+    if confirm.command != confirm.original {
+        command_style = command_style.fg(Color::Yellow);
+    }
+    let mut lines = vec![
+        Line::raw(""),
+        field("Package", format!("{} {}", confirm.pkg.name, confirm.pkg.version)),
+        field("Provider", app.provider.name().to_string()),
+        field("Source", confirm.pkg.source.clone()),
+        Line::raw(""),
+        Line::styled(" Command", label),
+        Line::styled(format!(" {command}"), command_style),
+        Line::raw(""),
+    ];
+    if let Some(plan) = &confirm.plan {
+        // Borders, the lines around the plan and its header and summary
+        let room = area.height.saturating_sub(lines.len() as u16 + 6) as usize;
+        lines.extend(plan_lines(plan, room));
+        lines.push(Line::raw(""));
+    }
+    let key = Style::new().fg(Color::Cyan);
+    lines.push(if confirm.editing.is_some() {
+        Line::from(vec![
+            Span::styled(" Enter", key),
+            Span::raw(" run edited command   "),
+            Span::styled("Esc", key),
+            Span::raw(" stop editing"),
+        ])
+    } else if confirm.editable {
+        Line::from(vec![
+            Span::styled(" Enter", key),
+            Span::raw(" confirm   "),
+            Span::styled("e", key),
+            Span::raw(" edit command   "),
+            Span::styled("Esc", key),
+            Span::raw(" cancel"),
+        ])
+    } else {
+        Line::from(vec![
+            Span::styled(" Enter", key),
+            Span::raw(" confirm   "),
+            Span::styled("Esc", key),
+            Span::raw(" cancel"),
+        ])
+    });
+
+    let height = (lines.len() as u16 + 2).min(area.height);
+    let popup = ratatui::layout::Rect {
+        x: area.x + (area.width - width) / 2,
+        y: area.y + (area.height - height) / 2,
+        width,
+        height,
+    };
+    let block = Block::default()
+        .borders(Borders::ALL)
+        .border_style(Style::new().fg(color))
+        .title(format!(" {verb} {}? ", confirm.pkg.name));
+    let inner = block.inner(popup);
+    f.render_widget(Clear, popup);
+    f.render_widget(block, popup);
+    f.render_widget(Paragraph::new(lines), inner);
+
+    if let Some(cursor) = confirm.editing {
+        // The command is on the 7th line, after a leading space
+        f.set_cursor_position((inner.x + 1 + (cursor - start) as u16, inner.y + 6));
+    }
+}
+
+/// The dry run of an install, listing at most `room` packages.
+fn plan_lines(plan: &PlanState, room: usize) -> Vec<Line<'static>> {
+    let label = Style::new().fg(Color::DarkGray);
+    let plan = match plan {
+        PlanState::Resolving => return vec![Line::styled(" Dry run: resolving...", label)],
+        // Providers explain themselves why they can't do one
+        PlanState::Failed(SearchError::Other(message)) => {
+            return vec![Line::styled(format!(" No dry run: {message}"), label)];
+        }
+        PlanState::Failed(e) => {
+            let style = Style::new().fg(Color::Red);
+            return vec![Line::styled(format!(" Dry run failed: {e}"), style)];
+        }
+        PlanState::Ready(plan) if plan.entries.is_empty() => {
+            return vec![Line::styled(" Dry run: nothing to change", label)];
+        }
+        PlanState::Ready(plan) => plan,
+    };
+
+    let n = plan.entries.len();
+    let mut lines = vec![Line::styled(
+        format!(" Transaction ({n} package{})", if n == 1 { "" } else { "s" }),
+        label,
+    )];
+    // Keep a line for "... and N more" when they don't all fit
+    let shown = if n > room { room.saturating_sub(1) } else { n };
+    for entry in &plan.entries[..shown] {
+        let (mark, color) = match entry.action {
+            PlanAction::Install => ("+", Color::Green),
+            PlanAction::Upgrade => ("↑", Color::Yellow),
+            PlanAction::Remove => ("-", Color::Red),
+        };
+        lines.push(Line::from(vec![
+            Span::styled(format!("  {mark} "), Style::new().fg(color)),
+            Span::raw(entry.name.clone()),
+            Span::styled(format!(" {}", entry.version), label),
+        ]));
+    }
+    if shown < n {
+        lines.push(Line::styled(format!("    ... and {} more", n - shown), label));
+    }
+
+    let mut sizes = Vec::new();
+    if let Some(bytes) = plan.download_size {
+        sizes.push(format!("download {}", format_size(bytes)));
+    }
+    match plan.install_size {
+        Some(bytes) if bytes < 0 => {
+            sizes.push(format!("frees {}", format_size(bytes.unsigned_abs())));
+        }
+        Some(bytes) => sizes.push(format!("uses {}", format_size(bytes as u64))),
+        None => {}
+    }
+    if !sizes.is_empty() {
+        lines.push(Line::styled(format!(" {}", sizes.join(", ")), label));
+    }
+    lines
+}
+
+/// The embedded terminal of a running install or removal.
+fn render_terminal(f: &mut Frame, pane: &TerminalPane, area: ratatui::layout::Rect) {
+    let hint = match pane.exit {
+        None => " Shift+PgUp/PgDn: scroll ".to_string(),
+        Some(success) => {
+            let outcome = if success { "finished" } else { "failed" };
+            format!(" {outcome} — Enter: back to results ")
+        }
+    };
+    let border = match pane.exit {
+        None => Color::Yellow,
+        Some(true) => Color::Green,
+        Some(false) => Color::Red,
+    };
+    let block = Block::default()
+        .borders(Borders::ALL)
+        .border_style(Style::new().fg(border))
+        .title(format!(" {} ", pane.title))
+        .title_bottom(Line::from(hint).right_aligned());
+    let inner = block.inner(area);
+    f.render_widget(block, area);
+
+    let Some((lines, cursor)) = pane.with_screen(|screen| {
+        let (rows, cols) = screen.size();
+        let lines: Vec<Line> = (0..rows)
+            .map(|row| {
+                let spans: Vec<Span> = (0..cols)
+                    .filter_map(|col| screen.cell(row, col))
+                    .filter(|cell| !cell.is_wide_continuation())
+                    .map(|cell| {
+                        let text = if cell.has_contents() { cell.contents() } else { " " };
+                        Span::styled(text.to_string(), cell_style(cell))
+                    })
+                    .collect();
+                Line::from(spans)
+            })
+            .collect();
+        let cursor = (screen.scrollback() == 0 && !screen.hide_cursor())
+            .then(|| screen.cursor_position());
+        (lines, cursor)
+    }) else {
+        return;
+    };
+    f.render_widget(Paragraph::new(lines), inner);
+    if pane.exit.is_none()
+        && let Some((row, col)) = cursor
+    {
+        f.set_cursor_position((inner.x + col, inner.y + row));
+    }
+}
+
+fn cell_style(cell: &vt100::Cell) -> Style {
+    let color = |c: vt100::Color| match c {
+        vt100::Color::Default => Color::Reset,
+        vt100::Color::Idx(i) => Color::Indexed(i),
+        vt100::Color::Rgb(r, g, b) => Color::Rgb(r, g, b),
+    };
+    let mut style = Style::new().fg(color(cell.fgcolor())).bg(color(cell.bgcolor()));
+    for (on, modifier) in [
+        (cell.bold(), Modifier::BOLD),
+        (cell.dim(), Modifier::DIM),
+        (cell.italic(), Modifier::ITALIC),
+        (cell.underline(), Modifier::UNDERLINED),
+        (cell.inverse(), Modifier::REVERSED),
+    ] {
+        if on {
+            style = style.add_modifier(modifier);
+        }
+    }
+    style
 }
 
 fn truncate(s: &str, max: usize) -> String {
@@ -40,6 +357,28 @@ fn truncate(s: &str, max: usize) -> String {
     }
 }
 
+/// " name" with the characters matching the query highlighted.
+fn name_spans(name: &str, query: &str, style: Style) -> Vec<Span<'static>> {
+    let positions = fex::fuzzy::highlight_positions(query, name);
+    let highlight = style.fg(Color::Yellow);
+    let mut spans = vec![Span::styled(" ", style)];
+    let mut run = String::new();
+    let mut run_matched = false;
+    for (i, c) in name.chars().enumerate() {
+        let matched = positions.binary_search(&i).is_ok();
+        if matched != run_matched && !run.is_empty() {
+            let s = if run_matched { highlight } else { style };
+            spans.push(Span::styled(std::mem::take(&mut run), s));
+        }
+        run_matched = matched;
+        run.push(c);
+    }
+    if !run.is_empty() {
+        spans.push(Span::styled(run, if run_matched { highlight } else { style }));
+    }
+    spans
+}
+
 fn render_results(f: &mut Frame, app: &App, area: ratatui::layout::Rect) {
     let visible_count = (area.height as usize) / 2;
     let width = area.width as usize;
@@ -56,8 +395,20 @@ fn render_results(f: &mut Frame, app: &App, area: ratatui::layout::Rect) {
         }
         let pkg = &app.packages[pkg_idx];
         let is_selected = pkg_idx == app.selected;
+        // Packages marked for export have yellow names
+        let name_style = if app.is_marked(pkg) {
+            Style::new().fg(Color::Yellow).add_modifier(Modifier::BOLD)
+        } else {
+            Style::new().add_modifier(Modifier::BOLD)
+        };
 
-        let source_color = app.provider.source_color(&pkg.source);
+        let source_color = to_tui_color(app.provider.source_color(&pkg.source));
+        // File searches show the paths that matched instead of the description
+        let (detail, detail_style) = if pkg.files.is_empty() {
+            (pkg.description.clone(), Style::new())
+        } else {
+            (pkg.files.join("  "), Style::new().fg(Color::Cyan))
+        };
 
         if is_selected {
             // Source badge keeps its color; the rest gets REVERSED
@@ -71,11 +422,10 @@ fn render_results(f: &mut Frame, app: &App, area: ratatui::layout::Rect) {
             } else {
                 Span::styled("  ", Style::new().add_modifier(Modifier::REVERSED))
             };
-            let name_span = Span::styled(
-                format!(" {}", pkg.name),
-                Style::new()
-                    .add_modifier(Modifier::BOLD)
-                    .add_modifier(Modifier::REVERSED),
+            let name_spans = name_spans(
+                &pkg.name,
+                &app.parsed.text,
+                name_style.add_modifier(Modifier::REVERSED),
             );
             let version_part = if pkg.version.is_empty() {
                 String::new()
@@ -97,21 +447,21 @@ fn render_results(f: &mut Frame, app: &App, area: ratatui::layout::Rect) {
                 " ".repeat(pad_len),
                 Style::new().add_modifier(Modifier::REVERSED),
             );
-            lines.push(Line::from(vec![
-                source_span,
-                installed_span,
-                name_span,
-                version_span,
-                pad_span,
-            ]));
+            let mut spans = vec![source_span, installed_span];
+            spans.extend(name_spans);
+            spans.extend([version_span, pad_span]);
+            lines.push(Line::from(spans));
 
             // Description line (selected)
             let indent = "         "; // 9 spaces to match C++ DESC indent
             let max_desc = width.saturating_sub(9);
-            let desc = truncate(&pkg.description, max_desc);
+            let desc = truncate(&detail, max_desc);
             let desc_pad = " ".repeat(width.saturating_sub(9 + desc.len()));
             lines.push(Line::from(vec![
-                Span::styled(format!("{indent}{desc}"), Style::new().add_modifier(Modifier::REVERSED)),
+                Span::styled(
+                    format!("{indent}{desc}"),
+                    detail_style.add_modifier(Modifier::REVERSED),
+                ),
                 Span::styled(desc_pad, Style::new().add_modifier(Modifier::REVERSED)),
             ]));
         } else {
@@ -123,26 +473,24 @@ fn render_results(f: &mut Frame, app: &App, area: ratatui::layout::Rect) {
             } else {
                 Span::raw("  ")
             };
-            let name_span =
-                Span::styled(format!(" {}", pkg.name), Style::new().add_modifier(Modifier::BOLD));
+            let name_spans =
+                name_spans(&pkg.name, &app.parsed.text, name_style);
             let version_part = if pkg.version.is_empty() {
                 String::new()
             } else {
                 format!(" {}", pkg.version)
             };
             let version_span = Span::raw(version_part);
-            lines.push(Line::from(vec![
-                source_span,
-                installed_span,
-                name_span,
-                version_span,
-            ]));
+            let mut spans = vec![source_span, installed_span];
+            spans.extend(name_spans);
+            spans.push(version_span);
+            lines.push(Line::from(spans));
 
             // Description line
             let indent = "         ";
             let max_desc = width.saturating_sub(9);
-            let desc = truncate(&pkg.description, max_desc);
-            lines.push(Line::raw(format!("{indent}{desc}")));
+            let desc = truncate(&detail, max_desc);
+            lines.push(Line::styled(format!("{indent}{desc}"), detail_style));
         }
     }
 
@@ -150,23 +498,240 @@ fn render_results(f: &mut Frame, app: &App, area: ratatui::layout::Rect) {
     f.render_widget(results_widget, area);
 }
 
+/// Journal entries, one per line, newest first.
+fn render_history(f: &mut Frame, history: &HistoryView, area: ratatui::layout::Rect) {
+    let hints = Line::from(" ↑↓ select · u undo · Esc close ").right_aligned();
+    let mut block = Block::default().borders(Borders::ALL).title(" History ").title_bottom(hints);
+    if let Some(message) = &history.message {
+        let message = Span::styled(format!(" {message} "), Style::new().fg(Color::Red));
+        block = block.title_bottom(message);
+    }
+    let inner = block.inner(area);
+    f.render_widget(block, area);
+
+    if history.entries.is_empty() {
+        let empty = Paragraph::new("No installs or removals recorded yet.")
+            .style(Style::new().fg(Color::DarkGray));
+        f.render_widget(empty, inner);
+        return;
+    }
+
+    let width = inner.width as usize;
+    let lines: Vec<Line> = history
+        .entries
+        .iter()
+        .enumerate()
+        .skip(history.scroll_offset)
+        .take(inner.height as usize)
+        .map(|(idx, entry)| {
+            let (verb, color) = match entry.operation {
+                Operation::Install => ("install", Color::Green),
+                Operation::Remove => ("remove ", Color::Red),
+            };
+            let outcome = match entry.exit_code {
+                Some(0) => Span::styled("ok      ", Style::new().fg(Color::Green)),
+                Some(code) => Span::styled(format!("exit {code:<3}"), Style::new().fg(Color::Red)),
+                None => Span::styled("killed  ", Style::new().fg(Color::Red)),
+            };
+            let mut spans = vec![
+                Span::raw(format!("{}  ", format_timestamp(entry.timestamp))),
+                Span::styled(verb, Style::new().fg(color)),
+                Span::raw("  "),
+                outcome,
+                Span::styled(entry.package.clone(), Style::new().add_modifier(Modifier::BOLD)),
+            ];
+            let detail = format!(
+                " {} [{}] via {}, {:.1}s{}",
+                entry.version,
+                entry.source,
+                entry.provider,
+                entry.duration_ms as f64 / 1000.0,
+                if entry.user.is_empty() { String::new() } else { format!(" by {}", entry.user) },
+            );
+            spans.push(Span::styled(detail, Style::new().fg(Color::DarkGray)));
+            let mut line = Line::from(spans);
+            if idx == history.selected {
+                let used: usize = line.spans.iter().map(|s| s.content.chars().count()).sum();
+                line.spans.push(Span::raw(" ".repeat(width.saturating_sub(used))));
+                line = line.style(Style::new().add_modifier(Modifier::REVERSED));
+            }
+            line
+        })
+        .collect();
+    f.render_widget(Paragraph::new(Text::from(lines)), inner);
+}
+
+/// A dependency tree, one package per line, indented by depth.
+fn render_tree(f: &mut Frame, tree: &DependencyTree, area: ratatui::layout::Rect) {
+    let hints = " →← expand/collapse · Enter search · i install · Tab reverse · Esc close ";
+    let title = format!(" {}: {} ", tree.direction.label(), tree.nodes[0].pkg.name);
+    let mut block = Block::default()
+        .borders(Borders::ALL)
+        .title(title)
+        .title_bottom(Line::from(hints).right_aligned());
+    if let Some(message) = &tree.message {
+        let message = Span::styled(format!(" {message} "), Style::new().fg(Color::Red));
+        block = block.title_bottom(message);
+    }
+    let inner = block.inner(area);
+    f.render_widget(block, area);
+
+    let width = inner.width as usize;
+    let dim = Style::new().fg(Color::DarkGray);
+    let lines: Vec<Line> = tree
+        .nodes
+        .iter()
+        .enumerate()
+        .skip(tree.scroll_offset)
+        .take(inner.height as usize)
+        .map(|(idx, node)| {
+            let listed = tree.children.get(&node.pkg.name);
+            let marker = match listed {
+                Some(Ok(children)) if children.is_empty() => "· ",
+                _ if node.expanded => "▾ ",
+                _ => "▸ ",
+            };
+            let mut spans = vec![
+                Span::raw(format!("{}{marker}", "  ".repeat(node.depth))),
+                Span::styled(node.pkg.name.clone(), Style::new().add_modifier(Modifier::BOLD)),
+            ];
+            if node.pkg.installed {
+                spans.push(Span::styled(" *", Style::new().fg(Color::Green)));
+            }
+            if !node.pkg.version.is_empty() {
+                spans.push(Span::styled(format!(" {}", node.pkg.version), dim));
+            }
+            if node.expanded {
+                match listed {
+                    None => spans.push(Span::styled("  listing...", dim)),
+                    Some(Err(e)) => {
+                        spans.push(Span::styled(format!("  {e}"), Style::new().fg(Color::Red)));
+                    }
+                    Some(Ok(children)) if children.is_empty() => {
+                        spans.push(Span::styled("  (none)", dim));
+                    }
+                    Some(Ok(_)) => {}
+                }
+            }
+            let mut line = Line::from(spans);
+            if idx == tree.selected {
+                let used: usize = line.spans.iter().map(|s| s.content.chars().count()).sum();
+                line.spans.push(Span::raw(" ".repeat(width.saturating_sub(used))));
+                line = line.style(Style::new().add_modifier(Modifier::REVERSED));
+            }
+            line
+        })
+        .collect();
+    f.render_widget(Paragraph::new(Text::from(lines)), inner);
+}
+
+/// A package's files below the filter being typed. Matches of the filter are
+/// highlighted and executables shown in green.
+fn render_files(f: &mut Frame, list: &FileList, area: ratatui::layout::Rect) {
+    let visible = list.visible();
+    let title = match &list.files {
+        Some(Ok(files)) if !list.filter.is_empty() => {
+            format!(" Files of {} ({} of {}) ", list.pkg.name, visible.len(), files.len())
+        }
+        Some(Ok(files)) => format!(" Files of {} ({}) ", list.pkg.name, files.len()),
+        _ => format!(" Files of {} ", list.pkg.name),
+    };
+    let hints = Line::from(" type to filter · ↑↓ select · Esc clear/close ").right_aligned();
+    let block = Block::default().borders(Borders::ALL).title(title).title_bottom(hints);
+    let inner = block.inner(area);
+    f.render_widget(block, area);
+
+    let dim = Style::new().fg(Color::DarkGray);
+    let mut lines = vec![Line::from(vec![
+        Span::styled("Filter: ", dim),
+        Span::styled(format!("{}█", list.filter), Style::new().add_modifier(Modifier::BOLD)),
+    ])];
+    match &list.files {
+        None => lines.push(Line::styled("Listing files...", dim)),
+        Some(Err(e)) => lines.push(Line::styled(e.clone(), Style::new().fg(Color::Red))),
+        Some(Ok(files)) if files.is_empty() => lines.push(Line::styled("No files.", dim)),
+        Some(Ok(_)) if visible.is_empty() => {
+            lines.push(Line::styled("No files match the filter.", dim));
+        }
+        Some(Ok(_)) => {}
+    }
+
+    let width = inner.width as usize;
+    let rows = (inner.height as usize).saturating_sub(1);
+    for (idx, path) in visible.iter().enumerate().skip(list.scroll_offset).take(rows) {
+        let style = if path.contains("/bin/") || path.contains("/sbin/") {
+            Style::new().fg(Color::Green)
+        } else {
+            Style::new()
+        };
+        let mut spans = Vec::new();
+        // ASCII lowercasing keeps byte offsets, so the match can be sliced out
+        let start = path.to_ascii_lowercase().find(&list.filter.to_ascii_lowercase());
+        match start {
+            Some(start) if !list.filter.is_empty() => {
+                let end = start + list.filter.len();
+                spans.push(Span::styled(path[..start].to_string(), style));
+                spans.push(Span::styled(path[start..end].to_string(), style.fg(Color::Yellow)));
+                spans.push(Span::styled(path[end..].to_string(), style));
+            }
+            _ => spans.push(Span::styled(path.to_string(), style)),
+        }
+        let mut line = Line::from(spans);
+        if idx == list.selected {
+            line.spans.push(Span::raw(" ".repeat(width.saturating_sub(path.chars().count()))));
+            line = line.style(Style::new().add_modifier(Modifier::REVERSED));
+        }
+        lines.push(line);
+    }
+    f.render_widget(Paragraph::new(Text::from(lines)), inner);
+}
+
 fn render_status(f: &mut Frame, app: &App, area: ratatui::layout::Rect) {
+    // Failed searches get the reason in red and what to do about it
+    if let Some(err) = &app.search_error {
+        let mut spans = vec![Span::styled(format!(" Error: {err}"), Style::new().fg(Color::Red))];
+        if let Some(hint) = err.hint() {
+            spans.push(Span::styled(format!(" {hint}"), Style::new().fg(Color::DarkGray)));
+        }
+        f.render_widget(Paragraph::new(Line::from(spans)), area);
+        return;
+    }
+
     let provider_name = app.provider.name();
     let n = app.packages.len();
 
     let search_indicator = match app.search_state {
         SearchState::Searching => " Searching...",
+        SearchState::Partial => " Searching... (partial)",
         SearchState::Done => " Ready",
+        SearchState::Cancelled => " Cancelled",
         SearchState::Idle => "",
     };
 
+    let sort = Some(format!("sort: {}", app.sort_mode));
+    let context: String = [app.provider.status_context(), index_status(app), sort]
+        .into_iter()
+        .flatten()
+        .map(|ctx| format!(" │ {ctx}"))
+        .collect();
+
+    let filtered = match app.filtered_out() {
+        0 => String::new(),
+        hidden => format!(" ({hidden} filtered out)"),
+    };
+    let marked = match app.marked.len() {
+        0 => String::new(),
+        n => format!(", {n} marked"),
+    };
+
     let status_text = format!(
-        " provider: {provider_name} │ {n} results{search_indicator}"
+        " provider: {provider_name}{context} │ {n} results{filtered}{marked}{search_indicator}"
     );
 
     let style = match app.search_state {
-        SearchState::Searching => Style::new().fg(Color::Yellow),
+        SearchState::Searching | SearchState::Partial => Style::new().fg(Color::Yellow),
         SearchState::Done if !app.packages.is_empty() => Style::new().fg(Color::Green),
+        SearchState::Cancelled => Style::new().fg(Color::DarkGray),
         _ => Style::new(),
     };
 
@@ -180,6 +745,8 @@ fn render_status(f: &mut Frame, app: &App, area: ratatui::layout::Rect) {
     let msg = if app.status_message.starts_with("Error:")
         || app.status_message == "No results found."
         || app.status_message == "Start typing to search."
+        || app.status_message == "Search cancelled."
+        || app.status_message.starts_with("Exported ")
     {
         format!(" {}", app.status_message)
     } else {
@@ -190,10 +757,37 @@ fn render_status(f: &mut Frame, app: &App, area: ratatui::layout::Rect) {
     f.render_widget(para, area);
 }
 
+/// Age and refresh progress of the offline index.
+fn index_status(app: &App) -> Option<String> {
+    let age = app.index.as_ref().map(|index| format_age(index.age()));
+    match (&app.index_state, age) {
+        (IndexState::Refreshing, Some(age)) => Some(format!("index: {age}, refreshing...")),
+        (IndexState::Refreshing, None) => Some("index: building...".to_string()),
+        (IndexState::Failed, Some(age)) => Some(format!("index: {age}, refresh failed")),
+        (IndexState::Failed, None) => Some("index: build failed".to_string()),
+        (IndexState::Idle, age) => age.map(|age| format!("index: {age}")),
+    }
+}
+
+fn format_age(age: std::time::Duration) -> String {
+    let mins = age.as_secs() / 60;
+    match mins {
+        0 => "fresh".to_string(),
+        1..60 => format!("{mins}m old"),
+        60..1440 => format!("{}h old", mins / 60),
+        _ => format!("{}d old", mins / 1440),
+    }
+}
+
 fn render_search(f: &mut Frame, app: &App, area: ratatui::layout::Rect) {
-    let block = Block::default()
+    let mut block = Block::default()
         .borders(Borders::ALL)
-        .title(" Search ");
+        .title(if app.searching_files() { " Search files " } else { " Search " })
+        .title_bottom(filter_chips(app).right_aligned());
+    if let Some(hint) = &app.hint {
+        let hint = Span::styled(format!(" {hint} "), Style::new().fg(Color::Yellow));
+        block = block.title_bottom(Line::from(hint).left_aligned());
+    }
 
     let inner = block.inner(area);
     f.render_widget(block, area);
@@ -203,3 +797,19 @@ fn render_search(f: &mut Frame, app: &App, area: ratatui::layout::Rect) {
     let para = Paragraph::new(display).style(Style::new().add_modifier(Modifier::BOLD));
     f.render_widget(para, inner);
 }
+
+/// The active filters as chips: negated ones in red, unparseable ones struck out.
+fn filter_chips(app: &App) -> Line<'static> {
+    let mut spans = Vec::new();
+    for filter in &app.parsed.filters {
+        let bg = if filter.negated { Color::Red } else { Color::Blue };
+        spans.push(Span::styled(format!(" {filter} "), Style::new().fg(Color::White).bg(bg)));
+        spans.push(Span::raw(" "));
+    }
+    for invalid in &app.parsed.invalid {
+        let style = Style::new().fg(Color::Red).add_modifier(Modifier::CROSSED_OUT);
+        spans.push(Span::styled(format!(" {invalid} "), style));
+        spans.push(Span::raw(" "));
+    }
+    Line::from(spans)
+}
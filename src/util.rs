@@ -1,3 +1,18 @@
+use std::cell::RefCell;
+use std::cmp::Reverse;
+use std::collections::{HashMap, HashSet};
+use std::io::{BufRead, BufReader, Read};
+use std::os::unix::process::CommandExt;
+use std::path::PathBuf;
+use std::process::{Child, Command, Output, Stdio};
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::mpsc::{self, RecvTimeoutError};
+use std::sync::{Arc, Condvar, LazyLock, Mutex};
+use std::thread;
+use std::time::{Duration, Instant};
+
+use crate::error::SearchError;
+use crate::fuzzy::score_package;
 use crate::provider::Package;
 
 /// Escape shell special characters in a query string.
@@ -15,75 +30,607 @@ pub fn escape_query(query: &str) -> String {
     escaped
 }
 
-/// Execute a shell command and return its stdout.
-pub fn exec_command(cmd: &str) -> String {
-    std::process::Command::new("sh")
+/// Quote a string as a single shell word.
+pub fn shell_quote(s: &str) -> String {
+    format!("'{}'", s.replace('\'', "'\\''"))
+}
+
+/// Cancels the shell commands of a search, killing their process trees.
+///
+/// Commands run by `exec_command`/`exec_command_full` on a thread inside
+/// `with_cancel_token` are tied to that token: each runs in its own process
+/// group, which `cancel` kills, and no new commands start once cancelled.
+#[derive(Clone, Default)]
+pub struct CancelToken(Arc<CancelState>);
+
+#[derive(Default)]
+struct CancelState {
+    cancelled: AtomicBool,
+    timed_out: AtomicBool,
+    children: Mutex<Vec<u32>>,
+    finished: Mutex<bool>,
+    finished_cv: Condvar,
+}
+
+impl CancelToken {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn cancel(&self) {
+        self.0.cancelled.store(true, Ordering::SeqCst);
+        let children = self.0.children.lock().map(|c| c.clone()).unwrap_or_default();
+        for pid in children {
+            kill_process_group(pid);
+        }
+    }
+
+    pub fn is_cancelled(&self) -> bool {
+        self.0.cancelled.load(Ordering::SeqCst)
+    }
+
+    /// Cancel automatically if `finish` has not been called within `timeout`.
+    pub fn set_timeout(&self, timeout: Duration) {
+        let token = self.clone();
+        thread::spawn(move || {
+            let Ok(finished) = token.0.finished.lock() else { return };
+            let Ok((finished, _)) =
+                token.0.finished_cv.wait_timeout_while(finished, timeout, |done| !*done)
+            else {
+                return;
+            };
+            if !*finished {
+                token.0.timed_out.store(true, Ordering::SeqCst);
+                token.cancel();
+            }
+        });
+    }
+
+    /// Mark the work as complete, stopping any pending timeout.
+    pub fn finish(&self) {
+        if let Ok(mut finished) = self.0.finished.lock() {
+            *finished = true;
+        }
+        self.0.finished_cv.notify_all();
+    }
+
+    /// Whether the token was cancelled by its timeout rather than explicitly.
+    pub fn timed_out(&self) -> bool {
+        self.0.timed_out.load(Ordering::SeqCst)
+    }
+
+    fn register(&self, pid: u32) {
+        if let Ok(mut children) = self.0.children.lock() {
+            children.push(pid);
+        }
+        // Cancelled between spawn and registration
+        if self.is_cancelled() {
+            kill_process_group(pid);
+        }
+    }
+
+    fn unregister(&self, pid: u32) {
+        if let Ok(mut children) = self.0.children.lock() {
+            children.retain(|&p| p != pid);
+        }
+    }
+}
+
+thread_local! {
+    static CANCEL_TOKEN: RefCell<Option<CancelToken>> = const { RefCell::new(None) };
+}
+
+/// Whether this thread's cancel token (see `with_cancel_token`) has fired.
+fn is_cancelled() -> bool {
+    CANCEL_TOKEN.with(|t| t.borrow().as_ref().is_some_and(CancelToken::is_cancelled))
+}
+
+/// Run `f` with every shell command it executes on this thread tied to `token`.
+pub fn with_cancel_token<R>(token: &CancelToken, f: impl FnOnce() -> R) -> R {
+    CANCEL_TOKEN.with(|t| *t.borrow_mut() = Some(token.clone()));
+    let result = f();
+    CANCEL_TOKEN.with(|t| *t.borrow_mut() = None);
+    result
+}
+
+fn kill_process_group(pid: u32) {
+    Command::new("kill")
+        .args(["-KILL", "--", &format!("-{pid}")])
+        .stderr(Stdio::null())
+        .status()
+        .ok();
+}
+
+type BatchSink = Box<dyn FnMut(&[Package])>;
+
+thread_local! {
+    static BATCH_SINK: RefCell<Option<BatchSink>> = const { RefCell::new(None) };
+}
+
+/// Run `f` with the partial results of streaming searches on this thread
+/// passed to `sink` as they are parsed. The final `SearchResult` still holds
+/// every package, so callers that don't care about batches can ignore this.
+pub fn with_batch_sink<R>(sink: impl FnMut(&[Package]) + 'static, f: impl FnOnce() -> R) -> R {
+    BATCH_SINK.with(|s| *s.borrow_mut() = Some(Box::new(sink)));
+    let result = f();
+    BATCH_SINK.with(|s| *s.borrow_mut() = None);
+    result
+}
+
+fn emit_batch(batch: &[Package]) {
+    BATCH_SINK.with(|s| {
+        if let Some(sink) = s.borrow_mut().as_mut() {
+            sink(batch);
+        }
+    });
+}
+
+/// Spawn `sh -c cmd` in its own process group, tied to this thread's cancel
+/// token. Returns `None` if it could not be started or was already cancelled.
+fn spawn_shell(cmd: &str) -> Option<(Child, Option<CancelToken>)> {
+    let token = CANCEL_TOKEN.with(|t| t.borrow().clone());
+    if token.as_ref().is_some_and(CancelToken::is_cancelled) {
+        return None;
+    }
+
+    let child = Command::new("sh")
         .arg("-c")
         .arg(cmd)
-        .output()
+        .stdin(Stdio::null())
+        .stdout(Stdio::piped())
+        .stderr(Stdio::piped())
+        .process_group(0)
+        .spawn()
+        .ok()?;
+
+    if let Some(token) = &token {
+        token.register(child.id());
+    }
+    Some((child, token))
+}
+
+/// Detach a finished child from its token. Returns false if it was cancelled.
+fn release(token: Option<CancelToken>, pid: u32) -> bool {
+    match token {
+        Some(token) => {
+            token.unregister(pid);
+            !token.is_cancelled()
+        }
+        None => true,
+    }
+}
+
+/// Run `sh -c cmd` in its own process group and capture its output.
+/// Returns `None` if the command could not be started or was cancelled.
+fn run_shell(cmd: &str) -> Option<Output> {
+    let (child, token) = spawn_shell(cmd)?;
+    let pid = child.id();
+    let output = child.wait_with_output().ok();
+    if !release(token, pid) {
+        return None;
+    }
+    output
+}
+
+/// Execute a shell command and return its stdout.
+pub fn exec_command(cmd: &str) -> String {
+    run_shell(cmd)
         .map(|o| String::from_utf8_lossy(&o.stdout).into_owned())
         .unwrap_or_default()
 }
 
 /// Execute a shell command and return (stdout, stderr, exit_code).
 pub fn exec_command_full(cmd: &str) -> (String, String, i32) {
-    match std::process::Command::new("sh")
-        .arg("-c")
-        .arg(cmd)
-        .output()
-    {
-        Ok(output) => (
+    match run_shell(cmd) {
+        Some(output) => (
             String::from_utf8_lossy(&output.stdout).into_owned(),
             String::from_utf8_lossy(&output.stderr).into_owned(),
             output.status.code().unwrap_or(-1),
         ),
-        Err(_) => (String::new(), String::new(), -1),
+        None => (String::new(), String::new(), -1),
+    }
+}
+
+/// Run a provider's search command and return its stdout.
+///
+/// Package managers disagree on how "nothing found" is reported: a non-zero
+/// exit with empty output, or a message matching one of `no_match`. Both are
+/// treated as an empty result; any other failure is classified from stderr.
+pub fn exec_search(cmd: &str, no_match: &[&str]) -> Result<String, SearchError> {
+    let (stdout, stderr, code) = exec_command_full(cmd);
+    let no_results = no_match.iter().any(|m| stdout.contains(m) || stderr.contains(m));
+    if search_succeeded(cmd, code, !stdout.trim().is_empty(), no_results, &stderr)? {
+        Ok(stdout)
+    } else {
+        Ok(String::new())
+    }
+}
+
+/// How often `exec_search_lines` hands parsed packages to the batch sink.
+const BATCH_INTERVAL: Duration = Duration::from_millis(100);
+
+/// Like `exec_search`, but parses stdout a line at a time while the command
+/// runs, passing new packages to the batch sink (see `with_batch_sink`) every
+/// `BATCH_INTERVAL`. Returns every package parsed.
+pub fn exec_search_lines(
+    cmd: &str,
+    no_match: &[&str],
+    mut parse_line: impl FnMut(&str) -> Option<Package>,
+) -> Result<Vec<Package>, SearchError> {
+    let Some((mut child, token)) = spawn_shell(cmd) else {
+        return Ok(Vec::new());
+    };
+    let pid = child.id();
+
+    // Read both pipes on their own threads so neither can fill up and block
+    let stderr = child.stderr.take().map(|mut pipe| {
+        thread::spawn(move || {
+            let mut buf = Vec::new();
+            pipe.read_to_end(&mut buf).ok();
+            String::from_utf8_lossy(&buf).into_owned()
+        })
+    });
+    let (line_tx, line_rx) = mpsc::channel();
+    let stdout = child.stdout.take();
+    thread::spawn(move || {
+        let Some(pipe) = stdout else { return };
+        let mut reader = BufReader::new(pipe);
+        let mut buf = Vec::new();
+        while matches!(reader.read_until(b'\n', &mut buf), Ok(n) if n > 0) {
+            let line = String::from_utf8_lossy(&buf);
+            if line_tx.send(line.trim_end_matches(['\n', '\r']).to_string()).is_err() {
+                break;
+            }
+            buf.clear();
+        }
+    });
+
+    let mut packages = Vec::new();
+    let mut pending = Vec::new();
+    let mut has_output = false;
+    let mut no_results = false;
+    let mut last_batch = Instant::now();
+    loop {
+        match line_rx.recv_timeout(BATCH_INTERVAL) {
+            Ok(line) => {
+                has_output |= !line.trim().is_empty();
+                no_results |= no_match.iter().any(|m| line.contains(m));
+                pending.extend(parse_line(&line));
+            }
+            Err(RecvTimeoutError::Timeout) => {}
+            Err(RecvTimeoutError::Disconnected) => break,
+        }
+        if !pending.is_empty() && last_batch.elapsed() >= BATCH_INTERVAL {
+            emit_batch(&pending);
+            packages.append(&mut pending);
+            last_batch = Instant::now();
+        }
+    }
+    packages.append(&mut pending);
+
+    let code = child.wait().ok().and_then(|status| status.code()).unwrap_or(-1);
+    let stderr = stderr.and_then(|t| t.join().ok()).unwrap_or_default();
+    if !release(token, pid) {
+        return Ok(Vec::new());
+    }
+    no_results |= no_match.iter().any(|m| stderr.contains(m));
+    if search_succeeded(cmd, code, has_output, no_results, &stderr)? {
+        Ok(packages)
+    } else {
+        Ok(Vec::new())
+    }
+}
+
+/// Judge a finished search command. `Ok(false)` means it reported that
+/// nothing matched, so its output should be ignored.
+fn search_succeeded(
+    cmd: &str,
+    code: i32,
+    has_output: bool,
+    no_results: bool,
+    stderr: &str,
+) -> Result<bool, SearchError> {
+    // A cancelled search has no results; the caller already knows why
+    if is_cancelled() {
+        return Ok(false);
+    }
+    if code == 0 {
+        return Ok(true);
+    }
+    if no_results {
+        return Ok(false);
+    }
+    // -1 means it was killed by a signal or could not be started
+    if code != 127 && code != -1 && (has_output || stderr.trim().is_empty()) {
+        return Ok(true);
+    }
+    Err(SearchError::from_command(cmd, code, stderr))
+}
+
+static INSTALLED_CACHE: LazyLock<Mutex<HashMap<String, Arc<HashSet<String>>>>> =
+    LazyLock::new(Mutex::default);
+
+/// The installed-package set cached under `key`, listed with `list` on first
+/// use. Listing is slow for most package managers, so the set is kept for the
+/// rest of the process until `invalidate_installed` is called.
+pub fn cached_installed(
+    key: &str,
+    list: impl FnOnce() -> HashSet<String>,
+) -> Arc<HashSet<String>> {
+    if let Some(set) = INSTALLED_CACHE.lock().ok().and_then(|cache| cache.get(key).cloned()) {
+        return set;
+    }
+    let set = Arc::new(list());
+    // A listing cut short by cancellation is incomplete; don't keep it
+    if !is_cancelled() && let Ok(mut cache) = INSTALLED_CACHE.lock() {
+        cache.insert(key.to_string(), Arc::clone(&set));
+    }
+    set
+}
+
+/// Forget all cached installed sets, e.g. after installing or removing a package.
+pub fn invalidate_installed() {
+    if let Ok(mut cache) = INSTALLED_CACHE.lock() {
+        cache.clear();
     }
 }
 
 /// Check if a command exists on the system.
 pub fn command_exists(cmd: &str) -> bool {
-    std::process::Command::new("sh")
+    Command::new("sh")
         .args(["-c", &format!("command -v {cmd} > /dev/null 2>&1")])
         .status()
         .map(|s| s.success())
         .unwrap_or(false)
 }
 
-/// Sort packages by relevance to the query.
-/// Priority: exact match > starts with > contains (shorter first) > alphabetical.
-pub fn sort_by_relevance(packages: &mut Vec<Package>, query: &str) {
-    let query_lower = query.to_lowercase();
-    packages.sort_by(|a, b| {
-        let a_lower = a.name.to_lowercase();
-        let b_lower = b.name.to_lowercase();
-
-        // Exact match gets highest priority
-        let a_exact = a_lower == query_lower;
-        let b_exact = b_lower == query_lower;
-        if a_exact != b_exact {
-            return b_exact.cmp(&a_exact);
-        }
+/// Resolve an XDG base directory, falling back to `$HOME/<fallback>`.
+fn xdg_dir(var: &str, fallback: &str) -> PathBuf {
+    match std::env::var_os(var) {
+        Some(dir) if !dir.is_empty() => PathBuf::from(dir),
+        _ => home_dir().join(fallback),
+    }
+}
 
-        // Starts with query gets next priority
-        let a_starts = a_lower.starts_with(&query_lower);
-        let b_starts = b_lower.starts_with(&query_lower);
-        if a_starts != b_starts {
-            return b_starts.cmp(&a_starts);
-        }
+/// The user's home directory.
+pub fn home_dir() -> PathBuf {
+    std::env::var_os("HOME").map(PathBuf::from).unwrap_or_else(|| PathBuf::from("."))
+}
 
-        // Contains query (shorter names preferred)
-        let a_contains = a_lower.contains(&query_lower);
-        let b_contains = b_lower.contains(&query_lower);
-        if a_contains != b_contains {
-            return b_contains.cmp(&a_contains);
-        }
+/// fex's cache directory (`$XDG_CACHE_HOME/fex`).
+pub fn cache_dir() -> PathBuf {
+    xdg_dir("XDG_CACHE_HOME", ".cache").join("fex")
+}
+
+/// fex's config directory (`$XDG_CONFIG_HOME/fex`).
+pub fn config_dir() -> PathBuf {
+    xdg_dir("XDG_CONFIG_HOME", ".config").join("fex")
+}
+
+/// fex's data directory (`$XDG_DATA_HOME/fex`).
+pub fn data_dir() -> PathBuf {
+    xdg_dir("XDG_DATA_HOME", ".local/share").join("fex")
+}
+
+/// fex's state directory (`$XDG_STATE_HOME/fex`).
+pub fn state_dir() -> PathBuf {
+    xdg_dir("XDG_STATE_HOME", ".local/state").join("fex")
+}
+
+/// The user's applications directory for desktop entries.
+pub fn applications_dir() -> PathBuf {
+    xdg_dir("XDG_DATA_HOME", ".local/share").join("applications")
+}
+
+/// Parse a size as printed by package managers, such as "65 M", "1,234 kB",
+/// "4.5 MiB" or "0 B", into bytes. SI units such as "kB" and "MB" (apt,
+/// flatpak) are powers of 1000; "KiB" and bare prefixes such as "M" (dnf)
+/// are powers of 1024.
+pub fn parse_size(text: &str) -> Option<u64> {
+    let text = text.trim().replace(',', "");
+    let split = text.find(|c: char| !c.is_ascii_digit() && c != '.').unwrap_or(text.len());
+    let (number, unit) = text.split_at(split);
+    let number: f64 = number.parse().ok()?;
+    let unit = unit.trim();
+    let base: f64 = if unit.len() == 2 && unit.ends_with('B') { 1000.0 } else { 1024.0 };
+    let power = match unit.chars().next().map(|c| c.to_ascii_lowercase()) {
+        None | Some('b') => 0,
+        Some('k') => 1,
+        Some('m') => 2,
+        Some('g') => 3,
+        Some('t') => 4,
+        _ => return None,
+    };
+    Some((number * base.powi(power)) as u64)
+}
+
+/// Human-readable size, e.g. "4.5 MiB".
+pub fn format_size(bytes: u64) -> String {
+    const UNITS: [&str; 5] = ["B", "KiB", "MiB", "GiB", "TiB"];
+    let mut size = bytes as f64;
+    let mut unit = 0;
+    while size >= 1024.0 && unit < UNITS.len() - 1 {
+        size /= 1024.0;
+        unit += 1;
+    }
+    if unit == 0 { format!("{bytes} B") } else { format!("{size:.1} {}", UNITS[unit]) }
+}
 
-        if a_contains && b_contains {
-            return a.name.len().cmp(&b.name.len());
+/// Merge the (package, path) matches of a file search into one package per
+/// name and source, with its paths in `files`, in the order first seen.
+pub fn group_file_matches(matches: impl IntoIterator<Item = (Package, String)>) -> Vec<Package> {
+    let mut packages: Vec<Package> = Vec::new();
+    for (pkg, path) in matches {
+        match packages.iter_mut().find(|p| p.name == pkg.name && p.source == pkg.source) {
+            Some(known) if known.files.contains(&path) => {}
+            Some(known) => known.files.push(path),
+            None => packages.push(Package { files: vec![path], ..pkg }),
         }
+    }
+    packages
+}
 
-        // Alphabetical fallback
-        a_lower.cmp(&b_lower)
+/// The paths of a package file list, one per line, made absolute. Directories
+/// are left out: those ending in '/' and those holding other listed paths
+/// (dpkg and rpm list both without marking them).
+pub fn parse_file_list<'a>(lines: impl IntoIterator<Item = &'a str>) -> Vec<String> {
+    let paths: Vec<String> = lines
+        .into_iter()
+        // dpkg lists the root as "/."
+        .map(|line| line.trim().trim_end_matches("/."))
+        .filter(|line| !line.is_empty() && !line.ends_with('/'))
+        .map(|line| if line.starts_with('/') { line.to_string() } else { format!("/{line}") })
+        .collect();
+    let parents: HashSet<&str> = paths
+        .iter()
+        .flat_map(|path| path.match_indices('/').filter(|(i, _)| *i > 0).map(|(i, _)| &path[..i]))
+        .collect();
+    paths.iter().filter(|path| !parents.contains(path.as_str())).cloned().collect()
+}
+
+/// Sort packages by relevance to the query.
+/// Priority: exact match > starts with > contains > fuzzy score (see
+/// `crate::fuzzy`), then shorter names, then alphabetical.
+pub fn sort_by_relevance(packages: &mut [Package], query: &str) {
+    let query_lower = query.to_lowercase();
+    packages.sort_by_cached_key(|pkg| {
+        relevance_key(pkg, &query_lower, score_package(pkg, query).unwrap_or(i32::MIN))
     });
 }
+
+/// The `sort_by_relevance` key of `pkg`, for callers that already have its
+/// fuzzy `score`.
+pub(crate) fn relevance_key(
+    pkg: &Package,
+    query_lower: &str,
+    score: i32,
+) -> (Reverse<u8>, Reverse<i32>, usize, String) {
+    let name = pkg.name.to_lowercase();
+    let tier = if name == query_lower {
+        3
+    } else if name.starts_with(query_lower) {
+        2
+    } else if name.contains(query_lower) {
+        1
+    } else {
+        0
+    };
+    (Reverse(tier), Reverse(score), name.len(), name)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::time::Instant;
+
+    #[test]
+    fn parses_and_formats_sizes() {
+        assert_eq!(parse_size("65 M"), Some(65 * 1024 * 1024));
+        assert_eq!(parse_size("1,234 kB"), Some(1234 * 1000));
+        assert_eq!(parse_size("2.5 MB"), Some(2_500_000));
+        assert_eq!(parse_size("4.5 MiB"), Some(4718592));
+        assert_eq!(parse_size("0 B"), Some(0));
+        assert_eq!(parse_size("123456"), Some(123456));
+        assert_eq!(parse_size("lots"), None);
+        assert_eq!(format_size(512), "512 B");
+        assert_eq!(format_size(4718592), "4.5 MiB");
+    }
+
+    #[test]
+    fn parses_file_lists_without_directories() {
+        let output = "usr/\nusr/bin/\nusr/bin/fex\n/etc/fex.conf\n/\n";
+        let files = parse_file_list(output.lines());
+        assert_eq!(files, ["/usr/bin/fex", "/etc/fex.conf"]);
+        // As dpkg -L lists them, with unmarked directories
+        let output = "/.\n/opt\n/opt/fex\n/opt/fex/fex.1\n/opt/fex/doc\n/opt/fex/doc/README\n";
+        let files = parse_file_list(output.lines());
+        assert_eq!(files, ["/opt/fex/fex.1", "/opt/fex/doc/README"]);
+    }
+
+    #[test]
+    fn cancel_kills_running_command_tree() {
+        let token = CancelToken::new();
+        let canceller = token.clone();
+        std::thread::spawn(move || {
+            std::thread::sleep(Duration::from_millis(200));
+            canceller.cancel();
+        });
+
+        let start = Instant::now();
+        let (stdout, _, code) =
+            with_cancel_token(&token, || exec_command_full("sleep 30; echo done"));
+        assert!(start.elapsed() < Duration::from_secs(10));
+        assert_eq!(stdout, "");
+        assert_eq!(code, -1);
+
+        // Nothing new starts once cancelled
+        assert_eq!(with_cancel_token(&token, || exec_command("echo hi")), "");
+        assert!(!token.timed_out());
+    }
+
+    #[test]
+    fn timeout_cancels_unfinished_work_only() {
+        let slow = CancelToken::new();
+        slow.set_timeout(Duration::from_millis(100));
+        let (stdout, _, _) = with_cancel_token(&slow, || exec_command_full("sleep 30"));
+        assert_eq!(stdout, "");
+        assert!(slow.timed_out());
+
+        let fast = CancelToken::new();
+        fast.set_timeout(Duration::from_millis(100));
+        assert_eq!(with_cancel_token(&fast, || exec_command("echo hi")), "hi\n");
+        fast.finish();
+        std::thread::sleep(Duration::from_millis(200));
+        assert!(!fast.is_cancelled());
+    }
+
+    #[test]
+    fn exec_search_separates_no_results_from_failures() {
+        assert_eq!(exec_search("echo pkg; exit 1", &[]).unwrap(), "pkg\n");
+        assert_eq!(exec_search("exit 1", &[]).unwrap(), "");
+        assert_eq!(exec_search("echo 'No matches' >&2; exit 1", &["No matches"]).unwrap(), "");
+        assert_eq!(
+            exec_search("echo 'error: could not lock database' >&2; exit 1", &[]),
+            Err(SearchError::LockedDatabase)
+        );
+        assert!(matches!(
+            exec_search("fex-no-such-command", &[]),
+            Err(SearchError::ProviderMissing(_))
+        ));
+    }
+
+    #[test]
+    fn installed_sets_are_cached_until_invalidated() {
+        let calls = std::cell::Cell::new(0);
+        let list = || {
+            calls.set(calls.get() + 1);
+            HashSet::from(["vim".to_string()])
+        };
+        assert!(cached_installed("test-cache", list).contains("vim"));
+        assert!(cached_installed("test-cache", list).contains("vim"));
+        assert_eq!(calls.get(), 1);
+        invalidate_installed();
+        cached_installed("test-cache", list);
+        assert_eq!(calls.get(), 2);
+    }
+
+    #[test]
+    fn exec_search_lines_streams_batches() {
+        let batches = Arc::new(Mutex::new(Vec::new()));
+        let sink = Arc::clone(&batches);
+        let parse = |line: &str| {
+            Some(Package {
+                name: line.to_string(),
+                ..Default::default()
+            })
+        };
+        let packages = with_batch_sink(
+            move |batch: &[Package]| sink.lock().unwrap().push(batch.len()),
+            || exec_search_lines("echo a; echo b; sleep 0.5; echo c", &[], parse),
+        )
+        .unwrap();
+        assert_eq!(packages.len(), 3);
+        // The first two arrive while the command is still running
+        assert_eq!(batches.lock().unwrap().first(), Some(&2));
+    }
+}